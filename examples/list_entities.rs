@@ -0,0 +1,38 @@
+use std::{collections::BTreeMap, env};
+
+use streamdeck_homeassistant::{config::{self, HomeAssistantConfig}, PersistentHassConnection};
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Load the configuration
+    let config: HomeAssistantConfig = config::load_config("config.yaml")?;
+
+    let hass = PersistentHassConnection::new(
+        config.url.clone(),
+        env::var("HASS_API_TOKEN").map_err(|err| {
+            format!("Failed to get HASS_API_TOKEN from environment: {}", err)
+        })?,
+        std::time::Duration::from_secs(5),
+    ).await?;
+    hass.fetch_states().await?;
+
+    // Group entities by domain
+    let mut domains: BTreeMap<String, Vec<(String, Option<String>)>> = BTreeMap::new();
+    for (entity_id, friendly_name) in hass.list_entities().await {
+        let domain = entity_id.split('.').next().unwrap_or_default().to_string();
+        domains.entry(domain).or_default().push((entity_id, friendly_name));
+    }
+
+    for (domain, entities) in domains {
+        println!("# {}", domain);
+        for (entity_id, friendly_name) in entities {
+            match friendly_name {
+                Some(friendly_name) => println!("{}  # {}", entity_id, friendly_name),
+                None => println!("{}", entity_id),
+            }
+        }
+        println!();
+    }
+
+    Ok(())
+}
@@ -2,22 +2,23 @@ use std::{any::{Any, TypeId}, collections::BTreeMap, env, sync::Arc};
 
 use streamdeck_homeassistant::{
     config::{self, HomeAssistantConfig},
-    plugins::{self, HomeAssistantPlugin},
+    deck,
     PersistentHassConnection
 };
-use streamdeck_oxide::{elgato_streamdeck, generic_array::typenum::{U3, U5}, plugins::{Plugin, PluginContext, PluginNavigation}, run_with_external_triggers, ExternalTrigger, RenderConfig, Theme};
+use streamdeck_oxide::{elgato_streamdeck, plugins::PluginContext};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Load the configuration
     let config: HomeAssistantConfig = config::load_config("config.yaml")?;
+    let render_config = config.render_config()?;
     let config = Arc::new(config);
 
     let hid = elgato_streamdeck::new_hidapi()?;
     let devices = elgato_streamdeck::list_devices(&hid);
     let (kind, serial) = devices
         .into_iter()
-        .find(|(kind, _)| *kind == elgato_streamdeck::info::Kind::Mk2)
+        .find(|(kind, _)| *kind != elgato_streamdeck::info::Kind::Pedal)
         .ok_or("No Stream Deck found")?;
 
     println!("Found Stream Deck: {:?} ({})", kind, serial);
@@ -27,33 +28,43 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     )?);
     println!("Connected to Stream Deck successfully!");
 
-    let hass = PersistentHassConnection::new(
+    let hass = match PersistentHassConnection::builder(
         config.url.clone(),
         env::var("HASS_API_TOKEN").map_err(|err| {
             format!("Failed to get HASS_API_TOKEN from environment: {}", err)
         })?,
-        std::time::Duration::from_secs(5),
-    ).await?;
+    )
+    .turn_off_on_close(config.turn_off_on_exit.clone())
+    .entity_filter(config.only_cache_used_entities.then(|| config.entity_filter()))
+    .warm_up(true)
+    .build()
+    .await
+    {
+        Ok(hass) => hass,
+        Err(e) => {
+            // Say what's wrong on the deck itself until the user gives up
+            eprintln!("Failed to connect to HomeAssistant: {}", e);
+            tokio::select! {
+                result = deck::show_error(kind, config.theme.to_theme(), render_config, deck, e.as_ref()) => result?,
+                _ = tokio::signal::ctrl_c() => println!("Shutting down"),
+            }
+            return Err(e);
+        }
+    };
 
     let context = PluginContext::new(
         BTreeMap::from([
             (TypeId::of::<HomeAssistantConfig>(), Box::new(config.clone()) as Box<dyn Any + Send + Sync>),
-            (TypeId::of::<PersistentHassConnection>(), Box::new(hass) as Box<dyn Any + Send + Sync>),
+            (TypeId::of::<PersistentHassConnection>(), Box::new(hass.clone()) as Box<dyn Any + Send + Sync>),
         ]),
     );
 
-    let (sender, receiver) = tokio::sync::mpsc::channel::<ExternalTrigger<PluginNavigation<U5, U3>, U5, U3, PluginContext>>(1);
-
-    let default_menu = config.menu.clone();
-    sender.send(ExternalTrigger::new(
-        PluginNavigation::<U5, U3>::new(plugins::HomeAssistantPlugin {
-            menu: default_menu,
-            back_navigation: None,
-        }),
-        true
-    )).await?;
-
-    run_with_external_triggers(Theme::light(), RenderConfig::default(), deck, context, receiver).await?;
+    tokio::select! {
+        result = deck::run(kind, config.theme.to_theme(), render_config, deck, context, config.menu.clone()) => result?,
+        _ = tokio::signal::ctrl_c() => println!("Shutting down"),
+    }
+    // Turns off the entities listed in turn_off_on_exit
+    hass.close().await?;
 
     Ok(())
 }
@@ -0,0 +1,227 @@
+//! Abstraction over the connection used by the buttons.
+//!
+//! Buttons talk to HomeAssistant through the `HassBackend` trait, so a
+//! configuration can be rendered against a `MockHassConnection` instead
+//! of a live instance.
+
+use std::{future::Future, pin::Pin, sync::Arc, time::Duration};
+
+use hass_rs::HassEntity;
+
+use crate::hass::{HassError, PersistentHassConnection};
+
+/// A future returned by `HassBackend` methods.
+///
+/// Button actions must be `Sync`, which rules out `async_trait`, so the
+/// futures are boxed by hand.
+pub type BackendFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + Sync + 'a>>;
+
+/// The operations buttons need from a HomeAssistant connection.
+pub trait HassBackend: Send + Sync {
+    /// Gets the cached state of an entity.
+    fn get_state<'a>(&'a self, entity_id: &'a str) -> BackendFuture<'a, Option<HassEntity>>;
+
+    /// Returns whether any states have been cached yet.
+    fn has_states(&self) -> BackendFuture<'_, bool>;
+
+    /// Lists all cached entities with their friendly names, sorted by entity ID.
+    fn list_entities(&self) -> BackendFuture<'_, Vec<(String, Option<String>)>>;
+
+    /// Lists the entities assigned to an area, sorted by entity ID.
+    fn area_entities<'a>(&'a self, area_id: &'a str)
+        -> BackendFuture<'a, Result<Vec<String>, HassError>>;
+
+    /// Refreshes the cached states.
+    fn fetch_states(&self) -> BackendFuture<'_, Result<(), String>>;
+
+    /// Refreshes the cached states of some entities, e.g. lazy ones left out of `fetch_states`.
+    fn refresh_entities<'a>(
+        &'a self,
+        entity_ids: &'a [String],
+    ) -> BackendFuture<'a, Result<(), HassError>>;
+
+    /// Gets the temperature unit configured in HomeAssistant, e.g. "°C".
+    fn temperature_unit(&self) -> BackendFuture<'_, Option<String>>;
+
+    /// Calls a HomeAssistant service.
+    fn call_service<'a>(
+        &'a self,
+        domain: &'a str,
+        service: &'a str,
+        data: Option<serde_json::Value>,
+    ) -> BackendFuture<'a, Result<(), Box<dyn std::error::Error>>>;
+
+    /// Calls a HomeAssistant service on several entities with a single message.
+    ///
+    /// HomeAssistant applies the service to all entities at once, instead of
+    /// one after the other as with separate calls.
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - The domain of the service
+    /// * `service` - The service to call
+    /// * `entity_ids` - The entities to target
+    /// * `data` - Optional further service data, as a JSON object
+    fn call_service_batch<'a>(
+        &'a self,
+        domain: &'a str,
+        service: &'a str,
+        entity_ids: &[String],
+        data: Option<serde_json::Value>,
+    ) -> BackendFuture<'a, Result<(), Box<dyn std::error::Error>>> {
+        let mut data = data.unwrap_or_else(|| serde_json::json!({}));
+        let Some(fields) = data.as_object_mut() else {
+            return Box::pin(async { Err("Service data must be a JSON object".into()) });
+        };
+        fields.insert("entity_id".to_string(), serde_json::json!(entity_ids));
+        self.call_service(domain, service, Some(data))
+    }
+
+    /// Fires a custom event on the HomeAssistant event bus.
+    fn fire_event<'a>(
+        &'a self,
+        event_type: &'a str,
+        event_data: Option<serde_json::Value>,
+    ) -> BackendFuture<'a, Result<(), HassError>>;
+
+    /// Renders a Jinja template in HomeAssistant.
+    fn render_template<'a>(
+        &'a self,
+        template: &'a str,
+    ) -> BackendFuture<'a, Result<String, HassError>>;
+
+    /// Renders a Jinja template, reusing a rendering younger than `max_age`.
+    fn render_template_cached<'a>(
+        &'a self,
+        template: &'a str,
+        _max_age: Duration,
+    ) -> BackendFuture<'a, Result<String, HassError>> {
+        self.render_template(template)
+    }
+
+    /// Returns whether the connection to HomeAssistant is currently up.
+    fn is_connected(&self) -> bool;
+
+    /// Reconnects and refreshes all states.
+    fn reconnect(&self) -> BackendFuture<'_, Result<(), Box<dyn std::error::Error>>>;
+
+    /// Records a button press and decides whether to act on it.
+    fn accept_press(&self, key: &str) -> bool;
+
+    /// Blocks presses of a button for a while, replacing any running cooldown.
+    fn start_cooldown(&self, key: &str, duration: Duration);
+
+    /// Returns how much of a button's cooldown is left, or None if it isn't cooling down.
+    fn cooldown_remaining(&self, key: &str) -> Option<Duration>;
+
+    /// Schedules an entity to be turned off after a delay, replacing any running timer.
+    fn start_off_timer(self: Arc<Self>, domain: &str, entity_id: &str, delay: Duration);
+
+    /// Cancels the off timer of an entity, returning whether one was running.
+    fn cancel_off_timer(&self, entity_id: &str) -> bool;
+
+    /// Returns whether an off timer is running for an entity.
+    fn has_off_timer(&self, entity_id: &str) -> bool;
+}
+
+impl HassBackend for PersistentHassConnection {
+    fn get_state<'a>(&'a self, entity_id: &'a str) -> BackendFuture<'a, Option<HassEntity>> {
+        Box::pin(PersistentHassConnection::get_state(self, entity_id))
+    }
+
+    fn has_states(&self) -> BackendFuture<'_, bool> {
+        Box::pin(PersistentHassConnection::has_states(self))
+    }
+
+    fn list_entities(&self) -> BackendFuture<'_, Vec<(String, Option<String>)>> {
+        Box::pin(PersistentHassConnection::list_entities(self))
+    }
+
+    fn area_entities<'a>(
+        &'a self,
+        area_id: &'a str,
+    ) -> BackendFuture<'a, Result<Vec<String>, HassError>> {
+        Box::pin(PersistentHassConnection::area_entities(self, area_id))
+    }
+
+    fn fetch_states(&self) -> BackendFuture<'_, Result<(), String>> {
+        Box::pin(PersistentHassConnection::fetch_states(self))
+    }
+
+    fn refresh_entities<'a>(
+        &'a self,
+        entity_ids: &'a [String],
+    ) -> BackendFuture<'a, Result<(), HassError>> {
+        Box::pin(PersistentHassConnection::refresh_entities(self, entity_ids))
+    }
+
+    fn temperature_unit(&self) -> BackendFuture<'_, Option<String>> {
+        Box::pin(PersistentHassConnection::temperature_unit(self))
+    }
+
+    fn call_service<'a>(
+        &'a self,
+        domain: &'a str,
+        service: &'a str,
+        data: Option<serde_json::Value>,
+    ) -> BackendFuture<'a, Result<(), Box<dyn std::error::Error>>> {
+        Box::pin(PersistentHassConnection::call_service(self, domain, service, data))
+    }
+
+    fn fire_event<'a>(
+        &'a self,
+        event_type: &'a str,
+        event_data: Option<serde_json::Value>,
+    ) -> BackendFuture<'a, Result<(), HassError>> {
+        Box::pin(PersistentHassConnection::fire_event(self, event_type, event_data))
+    }
+
+    fn render_template<'a>(
+        &'a self,
+        template: &'a str,
+    ) -> BackendFuture<'a, Result<String, HassError>> {
+        Box::pin(PersistentHassConnection::render_template(self, template))
+    }
+
+    fn render_template_cached<'a>(
+        &'a self,
+        template: &'a str,
+        max_age: Duration,
+    ) -> BackendFuture<'a, Result<String, HassError>> {
+        Box::pin(PersistentHassConnection::render_template_cached(
+            self, template, max_age,
+        ))
+    }
+
+    fn is_connected(&self) -> bool {
+        PersistentHassConnection::is_connected(self)
+    }
+
+    fn reconnect(&self) -> BackendFuture<'_, Result<(), Box<dyn std::error::Error>>> {
+        Box::pin(PersistentHassConnection::reconnect(self))
+    }
+
+    fn accept_press(&self, key: &str) -> bool {
+        PersistentHassConnection::accept_press(self, key)
+    }
+
+    fn start_cooldown(&self, key: &str, duration: Duration) {
+        PersistentHassConnection::start_cooldown(self, key, duration)
+    }
+
+    fn cooldown_remaining(&self, key: &str) -> Option<Duration> {
+        PersistentHassConnection::cooldown_remaining(self, key)
+    }
+
+    fn start_off_timer(self: Arc<Self>, domain: &str, entity_id: &str, delay: Duration) {
+        PersistentHassConnection::start_off_timer(&self, domain, entity_id, delay)
+    }
+
+    fn cancel_off_timer(&self, entity_id: &str) -> bool {
+        PersistentHassConnection::cancel_off_timer(self, entity_id)
+    }
+
+    fn has_off_timer(&self, entity_id: &str) -> bool {
+        PersistentHassConnection::has_off_timer(self, entity_id)
+    }
+}
@@ -0,0 +1,789 @@
+//! Custom buttons for the StreamDeck HomeAssistant integration.
+//!
+//! This module contains button implementations that go beyond the
+//! toggle and click buttons provided by `streamdeck-oxide`.
+
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use streamdeck_oxide::{
+    md_icons,
+    view::customizable::{ClickAction, CustomButton},
+    Button, ButtonState, Theme,
+};
+
+/// Error returned by fetch functions when an entity is `unavailable` or `unknown`.
+///
+/// `LabelButton` and `LabelToggleButton` don't treat it as a failure, but
+/// show the button grayed out with a cloud icon until the entity is back.
+#[derive(Debug)]
+pub struct EntityUnavailable;
+
+impl std::fmt::Display for EntityUnavailable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Entity is unavailable")
+    }
+}
+
+impl std::error::Error for EntityUnavailable {}
+
+/// Checks that an entity state is a real value.
+///
+/// # Arguments
+///
+/// * `state` - The entity state, e.g. "on" or "unavailable"
+///
+/// # Example
+///
+/// ```
+/// # use streamdeck_homeassistant::buttons::ensure_available;
+/// assert!(ensure_available("off").is_ok());
+/// assert!(ensure_available("unavailable").is_err());
+/// ```
+pub fn ensure_available(state: &str) -> Result<(), EntityUnavailable> {
+    match state {
+        "unavailable" | "unknown" => Err(EntityUnavailable),
+        _ => Ok(()),
+    }
+}
+
+/// Error returned by fetch and click functions while HomeAssistant is unreachable.
+///
+/// `LabelToggleButton` doesn't treat it as a failure, but shows an offline
+/// indicator until a later fetch finds the connection back up.
+#[derive(Debug)]
+pub struct Disconnected;
+
+impl std::fmt::Display for Disconnected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "HomeAssistant is disconnected")
+    }
+}
+
+impl std::error::Error for Disconnected {}
+
+/// Error returned by click functions that ignore a press repeating a recent one.
+///
+/// The buttons of this module treat it as a press that never happened: the
+/// state is kept, no error is shown, and the click succeeds.
+#[derive(Debug)]
+pub struct IgnoredPress;
+
+impl std::fmt::Display for IgnoredPress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Ignoring repeated press")
+    }
+}
+
+impl std::error::Error for IgnoredPress {}
+
+/// Returns how a button looks while HomeAssistant is unreachable.
+///
+/// The label is kept, so the button can still be told apart from its
+/// neighbours, and only the icon and state mark it as offline.
+fn offline_button(button: &Button) -> Button {
+    button
+        .updated_icon(md_icons::filled::ICON_WIFI_OFF)
+        .updated_state(ButtonState::Inactive)
+}
+
+/// Returns how a button looks while its entity is unavailable.
+fn unavailable_button(button: &Button) -> Button {
+    button
+        .updated_icon(md_icons::filled::ICON_CLOUD_OFF)
+        .updated_state(ButtonState::Inactive)
+}
+
+/// A future that returns a button label.
+pub type LabelFuture =
+    Pin<Box<dyn Future<Output = Result<String, Box<dyn std::error::Error>>> + Send + Sync>>;
+
+/// A function that returns a label future.
+pub type LabelFunction<C> = Arc<Box<dyn Fn(&C) -> LabelFuture + Send + Sync>>;
+
+/// A button whose label is fetched at runtime.
+///
+/// The label is refreshed whenever the view fetches state and after
+/// every click, so a button without a click action can be pressed
+/// to re-read the latest value.
+pub struct LabelButton<C>
+where
+    C: Send + Clone + Sync + 'static,
+{
+    /// The function to fetch the label.
+    fetch_label: LabelFunction<C>,
+    /// The optional function to call when clicked.
+    push_click: Option<ClickAction<C>>,
+    /// The button to display.
+    button: Button,
+    /// The most recently fetched label.
+    label: Mutex<String>,
+    /// Whether the entity was unavailable at the last fetch.
+    unavailable: Mutex<bool>,
+}
+
+impl<C> LabelButton<C>
+where
+    C: Send + Clone + Sync + 'static,
+{
+    /// Create a new label button.
+    ///
+    /// The given text is displayed until the first label is fetched.
+    pub fn new<F, FF, S>(text: S, icon: Option<&'static str>, fetch_label: F) -> Self
+    where
+        FF: Future<Output = Result<String, Box<dyn std::error::Error>>> + Send + Sync + 'static,
+        F: Fn(C) -> FF + Send + Sync + Clone + 'static,
+        S: Into<String>,
+    {
+        let text = text.into();
+        LabelButton {
+            fetch_label: Arc::new(Box::new(move |ctx| {
+                let fetch_label = fetch_label.clone();
+                let ctx = ctx.clone();
+                Box::pin(async move { fetch_label(ctx).await })
+            })),
+            push_click: None,
+            button: Button::new(text.clone(), icon, ButtonState::Default),
+            label: Mutex::new(text),
+            unavailable: Mutex::new(false),
+        }
+    }
+
+    /// Set the action performed when the button is clicked.
+    pub fn on_click<A, F>(self, action: A) -> Self
+    where
+        F: Future<Output = Result<(), Box<dyn std::error::Error>>> + Send + Sync + 'static,
+        A: Fn(C) -> F + Send + Sync + Clone + 'static,
+    {
+        LabelButton {
+            push_click: Some(Arc::new(Box::new(move |ctx| {
+                let action = action.clone();
+                let ctx = ctx.clone();
+                Box::pin(async move { action(ctx).await })
+            }))),
+            ..self
+        }
+    }
+
+    /// Set the theme of the button.
+    pub fn with_theme(self, theme: Theme) -> Self {
+        LabelButton {
+            button: self.button.with_theme(theme),
+            ..self
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<C> CustomButton<C> for LabelButton<C>
+where
+    C: Send + Clone + Sync + 'static,
+{
+    fn get_state(&self) -> Button {
+        if let Ok(true) = self.unavailable.lock().map(|unavailable| *unavailable) {
+            return unavailable_button(&self.button);
+        }
+        let label = self.label.lock().map(|label| label.clone()).unwrap_or_default();
+        self.button.updated_text(label)
+    }
+
+    async fn fetch(&self, context: &C) -> Result<(), Box<dyn std::error::Error>> {
+        let result = (self.fetch_label)(context).await;
+        let unavailable = matches!(&result, Err(e) if e.is::<EntityUnavailable>());
+        if let Ok(mut current) = self.unavailable.lock() {
+            *current = unavailable;
+        }
+        if unavailable {
+            return Ok(());
+        }
+        let label = result?;
+        if let Ok(mut current) = self.label.lock() {
+            *current = label;
+        }
+        Ok(())
+    }
+
+    async fn click(&self, context: &C) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(push_click) = &self.push_click {
+            match push_click(context).await {
+                Err(e) if e.is::<IgnoredPress>() => return Ok(()),
+                result => result?,
+            }
+        }
+        self.fetch(context).await
+    }
+}
+
+/// How long `FeedbackButton` shows a successful click as active.
+const SUCCESS_DISPLAY: Duration = Duration::from_secs(2);
+
+/// A button that shows whether its last click succeeded.
+///
+/// After a successful click the button is displayed as active for a short
+/// while, after a failed click it uses the error state until the next click.
+/// The error is still reported to the caller. The view is only redrawn on
+/// presses and navigation, so an expired success shows until the next redraw.
+/// Buttons built with `errors_only`, like those for stateless actions, only
+/// show failures.
+pub struct FeedbackButton<C>
+where
+    C: Send + Clone + Sync + 'static,
+{
+    /// The function to call when clicked.
+    push_click: ClickAction<C>,
+    /// The button to display.
+    button: Button,
+    /// Whether the last click succeeded and when, or None before the first click.
+    succeeded: Mutex<Option<(bool, Instant)>>,
+    /// Whether a successful click shows the button as active.
+    show_success: bool,
+}
+
+impl<C> FeedbackButton<C>
+where
+    C: Send + Clone + Sync + 'static,
+{
+    /// Create a new feedback button.
+    pub fn new<A, F, S>(text: S, icon: Option<&'static str>, action: A) -> Self
+    where
+        F: Future<Output = Result<(), Box<dyn std::error::Error>>> + Send + Sync + 'static,
+        A: Fn(C) -> F + Send + Sync + Clone + 'static,
+        S: Into<String>,
+    {
+        FeedbackButton {
+            push_click: Arc::new(Box::new(move |ctx| {
+                let action = action.clone();
+                let ctx = ctx.clone();
+                Box::pin(async move { action(ctx).await })
+            })),
+            button: Button::new(text.into(), icon, ButtonState::Default),
+            succeeded: Mutex::new(None),
+            show_success: true,
+        }
+    }
+
+    /// Only show failed clicks, e.g. for buttons that can be pressed repeatedly.
+    pub fn errors_only(self) -> Self {
+        FeedbackButton {
+            show_success: false,
+            ..self
+        }
+    }
+
+    /// Set the theme of the button.
+    pub fn with_theme(self, theme: Theme) -> Self {
+        FeedbackButton {
+            button: self.button.with_theme(theme),
+            ..self
+        }
+    }
+
+    /// Whether a recent successful click is still shown as active.
+    fn showing_success(&self) -> bool {
+        let succeeded = self.succeeded.lock().ok().and_then(|succeeded| *succeeded);
+        matches!(succeeded, Some((true, at)) if self.show_success && at.elapsed() < SUCCESS_DISPLAY)
+    }
+}
+
+#[async_trait::async_trait]
+impl<C> CustomButton<C> for FeedbackButton<C>
+where
+    C: Send + Clone + Sync + 'static,
+{
+    fn get_state(&self) -> Button {
+        if self.showing_success() {
+            return self.button.updated_state(ButtonState::Active);
+        }
+        let succeeded = self.succeeded.lock().ok().and_then(|succeeded| *succeeded);
+        match succeeded {
+            Some((false, _)) => self.button.updated_state(ButtonState::Error),
+            _ => self.button.clone(),
+        }
+    }
+
+    async fn fetch(&self, _context: &C) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+
+    async fn click(&self, context: &C) -> Result<(), Box<dyn std::error::Error>> {
+        let result = (self.push_click)(context).await;
+        if matches!(&result, Err(e) if e.is::<IgnoredPress>()) {
+            return Ok(());
+        }
+        if let Ok(mut succeeded) = self.succeeded.lock() {
+            *succeeded = Some((result.is_ok(), Instant::now()));
+        }
+        result
+    }
+}
+
+/// A button that only displays a fixed text, icon and state.
+///
+/// Clicking it does nothing. Used for buttons that can't work, such as
+/// those referring to entities that don't exist.
+pub struct StaticButton {
+    /// The button to display.
+    button: Button,
+}
+
+impl StaticButton {
+    /// Create a new static button.
+    pub fn new<S: Into<String>>(text: S, icon: Option<&'static str>, state: ButtonState) -> Self {
+        StaticButton {
+            button: Button::new(text.into(), icon, state),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<C> CustomButton<C> for StaticButton
+where
+    C: Send + Clone + Sync + 'static,
+{
+    fn get_state(&self) -> Button {
+        self.button.clone()
+    }
+
+    async fn fetch(&self, _context: &C) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+
+    async fn click(&self, _context: &C) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+}
+
+/// A future that returns whether a toggle is active and its label.
+pub type ToggleStateFuture =
+    Pin<Box<dyn Future<Output = Result<(bool, String), Box<dyn std::error::Error>>> + Send + Sync>>;
+
+/// A function that returns a toggle state future.
+pub type ToggleStateFunction<C> = Arc<Box<dyn Fn(&C) -> ToggleStateFuture + Send + Sync>>;
+
+/// A future that pushes a new toggle state.
+pub type PushFuture =
+    Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>> + Send + Sync>>;
+
+/// A function that returns a push future.
+pub type PushFunction<C> = Arc<Box<dyn Fn(&C, bool) -> PushFuture + Send + Sync>>;
+
+/// A future that returns a theme, or None to keep the default one.
+pub type ThemeFuture = Pin<Box<dyn Future<Output = Option<Theme>> + Send + Sync>>;
+
+/// A function that returns a theme future.
+pub type ThemeFunction<C> = Arc<Box<dyn Fn(&C) -> ThemeFuture + Send + Sync>>;
+
+/// A toggle button whose label is fetched along with its state.
+///
+/// Works like `ToggleButton`, but the fetch function also returns the label,
+/// so the button can show details such as a brightness level. After a click
+/// the label resets to the initial text until the next fetch. A failed click
+/// keeps the previous state and shows the error state until the next click
+/// or fetch, while a press ignored with [`IgnoredPress`] changes nothing.
+/// While the entity is unavailable, clicking only fetches again.
+pub struct LabelToggleButton<C>
+where
+    C: Send + Clone + Sync + 'static,
+{
+    /// The function to fetch the state and label.
+    fetch_state: ToggleStateFunction<C>,
+    /// The function to push a new state.
+    push_active: PushFunction<C>,
+    /// The button to display when inactive.
+    button: Button,
+    /// The button to display when active.
+    active_button: Button,
+    /// The initial text, shown until a label is fetched.
+    text: String,
+    /// The text shown after a click turned the button on, if it differs.
+    active_text: Option<String>,
+    /// The most recently fetched state and label.
+    state: Mutex<(bool, String)>,
+    /// Whether the last click failed.
+    failed: Mutex<bool>,
+    /// Whether the entity was unavailable at the last fetch.
+    unavailable: Mutex<bool>,
+    /// Whether HomeAssistant was unreachable at the last fetch or click.
+    offline: Mutex<bool>,
+    /// The optional function to fetch the theme used while active.
+    fetch_active_theme: Option<ThemeFunction<C>>,
+    /// The most recently fetched theme used while active.
+    active_theme: Mutex<Option<Theme>>,
+}
+
+impl<C> LabelToggleButton<C>
+where
+    C: Send + Clone + Sync + 'static,
+{
+    /// Create a new label toggle button.
+    pub fn new<FF, PF, F, P, S>(
+        text: S,
+        icon: Option<&'static str>,
+        fetch_state: F,
+        push_active: P,
+    ) -> Self
+    where
+        FF: Future<Output = Result<(bool, String), Box<dyn std::error::Error>>>
+            + Send
+            + Sync
+            + 'static,
+        PF: Future<Output = Result<(), Box<dyn std::error::Error>>> + Send + Sync + 'static,
+        F: Fn(C) -> FF + Send + Sync + Clone + 'static,
+        P: Fn(C, bool) -> PF + Send + Sync + Clone + 'static,
+        S: Into<String>,
+    {
+        let text = text.into();
+        LabelToggleButton {
+            fetch_state: Arc::new(Box::new(move |ctx| {
+                let fetch_state = fetch_state.clone();
+                let ctx = ctx.clone();
+                Box::pin(async move { fetch_state(ctx).await })
+            })),
+            push_active: Arc::new(Box::new(move |ctx, active| {
+                let push_active = push_active.clone();
+                let ctx = ctx.clone();
+                Box::pin(async move { push_active(ctx, active).await })
+            })),
+            button: Button::new(text.clone(), icon, ButtonState::Default),
+            active_button: Button::new(text.clone(), icon, ButtonState::Active),
+            state: Mutex::new((false, text.clone())),
+            failed: Mutex::new(false),
+            unavailable: Mutex::new(false),
+            offline: Mutex::new(false),
+            fetch_active_theme: None,
+            active_theme: Mutex::new(None),
+            text,
+            active_text: None,
+        }
+    }
+
+    /// Fetch the theme used while active along with the state.
+    ///
+    /// This lets the button reflect the entity, e.g. show a light's color.
+    /// When the function returns None, the active theme set with
+    /// `with_theme` is used.
+    pub fn with_active_theme<F, FF>(self, fetch_theme: F) -> Self
+    where
+        FF: Future<Output = Option<Theme>> + Send + Sync + 'static,
+        F: Fn(C) -> FF + Send + Sync + Clone + 'static,
+    {
+        LabelToggleButton {
+            fetch_active_theme: Some(Arc::new(Box::new(move |ctx| {
+                let fetch_theme = fetch_theme.clone();
+                let ctx = ctx.clone();
+                Box::pin(async move { fetch_theme(ctx).await })
+            }))),
+            ..self
+        }
+    }
+
+    /// Set the icon displayed when active.
+    pub fn when_active(self, icon: Option<&'static str>) -> Self {
+        LabelToggleButton {
+            active_button: Button::new(self.text.clone(), icon, ButtonState::Active),
+            ..self
+        }
+    }
+
+    /// Set the text shown after a click turned the button on, until the next fetch.
+    ///
+    /// For toggles whose fetched label names the action, e.g. "Start" and "Pause".
+    pub fn with_active_text<S: Into<String>>(self, text: S) -> Self {
+        LabelToggleButton {
+            active_text: Some(text.into()),
+            ..self
+        }
+    }
+
+    /// Set the theme of the button.
+    pub fn with_theme(self, theme: Theme) -> Self {
+        LabelToggleButton {
+            button: self.button.with_theme(theme),
+            active_button: self.active_button.with_theme(theme),
+            ..self
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<C> CustomButton<C> for LabelToggleButton<C>
+where
+    C: Send + Clone + Sync + 'static,
+{
+    fn get_state(&self) -> Button {
+        if let Ok(true) = self.unavailable.lock().map(|unavailable| *unavailable) {
+            return unavailable_button(&self.button.updated_text(self.text.clone()));
+        }
+        let (active, label) = self
+            .state
+            .lock()
+            .map(|state| state.clone())
+            .unwrap_or_else(|_| (false, self.text.clone()));
+        let active_theme = self.active_theme.lock().ok().and_then(|theme| *theme);
+        let button = match (active, active_theme) {
+            (true, Some(theme)) => self.active_button.updated_text(label).with_theme(theme),
+            (true, None) => self.active_button.updated_text(label),
+            (false, _) => self.button.updated_text(label),
+        };
+        // The last known state stays visible under the offline overlay
+        if let Ok(true) = self.offline.lock().map(|offline| *offline) {
+            return offline_button(&button);
+        }
+        match self.failed.lock().map(|failed| *failed) {
+            Ok(true) => button.updated_state(ButtonState::Error),
+            _ => button,
+        }
+    }
+
+    async fn fetch(&self, context: &C) -> Result<(), Box<dyn std::error::Error>> {
+        // Scoped so the error isn't held across the theme fetch below
+        {
+            let result = (self.fetch_state)(context).await;
+            let offline = matches!(&result, Err(e) if e.is::<Disconnected>());
+            if let Ok(mut current) = self.offline.lock() {
+                *current = offline;
+            }
+            let unavailable = matches!(&result, Err(e) if e.is::<EntityUnavailable>());
+            if let Ok(mut current) = self.unavailable.lock() {
+                *current = unavailable;
+            }
+            if offline || unavailable {
+                return Ok(());
+            }
+            let state = result?;
+            if let Ok(mut current) = self.state.lock() {
+                *current = state;
+            }
+            if let Ok(mut failed) = self.failed.lock() {
+                *failed = false;
+            }
+        }
+        if let Some(fetch_active_theme) = &self.fetch_active_theme {
+            let theme = fetch_active_theme(context).await;
+            if let Ok(mut current) = self.active_theme.lock() {
+                *current = theme;
+            }
+        }
+        Ok(())
+    }
+
+    async fn click(&self, context: &C) -> Result<(), Box<dyn std::error::Error>> {
+        let offline = self.offline.lock().map(|offline| *offline).unwrap_or(false);
+        let unavailable = self.unavailable.lock().map(|unavailable| *unavailable).unwrap_or(false);
+        if offline || unavailable {
+            return self.fetch(context).await;
+        }
+        let active = self.state.lock().map(|state| state.0).unwrap_or(false);
+        let result = (self.push_active)(context, !active).await;
+        if matches!(&result, Err(e) if e.is::<IgnoredPress>()) {
+            return Ok(());
+        }
+        // A press during an outage shows the offline indicator instead of an error
+        if matches!(&result, Err(e) if e.is::<Disconnected>()) {
+            if let Ok(mut offline) = self.offline.lock() {
+                *offline = true;
+            }
+            return Ok(());
+        }
+        if let Ok(mut failed) = self.failed.lock() {
+            *failed = result.is_err();
+        }
+        result?;
+        let text = match &self.active_text {
+            Some(active_text) if !active => active_text.clone(),
+            _ => self.text.clone(),
+        };
+        if let Ok(mut current) = self.state.lock() {
+            *current = (!active, text);
+        }
+        Ok(())
+    }
+}
+
+/// A future that returns a button label and icon.
+pub type IconLabelFuture = Pin<
+    Box<
+        dyn Future<Output = Result<(String, &'static str), Box<dyn std::error::Error>>>
+            + Send
+            + Sync,
+    >,
+>;
+
+/// A function that returns a label and icon future.
+pub type IconLabelFunction<C> = Arc<Box<dyn Fn(&C) -> IconLabelFuture + Send + Sync>>;
+
+/// A read-only button whose label and icon are both fetched at runtime.
+///
+/// Like `LabelButton` without a click action, but the icon can change with
+/// the state, e.g. to match the weather condition.
+pub struct IconLabelButton<C>
+where
+    C: Send + Clone + Sync + 'static,
+{
+    /// The function to fetch the label and icon.
+    fetch: IconLabelFunction<C>,
+    /// The button to display.
+    button: Button,
+    /// The most recently fetched label and icon.
+    current: Mutex<(String, &'static str)>,
+    /// Icon that shows the button in its error colors while displayed.
+    alert_icon: Option<&'static str>,
+}
+
+impl<C> IconLabelButton<C>
+where
+    C: Send + Clone + Sync + 'static,
+{
+    /// Create a new icon label button.
+    ///
+    /// The given text and icon are displayed until the first fetch.
+    pub fn new<F, FF, S>(text: S, icon: &'static str, fetch: F) -> Self
+    where
+        FF: Future<Output = Result<(String, &'static str), Box<dyn std::error::Error>>>
+            + Send
+            + Sync
+            + 'static,
+        F: Fn(C) -> FF + Send + Sync + Clone + 'static,
+        S: Into<String>,
+    {
+        let text = text.into();
+        IconLabelButton {
+            fetch: Arc::new(Box::new(move |ctx| {
+                let fetch = fetch.clone();
+                let ctx = ctx.clone();
+                Box::pin(async move { fetch(ctx).await })
+            })),
+            button: Button::new(text.clone(), Some(icon), ButtonState::Default),
+            current: Mutex::new((text, icon)),
+            alert_icon: None,
+        }
+    }
+
+    /// Set the theme of the button.
+    pub fn with_theme(self, theme: Theme) -> Self {
+        IconLabelButton {
+            button: self.button.with_theme(theme),
+            ..self
+        }
+    }
+
+    /// Show the button in its error colors whenever the fetched icon is `icon`.
+    pub fn with_alert_icon(self, icon: &'static str) -> Self {
+        IconLabelButton {
+            alert_icon: Some(icon),
+            ..self
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<C> CustomButton<C> for IconLabelButton<C>
+where
+    C: Send + Clone + Sync + 'static,
+{
+    fn get_state(&self) -> Button {
+        match self.current.lock() {
+            Ok(current) => {
+                let (label, icon) = current.clone();
+                let button = self.button.updated_text(label).updated_icon(icon);
+                if self.alert_icon == Some(icon) {
+                    button.updated_state(ButtonState::Error)
+                } else {
+                    button
+                }
+            }
+            Err(_) => self.button.clone(),
+        }
+    }
+
+    async fn fetch(&self, context: &C) -> Result<(), Box<dyn std::error::Error>> {
+        let current = (self.fetch)(context).await?;
+        if let Ok(mut previous) = self.current.lock() {
+            *previous = current;
+        }
+        Ok(())
+    }
+
+    async fn click(&self, context: &C) -> Result<(), Box<dyn std::error::Error>> {
+        self.fetch(context).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::{backend::HassBackend, mock::MockHassConnection};
+
+    #[tokio::test]
+    async fn ignored_press_keeps_the_toggle_state() {
+        let hass = Arc::new(
+            MockHassConnection::new()
+                .with_state("light.desk", "off", serde_json::json!({}))
+                .with_debounce(Duration::from_secs(60)),
+        );
+        let button = LabelToggleButton::new(
+            "Desk",
+            None,
+            |hass: Arc<MockHassConnection>| async move {
+                let state = hass.get_state("light.desk").await.ok_or("Failed to get state")?;
+                Ok((state.state == "on", "Desk".to_string()))
+            },
+            |hass: Arc<MockHassConnection>, value| async move {
+                if !hass.accept_press("light.desk") {
+                    return Err(IgnoredPress.into());
+                }
+                let service = if value { "turn_on" } else { "turn_off" };
+                let data = serde_json::json!({ "entity_id": "light.desk" });
+                hass.call_service("light", service, Some(data)).await
+            },
+        );
+        button.fetch(&hass).await.unwrap();
+
+        button.click(&hass).await.unwrap();
+        button.click(&hass).await.unwrap();
+
+        assert_eq!(hass.calls().len(), 1);
+        assert!(button.state.lock().unwrap().0);
+        assert!(!*button.failed.lock().unwrap());
+    }
+    #[tokio::test]
+    async fn going_offline_keeps_the_last_state() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        let online = Arc::new(AtomicBool::new(true));
+        let button = LabelToggleButton::new(
+            "Desk",
+            None,
+            |online: Arc<AtomicBool>| async move {
+                if !online.load(Ordering::SeqCst) {
+                    return Err(Disconnected.into());
+                }
+                Ok((true, "Desk 40%".to_string()))
+            },
+            |_: Arc<AtomicBool>, _| async move { Ok(()) },
+        );
+        button.fetch(&online).await.unwrap();
+
+        online.store(false, Ordering::SeqCst);
+        button.fetch(&online).await.unwrap();
+
+        assert!(*button.offline.lock().unwrap());
+        assert_eq!(*button.state.lock().unwrap(), (true, "Desk 40%".to_string()));
+    }
+
+    #[tokio::test]
+    async fn success_is_shown_only_briefly() {
+        let button = FeedbackButton::new("Ring", None, |_: ()| async move { Ok(()) });
+
+        button.click(&()).await.unwrap();
+        assert!(button.showing_success());
+
+        *button.succeeded.lock().unwrap() = Some((true, Instant::now() - SUCCESS_DISPLAY));
+        assert!(!button.showing_success());
+    }
+}
@@ -1,15 +1,86 @@
 //! Configuration types and functions for the StreamDeck HomeAssistant integration.
 
 use serde::{Deserialize, Serialize};
+use streamdeck_oxide::{RenderConfig, Theme};
+
+use crate::hass::EntityFilter;
 
 /// Main configuration for the HomeAssistant integration.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields, rename_all = "snake_case")]
 pub struct HomeAssistantConfig {
+    /// Schema version the configuration is written for; see [`CONFIG_VERSION`]
+    #[serde(default = "default_config_version")]
+    pub version: u32,
     /// WebSocket URL for the HomeAssistant instance (e.g., "ws://192.168.0.1:8123/api/websocket")
     pub url: String,
     /// Root menu configuration
     pub menu: HomeAssistantMenu,
+    /// Maximum number of nested submenus below the root menu
+    #[serde(default = "default_max_menu_depth")]
+    pub max_menu_depth: usize,
+    /// Theme used for every menu that doesn't choose its own
+    #[serde(default)]
+    pub theme: ThemeName,
+    /// Entities to turn off when the connection is closed, e.g. when the controller exits
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub turn_off_on_exit: Vec<String>,
+    /// Buttons shown in the same slots on every menu, next to the "Back" button
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub favorites: Vec<HomeAssistantButton>,
+    /// Whether menus two or more levels deep show a "Home" button leading to the root menu
+    #[serde(default)]
+    pub home_button: bool,
+    /// Whether to cache only the entities used by buttons instead of every entity
+    #[serde(default)]
+    pub only_cache_used_entities: bool,
+    /// Entities left out of the regular update and refreshed when a menu showing
+    /// them opens, e.g. cameras or energy meters; needs `only_cache_used_entities`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lazy_entities: Vec<String>,
+    /// TrueType or OpenType font file for button labels; defaults to the built-in Roboto
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub font_file: Option<std::path::PathBuf>,
+    /// Label height in pixels, used with `font_file`; defaults to 14
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub font_size: Option<f32>,
+    /// Name of the submenu shown at startup instead of the root menu
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_menu: Option<String>,
+}
+
+/// The configuration schema version this build reads.
+///
+/// Configurations without a `version` are taken to be version 1. Older
+/// versions are upgraded by [`migrate_config`] when loaded; newer ones are
+/// rejected, since their fields may mean something this build doesn't know.
+pub const CONFIG_VERSION: u32 = 1;
+
+/// Upgrades a configuration by one version, collecting warnings about
+/// deprecated fields it rewrote.
+type Migration = fn(&mut serde_yaml::Mapping, &mut Vec<String>);
+
+/// Migrations from each version to the next, starting at version 1.
+///
+/// A schema change that renames or removes a field bumps `CONFIG_VERSION`
+/// and adds the step here, so older configurations keep loading.
+const MIGRATIONS: [Migration; CONFIG_VERSION as usize - 1] = [];
+
+/// Domains of the entities an `area` button lists, all toggled with `turn_on`/`turn_off`.
+pub(crate) const AREA_DOMAINS: [&str; 4] = ["light", "switch", "fan", "input_boolean"];
+
+fn default_config_version() -> u32 {
+    CONFIG_VERSION
+}
+
+/// Label height used with a custom font when no size is configured.
+const DEFAULT_FONT_SIZE: f32 = 14.0;
+
+/// Width and height of rendered button images, as in `RenderConfig::default()`.
+const BUTTON_IMAGE_SIZE: u32 = 72;
+
+fn default_max_menu_depth() -> usize {
+    8
 }
 
 /// Represents a menu in the StreamDeck interface.
@@ -20,6 +91,60 @@ pub struct HomeAssistantMenu {
     pub name: String,
     /// List of buttons in this menu
     pub buttons: Vec<HomeAssistantButton>,
+    /// Theme for the buttons of this menu and its submenus; defaults to the parent's theme
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub theme: Option<ThemeName>,
+    /// Key to pin the submenu's button to in its parent menu
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<ButtonPosition>,
+}
+
+/// A key on the Stream Deck, counted from the top left.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ButtonPosition {
+    /// Zero-based column
+    pub x: usize,
+    /// Zero-based row
+    pub y: usize,
+}
+
+/// The built-in themes that can be selected in the configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThemeName {
+    /// Light buttons with dark text
+    #[default]
+    Light,
+    /// Dark buttons with light text
+    Dark,
+}
+
+impl ThemeName {
+    /// Returns the `streamdeck-oxide` theme with this name.
+    pub fn to_theme(self) -> Theme {
+        match self {
+            ThemeName::Light => Theme::light(),
+            ThemeName::Dark => Theme::dark(),
+        }
+    }
+}
+
+/// How a summary button combines the states of its entities.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SummaryAggregate {
+    /// Number of entities in the summary's `state`, e.g. lights that are on
+    #[default]
+    Count,
+    /// Mean of the numeric states
+    Average,
+    /// Lowest numeric state
+    Min,
+    /// Highest numeric state
+    Max,
+    /// Total of the numeric states
+    Sum,
 }
 
 /// Represents different types of buttons that can be placed on the StreamDeck.
@@ -27,22 +152,1355 @@ pub struct HomeAssistantMenu {
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum HomeAssistantButton {
     /// A simple on/off switch
-    Switch { entity_id: String, name: String },
+    Switch {
+        entity_id: String,
+        /// Display name; defaults to the entity's `friendly_name`
+        #[serde(default)]
+        name: Option<String>,
+        /// Background color as [r, g, b] while the entity is on; defaults to the theme's
+        #[serde(default)]
+        active_color: Option<(u8, u8, u8)>,
+        /// Seconds after a successful press during which further presses are ignored
+        #[serde(default)]
+        cooldown_secs: Option<u64>,
+        /// Name of the HomeAssistant instance to use; defaults to the primary connection
+        #[serde(default)]
+        instance: Option<String>,
+        /// Key to pin the button to; unpinned buttons fill the remaining keys
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        position: Option<ButtonPosition>,
+    },
+    /// An on/off toggle for any entity with `turn_on`/`turn_off` services,
+    /// such as lights, switches and input booleans
+    Toggle {
+        entity_id: String,
+        /// Display name; defaults to the entity's `friendly_name`
+        #[serde(default)]
+        name: Option<String>,
+        /// Background color as [r, g, b] while the entity is on; defaults to the theme's
+        #[serde(default)]
+        active_color: Option<(u8, u8, u8)>,
+        /// Seconds over which lights fade on and off; only valid for lights
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        transition: Option<f32>,
+        /// Seconds after a successful press during which further presses are ignored
+        #[serde(default)]
+        cooldown_secs: Option<u64>,
+        /// Name of the HomeAssistant instance to use; defaults to the primary connection
+        #[serde(default)]
+        instance: Option<String>,
+        /// Key to pin the button to; unpinned buttons fill the remaining keys
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        position: Option<ButtonPosition>,
+    },
+    /// A push button that turns an entity on and off again after a short pulse,
+    /// e.g. for garage door triggers
+    Momentary {
+        entity_id: String,
+        /// Display name; defaults to the entity's `friendly_name`
+        #[serde(default)]
+        name: Option<String>,
+        /// How long the entity stays on, in milliseconds
+        #[serde(default = "default_pulse_ms")]
+        pulse_ms: u64,
+        /// Seconds after a successful press during which further presses are ignored
+        #[serde(default)]
+        cooldown_secs: Option<u64>,
+        /// Name of the HomeAssistant instance to use; defaults to the primary connection
+        #[serde(default)]
+        instance: Option<String>,
+        /// Key to pin the button to; unpinned buttons fill the remaining keys
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        position: Option<ButtonPosition>,
+    },
+    /// A stateless `button` entity, e.g. to restart a router, pressed on each press
+    Button {
+        entity_id: String,
+        /// Display name; defaults to the entity's `friendly_name`
+        #[serde(default)]
+        name: Option<String>,
+        /// Seconds after a successful press during which further presses are ignored
+        #[serde(default)]
+        cooldown_secs: Option<u64>,
+        /// Name of the HomeAssistant instance to use; defaults to the primary connection
+        #[serde(default)]
+        instance: Option<String>,
+        /// Key to pin the button to; unpinned buttons fill the remaining keys
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        position: Option<ButtonPosition>,
+    },
+    /// Turns an entity on and back off after a fixed time
+    ///
+    /// Pressing the button while the timer runs cancels it and turns the
+    /// entity off right away.
+    TimedOn {
+        entity_id: String,
+        /// Display name; defaults to the entity's `friendly_name`
+        #[serde(default)]
+        name: Option<String>,
+        /// How long the entity stays on, in seconds
+        duration_secs: u64,
+        /// Seconds after a successful press during which further presses are ignored
+        #[serde(default)]
+        cooldown_secs: Option<u64>,
+        /// Name of the HomeAssistant instance to use; defaults to the primary connection
+        #[serde(default)]
+        instance: Option<String>,
+        /// Key to pin the button to; unpinned buttons fill the remaining keys
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        position: Option<ButtonPosition>,
+    },
+    /// An `input_boolean` helper toggled on and off
+    InputBoolean {
+        entity_id: String,
+        /// Display name; defaults to the entity's `friendly_name`
+        #[serde(default)]
+        name: Option<String>,
+        /// Background color as [r, g, b] while the entity is on; defaults to the theme's
+        #[serde(default)]
+        active_color: Option<(u8, u8, u8)>,
+        /// Seconds after a successful press during which further presses are ignored
+        #[serde(default)]
+        cooldown_secs: Option<u64>,
+        /// Name of the HomeAssistant instance to use; defaults to the primary connection
+        #[serde(default)]
+        instance: Option<String>,
+        /// Key to pin the button to; unpinned buttons fill the remaining keys
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        position: Option<ButtonPosition>,
+    },
     /// An RGB light with color control
-    RgbLight { entity_id: String, name: String },
+    RgbLight {
+        entity_id: String,
+        /// Display name; defaults to the entity's `friendly_name`
+        #[serde(default)]
+        name: Option<String>,
+        /// Seconds over which the light fades to a new state, color or brightness
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        transition: Option<f32>,
+        /// Name of the HomeAssistant instance to use; defaults to the primary connection
+        #[serde(default)]
+        instance: Option<String>,
+        /// Key to pin the button to; unpinned buttons fill the remaining keys
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        position: Option<ButtonPosition>,
+    },
+    /// A fan with on/off and speed presets
+    Fan {
+        entity_id: String,
+        /// Display name; defaults to the entity's `friendly_name`
+        #[serde(default)]
+        name: Option<String>,
+        /// Name of the HomeAssistant instance to use; defaults to the primary connection
+        #[serde(default)]
+        instance: Option<String>,
+        /// Key to pin the button to; unpinned buttons fill the remaining keys
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        position: Option<ButtonPosition>,
+    },
+    /// A lock that can be locked and unlocked
+    Lock {
+        entity_id: String,
+        /// Display name; defaults to the entity's `friendly_name`
+        #[serde(default)]
+        name: Option<String>,
+        /// Seconds after a successful press during which further presses are ignored
+        #[serde(default)]
+        cooldown_secs: Option<u64>,
+        /// Name of the HomeAssistant instance to use; defaults to the primary connection
+        #[serde(default)]
+        instance: Option<String>,
+        /// Key to pin the button to; unpinned buttons fill the remaining keys
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        position: Option<ButtonPosition>,
+    },
+    /// A read-only display of a sensor value
+    Sensor {
+        entity_id: String,
+        /// Display name; defaults to the entity's `friendly_name`
+        #[serde(default)]
+        name: Option<String>,
+        /// Unit appended to the value; defaults to the sensor's `unit_of_measurement`
+        #[serde(default)]
+        unit: Option<String>,
+        /// Label template with `{state}`, `{name}`, `{last_changed}` and `{attr:...}`
+        /// placeholders; overrides `unit` and `show_last_changed`
+        #[serde(default)]
+        label_template: Option<String>,
+        /// Appends how long ago the state changed, e.g. "on (2m ago)"
+        #[serde(default)]
+        show_last_changed: bool,
+        /// Label templates shown in turn, e.g. `["{state}°C", "{attr:humidity}%"]`;
+        /// overrides `label_template`
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        rotate_labels: Vec<String>,
+        /// Seconds each of the `rotate_labels` is shown
+        #[serde(default = "default_rotate_secs")]
+        rotate_secs: u64,
+        /// Name of the HomeAssistant instance to use; defaults to the primary connection
+        #[serde(default)]
+        instance: Option<String>,
+        /// Key to pin the button to; unpinned buttons fill the remaining keys
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        position: Option<ButtonPosition>,
+    },
+    /// A read-only display of a weather entity's condition and temperature
+    Weather {
+        entity_id: String,
+        /// Display name; defaults to the entity's `friendly_name`
+        #[serde(default)]
+        name: Option<String>,
+        /// Label templates shown in turn instead of the temperature,
+        /// e.g. `["{attr:humidity}%", "{attr:wind_speed} km/h"]`
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        rotate_labels: Vec<String>,
+        /// Seconds each of the `rotate_labels` is shown
+        #[serde(default = "default_rotate_secs")]
+        rotate_secs: u64,
+        /// Name of the HomeAssistant instance to use; defaults to the primary connection
+        #[serde(default)]
+        instance: Option<String>,
+        /// Key to pin the button to; unpinned buttons fill the remaining keys
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        position: Option<ButtonPosition>,
+    },
+    /// A read-only bar showing a numeric state or attribute, e.g. a battery level
+    Gauge {
+        entity_id: String,
+        /// Display name; defaults to the entity's `friendly_name`
+        #[serde(default)]
+        name: Option<String>,
+        /// Attribute to show instead of the state, e.g. "brightness"
+        #[serde(default)]
+        attribute: Option<String>,
+        /// Value of an empty bar
+        #[serde(default)]
+        min: f64,
+        /// Value of a full bar
+        #[serde(default = "default_gauge_max")]
+        max: f64,
+        /// Name of the HomeAssistant instance to use; defaults to the primary connection
+        #[serde(default)]
+        instance: Option<String>,
+        /// Key to pin the button to; unpinned buttons fill the remaining keys
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        position: Option<ButtonPosition>,
+    },
+    /// A read-only battery icon that turns red when the charge runs low
+    Battery {
+        entity_id: String,
+        /// Display name; defaults to the entity's `friendly_name`
+        #[serde(default)]
+        name: Option<String>,
+        /// Charge in percent below which the battery is flagged
+        #[serde(default = "default_battery_low")]
+        low: f64,
+        /// Name of the HomeAssistant instance to use; defaults to the primary connection
+        #[serde(default)]
+        instance: Option<String>,
+        /// Key to pin the button to; unpinned buttons fill the remaining keys
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        position: Option<ButtonPosition>,
+    },
+    /// An `input_number` helper with increment/decrement buttons
+    InputNumber {
+        entity_id: String,
+        /// Display name; defaults to the entity's `friendly_name`
+        #[serde(default)]
+        name: Option<String>,
+        /// Amount to add or subtract per press; defaults to the helper's `step` attribute
+        #[serde(default)]
+        step: Option<f64>,
+        /// Name of the HomeAssistant instance to use; defaults to the primary connection
+        #[serde(default)]
+        instance: Option<String>,
+        /// Key to pin the button to; unpinned buttons fill the remaining keys
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        position: Option<ButtonPosition>,
+    },
+    /// A robot vacuum with start/pause, stop, dock and locate controls
+    Vacuum {
+        entity_id: String,
+        /// Display name; defaults to the entity's `friendly_name`
+        #[serde(default)]
+        name: Option<String>,
+        /// Name of the HomeAssistant instance to use; defaults to the primary connection
+        #[serde(default)]
+        instance: Option<String>,
+        /// Key to pin the button to; unpinned buttons fill the remaining keys
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        position: Option<ButtonPosition>,
+    },
+    /// A humidifier with on/off and target humidity controls
+    Humidifier {
+        entity_id: String,
+        /// Display name; defaults to the entity's `friendly_name`
+        #[serde(default)]
+        name: Option<String>,
+        /// Name of the HomeAssistant instance to use; defaults to the primary connection
+        #[serde(default)]
+        instance: Option<String>,
+        /// Key to pin the button to; unpinned buttons fill the remaining keys
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        position: Option<ButtonPosition>,
+    },
+    /// A thermostat with on/off and target temperature controls
+    Climate {
+        entity_id: String,
+        /// Display name; defaults to the entity's `friendly_name`
+        #[serde(default)]
+        name: Option<String>,
+        /// Degrees to add or subtract per press, in the thermostat's unit; defaults to
+        /// the `target_temp_step` attribute, or 0.5 °C / 1 °F
+        #[serde(default)]
+        step: Option<f64>,
+        /// Name of the HomeAssistant instance to use; defaults to the primary connection
+        #[serde(default)]
+        instance: Option<String>,
+        /// Key to pin the button to; unpinned buttons fill the remaining keys
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        position: Option<ButtonPosition>,
+    },
+    /// A water heater such as a boiler with target temperature and operation mode controls
+    WaterHeater {
+        entity_id: String,
+        /// Display name; defaults to the entity's `friendly_name`
+        #[serde(default)]
+        name: Option<String>,
+        /// Degrees to add or subtract per press, in the water heater's unit; defaults to
+        /// the `target_temp_step` attribute, or 0.5 °C / 1 °F
+        #[serde(default)]
+        step: Option<f64>,
+        /// Name of the HomeAssistant instance to use; defaults to the primary connection
+        #[serde(default)]
+        instance: Option<String>,
+        /// Key to pin the button to; unpinned buttons fill the remaining keys
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        position: Option<ButtonPosition>,
+    },
+    /// An `input_select` helper that cycles to the next option on each press
+    InputSelect {
+        entity_id: String,
+        /// Display name; defaults to the entity's `friendly_name`
+        #[serde(default)]
+        name: Option<String>,
+        /// Seconds after a successful press during which further presses are ignored
+        #[serde(default)]
+        cooldown_secs: Option<u64>,
+        /// Name of the HomeAssistant instance to use; defaults to the primary connection
+        #[serde(default)]
+        instance: Option<String>,
+        /// Key to pin the button to; unpinned buttons fill the remaining keys
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        position: Option<ButtonPosition>,
+    },
+    /// A device `select` entity, cycled through its options like an `input_select`
+    Select {
+        entity_id: String,
+        /// Display name; defaults to the entity's `friendly_name`
+        #[serde(default)]
+        name: Option<String>,
+        /// Seconds after a successful press during which further presses are ignored
+        #[serde(default)]
+        cooldown_secs: Option<u64>,
+        /// Name of the HomeAssistant instance to use; defaults to the primary connection
+        #[serde(default)]
+        instance: Option<String>,
+        /// Key to pin the button to; unpinned buttons fill the remaining keys
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        position: Option<ButtonPosition>,
+    },
+    /// A cover such as blinds or shades with preset positions
+    CoverPosition {
+        entity_id: String,
+        /// Display name; defaults to the entity's `friendly_name`
+        #[serde(default)]
+        name: Option<String>,
+        /// Name of the HomeAssistant instance to use; defaults to the primary connection
+        #[serde(default)]
+        instance: Option<String>,
+        /// Key to pin the button to; unpinned buttons fill the remaining keys
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        position: Option<ButtonPosition>,
+    },
+    /// A cover with open, stop and close buttons and its current position
+    CoverControl {
+        entity_id: String,
+        /// Display name; defaults to the entity's `friendly_name`
+        #[serde(default)]
+        name: Option<String>,
+        /// Name of the HomeAssistant instance to use; defaults to the primary connection
+        #[serde(default)]
+        instance: Option<String>,
+        /// Key to pin the button to; unpinned buttons fill the remaining keys
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        position: Option<ButtonPosition>,
+    },
+    /// A button sending a notification through a `notify` service
+    Notify {
+        /// The notify service to call, e.g. "mobile_app_phone" for `notify.mobile_app_phone`
+        service: String,
+        /// Optional notification title
+        #[serde(default)]
+        title: Option<String>,
+        message: String,
+        name: String,
+        /// Seconds after a successful press during which further presses are ignored
+        #[serde(default)]
+        cooldown_secs: Option<u64>,
+        /// Name of the HomeAssistant instance to use; defaults to the primary connection
+        #[serde(default)]
+        instance: Option<String>,
+        /// Key to pin the button to; unpinned buttons fill the remaining keys
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        position: Option<ButtonPosition>,
+    },
+    /// A button firing a custom event on the HomeAssistant event bus,
+    /// e.g. for automations listening for it
+    FireEvent {
+        /// The event type, e.g. "desk_scene_cycle"
+        event_type: String,
+        /// Optional data sent with the event, as an object
+        #[serde(default)]
+        event_data: Option<serde_json::Value>,
+        name: String,
+        /// Seconds after a successful press during which further presses are ignored
+        #[serde(default)]
+        cooldown_secs: Option<u64>,
+        /// Name of the HomeAssistant instance to use; defaults to the primary connection
+        #[serde(default)]
+        instance: Option<String>,
+        /// Key to pin the button to; unpinned buttons fill the remaining keys
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        position: Option<ButtonPosition>,
+    },
+    /// A camera that saves a snapshot on each press
+    CameraSnapshot {
+        entity_id: String,
+        /// Display name; defaults to the entity's `friendly_name`
+        #[serde(default)]
+        name: Option<String>,
+        /// Where HomeAssistant saves the snapshot, e.g. "/media/porch.jpg"; may use
+        /// templates such as `{{ now().timestamp() }}`
+        filename: String,
+        /// Seconds after a successful press during which further presses are ignored
+        #[serde(default)]
+        cooldown_secs: Option<u64>,
+        /// Name of the HomeAssistant instance to use; defaults to the primary connection
+        #[serde(default)]
+        instance: Option<String>,
+        /// Key to pin the button to; unpinned buttons fill the remaining keys
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        position: Option<ButtonPosition>,
+    },
+    /// A button handled by a `ButtonHandler` registered in `ButtonHandlers`
+    Custom {
+        /// The name the handler was registered under
+        handler: String,
+        name: String,
+        /// The entity the button refers to, if any
+        #[serde(default)]
+        entity_id: Option<String>,
+        /// Free-form options passed to the handler
+        #[serde(default)]
+        options: serde_json::Value,
+        /// Seconds after a successful press during which further presses are ignored
+        #[serde(default)]
+        cooldown_secs: Option<u64>,
+        /// Name of the HomeAssistant instance to use; defaults to the primary connection
+        #[serde(default)]
+        instance: Option<String>,
+        /// Key to pin the button to; unpinned buttons fill the remaining keys
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        position: Option<ButtonPosition>,
+    },
+    /// A submenu listing every scene or script in HomeAssistant when opened
+    SceneList {
+        name: String,
+        /// The domain to list, "scene" or "script"
+        #[serde(default = "default_scene_list_domain")]
+        domain: String,
+        /// Name of the HomeAssistant instance to use; defaults to the primary connection
+        #[serde(default)]
+        instance: Option<String>,
+        /// Key to pin the button to; unpinned buttons fill the remaining keys
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        position: Option<ButtonPosition>,
+    },
+    /// A submenu with a toggle for every light, switch, fan and input boolean
+    /// assigned to a HomeAssistant area, listed when opened
+    Area {
+        /// The ID of the area, e.g. "living_room"
+        area_id: String,
+        name: String,
+        /// Name of the HomeAssistant instance to use; defaults to the primary connection
+        #[serde(default)]
+        instance: Option<String>,
+        /// Key to pin the button to; unpinned buttons fill the remaining keys
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        position: Option<ButtonPosition>,
+    },
+    /// A button reconnecting to HomeAssistant and refreshing all states
+    Refresh {
+        name: String,
+        /// Seconds after a successful press during which further presses are ignored
+        #[serde(default)]
+        cooldown_secs: Option<u64>,
+        /// Name of the HomeAssistant instance to use; defaults to the primary connection
+        #[serde(default)]
+        instance: Option<String>,
+        /// Key to pin the button to; unpinned buttons fill the remaining keys
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        position: Option<ButtonPosition>,
+    },
+    /// An alarm control panel with arm home, arm away and disarm buttons
+    Alarm {
+        entity_id: String,
+        /// Display name; defaults to the entity's `friendly_name`
+        #[serde(default)]
+        name: Option<String>,
+        /// Code sent with every arm and disarm call
+        code: AlarmCode,
+        /// Name of the HomeAssistant instance to use; defaults to the primary connection
+        #[serde(default)]
+        instance: Option<String>,
+        /// Key to pin the button to; unpinned buttons fill the remaining keys
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        position: Option<ButtonPosition>,
+    },
+    /// A read-only label combining the states of several entities,
+    /// e.g. the number of lights that are on
+    Summary {
+        name: String,
+        /// Entities to combine
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        entity_ids: Vec<String>,
+        /// Combines every entity of this domain as well, e.g. "light"
+        #[serde(default)]
+        domain: Option<String>,
+        /// How the states are combined
+        #[serde(default)]
+        aggregate: SummaryAggregate,
+        /// State counted by the `count` aggregate
+        #[serde(default = "default_summary_state")]
+        state: String,
+        /// Name of the HomeAssistant instance to use; defaults to the primary connection
+        #[serde(default)]
+        instance: Option<String>,
+        /// Key to pin the button to; unpinned buttons fill the remaining keys
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        position: Option<ButtonPosition>,
+    },
+    /// A single on/off toggle for several entities at once
+    Group {
+        entity_ids: Vec<String>,
+        name: String,
+        /// Domain whose `turn_on`/`turn_off` services are called; defaults to "homeassistant"
+        #[serde(default = "default_group_domain")]
+        domain: String,
+        /// Seconds after a successful press during which further presses are ignored
+        #[serde(default)]
+        cooldown_secs: Option<u64>,
+        /// Name of the HomeAssistant instance to use; defaults to the primary connection
+        #[serde(default)]
+        instance: Option<String>,
+        /// Key to pin the button to; unpinned buttons fill the remaining keys
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        position: Option<ButtonPosition>,
+    },
+    /// A single on/off toggle for every switch, toggle, input boolean and RGB
+    /// light in the same menu, e.g. to turn off a whole room
+    MenuToggle {
+        name: String,
+        /// Seconds after a successful press during which further presses are ignored
+        #[serde(default)]
+        cooldown_secs: Option<u64>,
+        /// Name of the HomeAssistant instance to use; defaults to the primary connection.
+        /// Only entities of this instance are toggled.
+        #[serde(default)]
+        instance: Option<String>,
+        /// Key to pin the button to; unpinned buttons fill the remaining keys
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        position: Option<ButtonPosition>,
+    },
+    /// A read-only label rendered from a HomeAssistant template
+    Template {
+        /// Name shown until the template has been rendered
+        name: String,
+        /// Jinja template rendered by HomeAssistant, e.g. "{{ states('sensor.power') }} W"
+        template: String,
+        /// Template that hides the button while it renders false, e.g. "{{ is_state('sun.sun', 'below_horizon') }}"
+        #[serde(default)]
+        visible: Option<String>,
+        /// Seconds between renderings of the templates
+        #[serde(default = "default_template_refresh_secs")]
+        refresh_secs: u64,
+        /// Name of the HomeAssistant instance to use; defaults to the primary connection
+        #[serde(default)]
+        instance: Option<String>,
+        /// Key to pin the button to; unpinned buttons fill the remaining keys
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        position: Option<ButtonPosition>,
+    },
     /// A submenu containing more buttons
     Menu(HomeAssistantMenu),
 }
 
-/// Loads a configuration from a YAML file.
+fn default_pulse_ms() -> u64 {
+    500
+}
+
+fn default_group_domain() -> String {
+    "homeassistant".to_string()
+}
+
+fn default_rotate_secs() -> u64 {
+    5
+}
+
+fn default_summary_state() -> String {
+    "on".to_string()
+}
+
+fn default_gauge_max() -> f64 {
+    100.0
+}
+
+fn default_battery_low() -> f64 {
+    20.0
+}
+
+fn default_template_refresh_secs() -> u64 {
+    10
+}
+
+fn default_scene_list_domain() -> String {
+    "scene".to_string()
+}
+
+/// A secret alarm panel code.
+///
+/// The code is redacted from `Debug` output so it never ends up in logs.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct AlarmCode(String);
+
+impl AlarmCode {
+    /// Creates an alarm code.
+    pub fn new<S: Into<String>>(code: S) -> Self {
+        AlarmCode(code.into())
+    }
+
+    /// Returns the code to send to HomeAssistant.
+    pub(crate) fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for AlarmCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("AlarmCode(***)")
+    }
+}
+
+/// Error returned when a configuration fails validation.
+///
+/// Lists every problem found, one per line, rather than stopping at the first.
+#[derive(Debug, Clone)]
+pub struct ValidationError {
+    /// Human-readable description of each problem
+    pub problems: Vec<String>,
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Invalid configuration ({} problem(s)):", self.problems.len())?;
+        for problem in &self.problems {
+            writeln!(f, "  - {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+impl HomeAssistantConfig {
+    /// Returns a filter matching every entity used by the menus and favorites.
+    ///
+    /// Scene lists and summaries over a domain use all entities of their
+    /// domain. Custom button handlers only see the entity they're configured
+    /// with. Used entities listed in `lazy_entities` are kept as lazy, so the
+    /// regular update leaves them out.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use streamdeck_homeassistant::config::{HomeAssistantButton, HomeAssistantConfig};
+    /// let config: HomeAssistantConfig = serde_yaml::from_str(r#"
+    /// url: "ws://homeassistant.local:8123/api/websocket"
+    /// menu:
+    ///   name: "Home"
+    ///   buttons:
+    ///     - type: "toggle"
+    ///       entity_id: "light.kitchen"
+    ///     - type: "scene_list"
+    ///       name: "Scenes"
+    /// "#).unwrap();
+    /// let filter = config.entity_filter();
+    /// assert!(filter.matches("light.kitchen"));
+    /// assert!(filter.matches("scene.movie_night"));
+    /// assert!(!filter.matches("light.hallway"));
+    /// ```
+    pub fn entity_filter(&self) -> EntityFilter {
+        let mut filter = EntityFilter::default();
+        let mut buttons: Vec<&HomeAssistantButton> =
+            self.menu.buttons.iter().chain(&self.favorites).collect();
+        while let Some(button) = buttons.pop() {
+            match button {
+                HomeAssistantButton::Menu(menu) => buttons.extend(&menu.buttons),
+                HomeAssistantButton::SceneList { domain, .. }
+                | HomeAssistantButton::Summary {
+                    domain: Some(domain),
+                    ..
+                } => {
+                    filter.domains.insert(domain.clone());
+                }
+                HomeAssistantButton::Area { .. } => {
+                    filter
+                        .domains
+                        .extend(AREA_DOMAINS.iter().map(|domain| domain.to_string()));
+                }
+                _ => {}
+            }
+            filter
+                .entity_ids
+                .extend(button.entity_ids().into_iter().map(str::to_string));
+        }
+        for entity_id in &self.lazy_entities {
+            if filter.entity_ids.remove(entity_id) {
+                filter.lazy_entity_ids.insert(entity_id.clone());
+            }
+        }
+        filter
+    }
+
+    /// Creates the render configuration for button images.
+    ///
+    /// Labels are drawn on a single line; streamdeck-oxide doesn't wrap them.
+    /// A smaller `font_size` fits longer names. Its built-in font can't be
+    /// reused at another size, so a size needs a `font_file`.
+    ///
+    /// # Returns
+    ///
+    /// The render configuration, or an error if the font file can't be read
+    pub fn render_config(&self) -> Result<RenderConfig, Box<dyn std::error::Error>> {
+        let Some(path) = &self.font_file else {
+            return Ok(RenderConfig::default());
+        };
+        let font_data = std::fs::read(path)
+            .map_err(|e| format!("Failed to read font file {}: {}", path.display(), e))?;
+        // The render configuration keeps the font for as long as the program runs
+        let font_data: &'static [u8] = Box::leak(font_data.into_boxed_slice());
+        Ok(RenderConfig::new(
+            BUTTON_IMAGE_SIZE,
+            BUTTON_IMAGE_SIZE,
+            font_data,
+            self.font_size.unwrap_or(DEFAULT_FONT_SIZE),
+        ))
+    }
+
+    /// Validates the configuration.
+    ///
+    /// Checks that every entity ID starts with a domain matching its button type,
+    /// that menu names are non-empty, and that menus don't nest deeper than
+    /// `max_menu_depth`.
+    ///
+    /// # Returns
+    ///
+    /// Ok(()) if the configuration is valid, or an error listing every problem found
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        let mut problems = Vec::new();
+        self.menu.validate(&self.menu.name, 0, self.max_menu_depth, &mut problems);
+        let favorites = HomeAssistantMenu {
+            name: "favorites".to_string(),
+            buttons: self.favorites.clone(),
+            theme: None,
+            position: None,
+        };
+        favorites.validate(&favorites.name, 0, self.max_menu_depth, &mut problems);
+        for entity_id in &self.turn_off_on_exit {
+            let valid = match entity_id.split_once('.') {
+                Some((domain, object_id)) => !domain.is_empty() && !object_id.is_empty(),
+                None => false,
+            };
+            if !valid {
+                problems.push(format!(
+                    "turn_off_on_exit: '{}' is not a valid entity ID",
+                    entity_id
+                ));
+            }
+        }
+        if !self.lazy_entities.is_empty() && !self.only_cache_used_entities {
+            problems.push("lazy_entities: needs only_cache_used_entities: true".to_string());
+        }
+        if let Some(name) = &self.default_menu {
+            if self.menu.find_path(name).is_none() {
+                problems.push(format!("default_menu: no menu is named '{}'", name));
+            }
+        }
+        if let Some(size) = self.font_size {
+            if self.font_file.is_none() {
+                problems.push("font_size: needs a font_file".to_string());
+            }
+            if !(size > 0.0 && size.is_finite()) {
+                problems.push(format!("font_size: {} must be greater than zero", size));
+            }
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationError { problems })
+        }
+    }
+}
+
+impl HomeAssistantMenu {
+    /// Finds a menu by name, searching submenus depth-first.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the menu to find
+    ///
+    /// # Returns
+    ///
+    /// The submenus leading from this menu to the named one, ending with it.
+    /// Empty if this menu has the name, None if no menu has it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use streamdeck_homeassistant::config::HomeAssistantMenu;
+    /// let menu: HomeAssistantMenu = serde_yaml::from_str(r#"
+    /// name: "Home"
+    /// buttons:
+    ///   - type: "menu"
+    ///     name: "Upstairs"
+    ///     buttons:
+    ///       - type: "menu"
+    ///         name: "Bedroom"
+    ///         buttons: []
+    /// "#).unwrap();
+    /// let path = menu.find_path("Bedroom").unwrap();
+    /// assert_eq!(path.iter().map(|menu| menu.name.as_str()).collect::<Vec<_>>(), ["Upstairs", "Bedroom"]);
+    /// assert_eq!(menu.find_path("Home").map(|path| path.len()), Some(0));
+    /// assert!(menu.find_path("Garage").is_none());
+    /// ```
+    pub fn find_path(&self, name: &str) -> Option<Vec<&HomeAssistantMenu>> {
+        if self.name == name {
+            return Some(Vec::new());
+        }
+        self.buttons.iter().find_map(|button| {
+            let HomeAssistantButton::Menu(menu) = button else {
+                return None;
+            };
+            let mut path = menu.find_path(name)?;
+            path.insert(0, menu);
+            Some(path)
+        })
+    }
+
+    /// Returns the entities a `menu_toggle` button in this menu switches.
+    ///
+    /// These are the entities of the menu's switch, toggle, input boolean and
+    /// RGB light buttons using the given instance, without submenus.
+    ///
+    /// # Arguments
+    ///
+    /// * `instance` - The instance of the `menu_toggle` button
+    pub(crate) fn toggle_entity_ids(&self, instance: Option<&str>) -> Vec<String> {
+        let mut entity_ids = Vec::new();
+        for button in &self.buttons {
+            let entity_id = match button {
+                HomeAssistantButton::Switch { entity_id, .. }
+                | HomeAssistantButton::Toggle { entity_id, .. }
+                | HomeAssistantButton::InputBoolean { entity_id, .. }
+                | HomeAssistantButton::RgbLight { entity_id, .. } => entity_id,
+                _ => continue,
+            };
+            if button.instance() == instance && !entity_ids.contains(entity_id) {
+                entity_ids.push(entity_id.clone());
+            }
+        }
+        entity_ids
+    }
+
+    /// Collects validation problems for this menu and its submenus.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Breadcrumb path of this menu, used in messages
+    /// * `depth` - Nesting depth of this menu (0 for the root)
+    /// * `max_depth` - Maximum allowed nesting depth
+    /// * `problems` - List to append problems to
+    fn validate(&self, path: &str, depth: usize, max_depth: usize, problems: &mut Vec<String>) {
+        if self.name.trim().is_empty() {
+            problems.push(format!("{}: menu name must not be empty", path));
+        }
+        if depth > max_depth {
+            problems.push(format!(
+                "{}: menu is nested {} levels deep, the limit is {}",
+                path, depth, max_depth
+            ));
+        }
+        let mut pinned: Vec<(ButtonPosition, usize)> = Vec::new();
+        for (index, button) in self.buttons.iter().enumerate() {
+            let Some(position) = button.position() else {
+                continue;
+            };
+            match pinned.iter().find(|(other, _)| *other == position) {
+                Some((_, other)) => problems.push(format!(
+                    "{}: buttons {} and {} are both pinned to ({}, {})",
+                    path,
+                    other + 1,
+                    index + 1,
+                    position.x,
+                    position.y
+                )),
+                None => pinned.push((position, index)),
+            }
+        }
+        for (index, button) in self.buttons.iter().enumerate() {
+            if let HomeAssistantButton::Menu(menu) = button {
+                let path = format!("{} > {}", path, menu.name);
+                menu.validate(&path, depth + 1, max_depth, problems);
+                continue;
+            }
+            let label = button.name().or(button.entity_id()).unwrap_or_default();
+            for problem in button.problems() {
+                problems.push(format!(
+                    "{}: button {} ('{}'): {}",
+                    path,
+                    index + 1,
+                    label,
+                    problem
+                ));
+            }
+        }
+    }
+}
+
+impl HomeAssistantButton {
+    /// Returns the entity ID controlled by this button, if any.
+    pub fn entity_id(&self) -> Option<&str> {
+        match self {
+            HomeAssistantButton::Switch { entity_id, .. }
+            | HomeAssistantButton::RgbLight { entity_id, .. }
+            | HomeAssistantButton::Fan { entity_id, .. }
+            | HomeAssistantButton::Lock { entity_id, .. }
+            | HomeAssistantButton::Sensor { entity_id, .. }
+            | HomeAssistantButton::InputNumber { entity_id, .. }
+            | HomeAssistantButton::InputBoolean { entity_id, .. }
+            | HomeAssistantButton::Vacuum { entity_id, .. }
+            | HomeAssistantButton::Humidifier { entity_id, .. }
+            | HomeAssistantButton::Climate { entity_id, .. }
+            | HomeAssistantButton::WaterHeater { entity_id, .. }
+            | HomeAssistantButton::CameraSnapshot { entity_id, .. }
+            | HomeAssistantButton::InputSelect { entity_id, .. }
+            | HomeAssistantButton::Select { entity_id, .. }
+            | HomeAssistantButton::CoverPosition { entity_id, .. }
+            | HomeAssistantButton::CoverControl { entity_id, .. }
+            | HomeAssistantButton::Alarm { entity_id, .. }
+            | HomeAssistantButton::Toggle { entity_id, .. }
+            | HomeAssistantButton::Momentary { entity_id, .. }
+            | HomeAssistantButton::Button { entity_id, .. }
+            | HomeAssistantButton::TimedOn { entity_id, .. }
+            | HomeAssistantButton::Weather { entity_id, .. }
+            | HomeAssistantButton::Gauge { entity_id, .. }
+            | HomeAssistantButton::Battery { entity_id, .. } => Some(entity_id),
+            HomeAssistantButton::Custom { entity_id, .. } => entity_id.as_deref(),
+            HomeAssistantButton::Group { .. }
+            | HomeAssistantButton::MenuToggle { .. }
+            | HomeAssistantButton::Template { .. }
+            | HomeAssistantButton::Summary { .. }
+            | HomeAssistantButton::Notify { .. }
+            | HomeAssistantButton::FireEvent { .. }
+            | HomeAssistantButton::Refresh { .. }
+            | HomeAssistantButton::SceneList { .. }
+            | HomeAssistantButton::Area { .. }
+            | HomeAssistantButton::Menu(_) => None,
+        }
+    }
+
+    /// Returns every entity ID controlled by this button.
+    pub fn entity_ids(&self) -> Vec<&str> {
+        match self {
+            HomeAssistantButton::Group { entity_ids, .. }
+            | HomeAssistantButton::Summary { entity_ids, .. } => {
+                entity_ids.iter().map(String::as_str).collect()
+            }
+            button => button.entity_id().into_iter().collect(),
+        }
+    }
+
+    /// Returns the name of the HomeAssistant instance this button uses, if any.
+    pub fn instance(&self) -> Option<&str> {
+        match self {
+            HomeAssistantButton::Switch { instance, .. }
+            | HomeAssistantButton::RgbLight { instance, .. }
+            | HomeAssistantButton::Fan { instance, .. }
+            | HomeAssistantButton::Lock { instance, .. }
+            | HomeAssistantButton::Sensor { instance, .. }
+            | HomeAssistantButton::InputNumber { instance, .. }
+            | HomeAssistantButton::InputBoolean { instance, .. }
+            | HomeAssistantButton::Vacuum { instance, .. }
+            | HomeAssistantButton::Humidifier { instance, .. }
+            | HomeAssistantButton::Climate { instance, .. }
+            | HomeAssistantButton::WaterHeater { instance, .. }
+            | HomeAssistantButton::CameraSnapshot { instance, .. }
+            | HomeAssistantButton::InputSelect { instance, .. }
+            | HomeAssistantButton::Select { instance, .. }
+            | HomeAssistantButton::CoverPosition { instance, .. }
+            | HomeAssistantButton::CoverControl { instance, .. }
+            | HomeAssistantButton::Alarm { instance, .. }
+            | HomeAssistantButton::Toggle { instance, .. }
+            | HomeAssistantButton::Momentary { instance, .. }
+            | HomeAssistantButton::Button { instance, .. }
+            | HomeAssistantButton::TimedOn { instance, .. }
+            | HomeAssistantButton::Weather { instance, .. }
+            | HomeAssistantButton::Gauge { instance, .. }
+            | HomeAssistantButton::Battery { instance, .. }
+            | HomeAssistantButton::Notify { instance, .. }
+            | HomeAssistantButton::FireEvent { instance, .. }
+            | HomeAssistantButton::Refresh { instance, .. }
+            | HomeAssistantButton::SceneList { instance, .. }
+            | HomeAssistantButton::Area { instance, .. }
+            | HomeAssistantButton::Custom { instance, .. }
+            | HomeAssistantButton::Summary { instance, .. }
+            | HomeAssistantButton::MenuToggle { instance, .. }
+            | HomeAssistantButton::Template { instance, .. }
+            | HomeAssistantButton::Group { instance, .. } => instance.as_deref(),
+            HomeAssistantButton::Menu(_) => None,
+        }
+    }
+
+    /// Returns the configured display name of this button, if any.
+    ///
+    /// Entity buttons may omit the name, in which case the entity's
+    /// `friendly_name` is displayed instead.
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            HomeAssistantButton::Switch { name, .. }
+            | HomeAssistantButton::RgbLight { name, .. }
+            | HomeAssistantButton::Fan { name, .. }
+            | HomeAssistantButton::Lock { name, .. }
+            | HomeAssistantButton::Sensor { name, .. }
+            | HomeAssistantButton::InputNumber { name, .. }
+            | HomeAssistantButton::InputBoolean { name, .. }
+            | HomeAssistantButton::Vacuum { name, .. }
+            | HomeAssistantButton::Humidifier { name, .. }
+            | HomeAssistantButton::Climate { name, .. }
+            | HomeAssistantButton::WaterHeater { name, .. }
+            | HomeAssistantButton::CameraSnapshot { name, .. }
+            | HomeAssistantButton::InputSelect { name, .. }
+            | HomeAssistantButton::Select { name, .. }
+            | HomeAssistantButton::CoverPosition { name, .. }
+            | HomeAssistantButton::CoverControl { name, .. }
+            | HomeAssistantButton::Alarm { name, .. }
+            | HomeAssistantButton::Toggle { name, .. }
+            | HomeAssistantButton::Momentary { name, .. }
+            | HomeAssistantButton::Button { name, .. }
+            | HomeAssistantButton::TimedOn { name, .. }
+            | HomeAssistantButton::Weather { name, .. }
+            | HomeAssistantButton::Gauge { name, .. }
+            | HomeAssistantButton::Battery { name, .. } => name.as_deref(),
+            HomeAssistantButton::Notify { name, .. }
+            | HomeAssistantButton::FireEvent { name, .. }
+            | HomeAssistantButton::Refresh { name, .. }
+            | HomeAssistantButton::SceneList { name, .. }
+            | HomeAssistantButton::Area { name, .. }
+            | HomeAssistantButton::Custom { name, .. }
+            | HomeAssistantButton::Summary { name, .. }
+            | HomeAssistantButton::MenuToggle { name, .. }
+            | HomeAssistantButton::Template { name, .. }
+            | HomeAssistantButton::Group { name, .. } => Some(name),
+            HomeAssistantButton::Menu(menu) => Some(&menu.name),
+        }
+    }
+
+    /// Returns how often the label of this button changes, if it rotates.
+    pub(crate) fn rotate_interval(&self) -> Option<std::time::Duration> {
+        match self {
+            HomeAssistantButton::Sensor {
+                rotate_labels,
+                rotate_secs,
+                ..
+            }
+            | HomeAssistantButton::Weather {
+                rotate_labels,
+                rotate_secs,
+                ..
+            } if !rotate_labels.is_empty() => {
+                Some(std::time::Duration::from_secs((*rotate_secs).max(1)))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the key this button is pinned to, if any.
+    pub fn position(&self) -> Option<ButtonPosition> {
+        match self {
+            HomeAssistantButton::Menu(menu) => menu.position,
+            HomeAssistantButton::Switch { position, .. }
+            | HomeAssistantButton::Toggle { position, .. }
+            | HomeAssistantButton::Momentary { position, .. }
+            | HomeAssistantButton::Button { position, .. }
+            | HomeAssistantButton::TimedOn { position, .. }
+            | HomeAssistantButton::InputBoolean { position, .. }
+            | HomeAssistantButton::RgbLight { position, .. }
+            | HomeAssistantButton::Fan { position, .. }
+            | HomeAssistantButton::Lock { position, .. }
+            | HomeAssistantButton::Sensor { position, .. }
+            | HomeAssistantButton::Weather { position, .. }
+            | HomeAssistantButton::Gauge { position, .. }
+            | HomeAssistantButton::Battery { position, .. }
+            | HomeAssistantButton::InputNumber { position, .. }
+            | HomeAssistantButton::Vacuum { position, .. }
+            | HomeAssistantButton::Humidifier { position, .. }
+            | HomeAssistantButton::Climate { position, .. }
+            | HomeAssistantButton::WaterHeater { position, .. }
+            | HomeAssistantButton::InputSelect { position, .. }
+            | HomeAssistantButton::Select { position, .. }
+            | HomeAssistantButton::CoverPosition { position, .. }
+            | HomeAssistantButton::CoverControl { position, .. }
+            | HomeAssistantButton::Notify { position, .. }
+            | HomeAssistantButton::FireEvent { position, .. }
+            | HomeAssistantButton::CameraSnapshot { position, .. }
+            | HomeAssistantButton::Custom { position, .. }
+            | HomeAssistantButton::SceneList { position, .. }
+            | HomeAssistantButton::Area { position, .. }
+            | HomeAssistantButton::Refresh { position, .. }
+            | HomeAssistantButton::Alarm { position, .. }
+            | HomeAssistantButton::Summary { position, .. }
+            | HomeAssistantButton::MenuToggle { position, .. }
+            | HomeAssistantButton::Template { position, .. }
+            | HomeAssistantButton::Group { position, .. } => *position,
+        }
+    }
+
+    /// Returns how long presses are ignored after a successful press, if at all.
+    pub(crate) fn cooldown(&self) -> Option<std::time::Duration> {
+        match self {
+            HomeAssistantButton::Switch { cooldown_secs, .. }
+            | HomeAssistantButton::Toggle { cooldown_secs, .. }
+            | HomeAssistantButton::Momentary { cooldown_secs, .. }
+            | HomeAssistantButton::Button { cooldown_secs, .. }
+            | HomeAssistantButton::TimedOn { cooldown_secs, .. }
+            | HomeAssistantButton::InputBoolean { cooldown_secs, .. }
+            | HomeAssistantButton::Lock { cooldown_secs, .. }
+            | HomeAssistantButton::InputSelect { cooldown_secs, .. }
+            | HomeAssistantButton::Select { cooldown_secs, .. }
+            | HomeAssistantButton::Refresh { cooldown_secs, .. }
+            | HomeAssistantButton::Notify { cooldown_secs, .. }
+            | HomeAssistantButton::FireEvent { cooldown_secs, .. }
+            | HomeAssistantButton::CameraSnapshot { cooldown_secs, .. }
+            | HomeAssistantButton::Custom { cooldown_secs, .. }
+            | HomeAssistantButton::MenuToggle { cooldown_secs, .. }
+            | HomeAssistantButton::Group { cooldown_secs, .. } => cooldown_secs
+                .filter(|secs| *secs > 0)
+                .map(std::time::Duration::from_secs),
+            _ => None,
+        }
+    }
+
+    /// Returns how often the menu showing this button needs to be redrawn, if at all.
+    ///
+    /// Rotating labels change at their own interval, cooldowns count down
+    /// every second, and templates are rendered again every `refresh_secs`.
+    pub(crate) fn refresh_interval(&self) -> Option<std::time::Duration> {
+        if let HomeAssistantButton::Template { refresh_secs, .. } = self {
+            return Some(std::time::Duration::from_secs((*refresh_secs).max(1)));
+        }
+        match self.cooldown() {
+            Some(_) => Some(std::time::Duration::from_secs(1)),
+            None => self.rotate_interval(),
+        }
+    }
+
+    /// Returns the entity domains this button type accepts, or none to accept any domain.
+    pub(crate) fn domains(&self) -> Vec<&str> {
+        match self {
+            HomeAssistantButton::Switch { .. } => vec!["switch"],
+            // The domain's own turn_on/turn_off services are called
+            HomeAssistantButton::Toggle { .. }
+            | HomeAssistantButton::Momentary { .. }
+            | HomeAssistantButton::TimedOn { .. } => vec![],
+            // Battery levels, brightness and the like come from many domains
+            HomeAssistantButton::Gauge { .. } | HomeAssistantButton::Battery { .. } => vec![],
+            HomeAssistantButton::RgbLight { .. } => vec!["light"],
+            HomeAssistantButton::Fan { .. } => vec!["fan"],
+            HomeAssistantButton::Lock { .. } => vec!["lock"],
+            HomeAssistantButton::Sensor { .. } => vec!["sensor", "binary_sensor"],
+            HomeAssistantButton::InputNumber { .. } => vec!["input_number"],
+            HomeAssistantButton::InputBoolean { .. } => vec!["input_boolean"],
+            HomeAssistantButton::Weather { .. } => vec!["weather"],
+            HomeAssistantButton::Vacuum { .. } => vec!["vacuum"],
+            HomeAssistantButton::Humidifier { .. } => vec!["humidifier"],
+            HomeAssistantButton::Climate { .. } => vec!["climate"],
+            HomeAssistantButton::WaterHeater { .. } => vec!["water_heater"],
+            HomeAssistantButton::CameraSnapshot { .. } => vec!["camera"],
+            HomeAssistantButton::InputSelect { .. } => vec!["input_select"],
+            HomeAssistantButton::Select { .. } => vec!["select"],
+            HomeAssistantButton::Button { .. } => vec!["button"],
+            HomeAssistantButton::CoverPosition { .. } | HomeAssistantButton::CoverControl { .. } => {
+                vec!["cover"]
+            }
+            HomeAssistantButton::Alarm { .. } => vec!["alarm_control_panel"],
+            // The homeassistant domain services work on entities of any domain
+            HomeAssistantButton::Group { domain, .. } if domain == "homeassistant" => vec![],
+            HomeAssistantButton::Group { domain, .. } => vec![domain],
+            // Entities come from the menu's other buttons, which are checked themselves
+            HomeAssistantButton::MenuToggle { .. } => vec![],
+            // Summaries may combine lights, switches and sensors alike
+            HomeAssistantButton::Summary { .. } => vec![],
+            HomeAssistantButton::Notify { .. }
+            | HomeAssistantButton::FireEvent { .. }
+            | HomeAssistantButton::Refresh { .. }
+            | HomeAssistantButton::Custom { .. }
+            | HomeAssistantButton::SceneList { .. }
+            | HomeAssistantButton::Area { .. }
+            | HomeAssistantButton::Template { .. }
+            | HomeAssistantButton::Menu(_) => vec![],
+        }
+    }
+
+    /// Returns every problem with the settings of this button, not counting submenus.
+    ///
+    /// The entity IDs are checked for every button type, on top of the checks
+    /// specific to the type, so a button with several mistakes reports all of them.
+    fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        match self {
+            HomeAssistantButton::Group { entity_ids, .. } if entity_ids.is_empty() => {
+                problems.push("group must list at least one entity".to_string());
+            }
+            HomeAssistantButton::Summary {
+                entity_ids,
+                domain: None,
+                ..
+            } if entity_ids.is_empty() => {
+                problems.push("summary needs entity_ids or a domain".to_string());
+            }
+            HomeAssistantButton::Template { template, .. } if template.trim().is_empty() => {
+                problems.push("template must not be empty".to_string());
+            }
+            HomeAssistantButton::FireEvent { event_type, .. } if event_type.trim().is_empty() => {
+                problems.push("event_type must not be empty".to_string());
+            }
+            HomeAssistantButton::FireEvent {
+                event_data: Some(data),
+                ..
+            } if !data.is_object() => {
+                problems.push("event_data must be an object".to_string());
+            }
+            HomeAssistantButton::Notify { service, .. } if service.trim().is_empty() => {
+                problems.push("notify service must not be empty".to_string());
+            }
+            HomeAssistantButton::CameraSnapshot { filename, .. } if filename.trim().is_empty() => {
+                problems.push("camera snapshot filename must not be empty".to_string());
+            }
+            HomeAssistantButton::Gauge { min, max, .. } if max <= min => {
+                problems.push("gauge max must be greater than min".to_string());
+            }
+            HomeAssistantButton::Battery { low, .. } if !(0.0..=100.0).contains(low) => {
+                problems.push("battery low level must be between 0 and 100".to_string());
+            }
+            HomeAssistantButton::Area { area_id, .. } if area_id.trim().is_empty() => {
+                problems.push("area_id must not be empty".to_string());
+            }
+            HomeAssistantButton::SceneList { domain, .. } if domain != "scene" && domain != "script" => {
+                problems.push(format!(
+                    "scene list domain must be 'scene' or 'script', not '{}'",
+                    domain
+                ));
+            }
+            _ => {}
+        }
+        if let HomeAssistantButton::Toggle {
+            transition: Some(secs),
+            ..
+        }
+        | HomeAssistantButton::RgbLight {
+            transition: Some(secs),
+            ..
+        } = self
+        {
+            if !(*secs >= 0.0 && secs.is_finite()) {
+                problems.push("transition must be zero or more seconds".to_string());
+            }
+        }
+        if let HomeAssistantButton::Toggle {
+            entity_id,
+            transition: Some(_),
+            ..
+        } = self
+        {
+            if !entity_id.starts_with("light.") {
+                problems.push("transition only works with lights".to_string());
+            }
+        }
+
+        let domains = self.domains();
+        for entity_id in self.entity_ids() {
+            let valid = match entity_id.split_once('.') {
+                Some((domain, object_id)) => {
+                    !object_id.is_empty() && (domains.is_empty() || domains.contains(&domain))
+                }
+                None => false,
+            };
+            if valid {
+                continue;
+            }
+            let expected = if domains.is_empty() {
+                "a domain prefix like 'light.'".to_string()
+            } else {
+                domains
+                    .iter()
+                    .map(|domain| format!("'{}.'", domain))
+                    .collect::<Vec<_>>()
+                    .join(" or ")
+            };
+            problems.push(format!(
+                "entity_id '{}' should start with {}",
+                entity_id, expected
+            ));
+        }
+        problems
+    }
+}
+
+/// Loads a configuration from a YAML, JSON or TOML file.
+///
+/// The format is chosen from the file extension: `.json` files are parsed as
+/// JSON, `.toml` files as TOML, everything else (including `.yaml` and
+/// `.yml`) as YAML. Buttons are tables with a `type` key in every format.
+///
+/// YAML files can pull in other YAML files with the `!include` tag, e.g.
+/// `buttons: !include kitchen.yaml`, with paths relative to the including
+/// file. Anchors, aliases and `<<` merge keys can be used to repeat parts
+/// of the configuration.
+///
+/// Configurations for older schema versions are upgraded with
+/// [`migrate_config`], logging a warning for every deprecated field.
+/// Unknown fields are rejected, so typos are caught; use
+/// [`load_config_with`] to ignore them instead.
 ///
 /// # Arguments
 ///
-/// * `arg` - Path to the YAML configuration file
+/// * `arg` - Path to the configuration file
 ///
 /// # Returns
 ///
-/// The parsed configuration or an error
+/// The parsed and validated configuration or an error
 ///
 /// # Example
 ///
@@ -55,8 +1513,708 @@ pub enum HomeAssistantButton {
 pub fn load_config<S: Into<String>>(
     arg: S,
 ) -> Result<HomeAssistantConfig, Box<dyn std::error::Error>> {
-    let file = std::fs::File::open(arg.into())?;
-    let reader = std::io::BufReader::new(file);
-    let config: HomeAssistantConfig = serde_yaml::from_reader(reader)?;
+    load_config_with(arg, true)
+}
+
+/// Loads a configuration like [`load_config`], optionally ignoring unknown fields.
+///
+/// With `strict` set to false, fields this version doesn't know, e.g. from a
+/// configuration shared with a newer release, are dropped with a warning
+/// instead of failing the whole load. Typos are then only reported in the log.
+///
+/// # Arguments
+///
+/// * `arg` - Path to the configuration file
+/// * `strict` - Whether unknown fields are an error
+///
+/// # Example
+///
+/// ```no_run
+/// use streamdeck_homeassistant::config;
+///
+/// let config = config::load_config_with("config.yaml", false).expect("Failed to load config");
+/// ```
+pub fn load_config_with<S: Into<String>>(
+    arg: S,
+    strict: bool,
+) -> Result<HomeAssistantConfig, Box<dyn std::error::Error>> {
+    let path = std::path::PathBuf::from(arg.into());
+    let has_extension = |name: &str| {
+        path.extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case(name))
+    };
+    let mut value: serde_yaml::Value = if has_extension("json") {
+        let file = std::fs::File::open(&path)?;
+        serde_json::from_reader(std::io::BufReader::new(file))?
+    } else if has_extension("toml") {
+        let text = std::fs::read_to_string(&path)?;
+        toml::from_str(&text).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?
+    } else {
+        load_yaml(&path, &mut Vec::new())?
+    };
+    let warnings =
+        migrate_config(&mut value).map_err(|e| format!("{}: {}", path.display(), e))?;
+    for warning in warnings {
+        log::warn!("{}: {}", path.display(), warning);
+    }
+    let config: HomeAssistantConfig = if strict {
+        serde_yaml::from_value(value)?
+    } else {
+        from_value_lenient(value, |warning| {
+            log::warn!("{}: {}", path.display(), warning)
+        })?
+    };
+    config.validate()?;
     Ok(config)
 }
+
+/// Deserializes a configuration, dropping unknown fields instead of failing.
+///
+/// `serde` can't switch `deny_unknown_fields` off at runtime, so keys the
+/// configuration, its menus and button positions don't declare are removed
+/// from the parsed value before deserializing it.
+///
+/// # Arguments
+///
+/// * `value` - The configuration as parsed from YAML or JSON
+/// * `warn` - Called with a warning for every field dropped
+fn from_value_lenient<F>(
+    mut value: serde_yaml::Value,
+    mut warn: F,
+) -> Result<HomeAssistantConfig, Box<dyn std::error::Error>>
+where
+    F: FnMut(String),
+{
+    let mut removed = Vec::new();
+    let fields = struct_fields::<HomeAssistantConfig>();
+    remove_unknown_fields(&mut value, fields, "", &mut removed);
+    if let Some(config) = value.as_mapping_mut() {
+        if let Some(menu) = config.get_mut("menu") {
+            remove_unknown_menu_fields(menu, "menu", false, &mut removed);
+        }
+        if let Some(serde_yaml::Value::Sequence(buttons)) = config.get_mut("favorites") {
+            remove_unknown_button_fields(buttons, "favorites", &mut removed);
+        }
+    }
+    for path in removed {
+        warn(format!("ignoring unknown field {}", path));
+    }
+    Ok(serde_yaml::from_value(value)?)
+}
+
+/// Removes the unknown fields of a menu, its buttons and its position.
+///
+/// # Arguments
+///
+/// * `value` - The menu as parsed from YAML or JSON
+/// * `path` - The path of the menu, for the warnings
+/// * `tagged` - Whether the menu is in a button list, where `type` tags it
+/// * `removed` - Receives the paths of the removed fields
+fn remove_unknown_menu_fields(
+    value: &mut serde_yaml::Value,
+    path: &str,
+    tagged: bool,
+    removed: &mut Vec<String>,
+) {
+    let mut fields = struct_fields::<HomeAssistantMenu>().to_vec();
+    if tagged {
+        fields.push("type");
+    }
+    remove_unknown_fields(value, &fields, path, removed);
+    if let Some(serde_yaml::Value::Sequence(buttons)) = value.get_mut("buttons") {
+        remove_unknown_button_fields(buttons, &format!("{}.buttons", path), removed);
+    }
+}
+
+/// Removes the unknown fields of submenus and button positions in a button list.
+///
+/// Other buttons accept unknown fields already, so they are left as they are.
+///
+/// # Arguments
+///
+/// * `buttons` - The buttons as parsed from YAML or JSON
+/// * `path` - The path of the list, for the warnings
+/// * `removed` - Receives the paths of the removed fields
+fn remove_unknown_button_fields(
+    buttons: &mut [serde_yaml::Value],
+    path: &str,
+    removed: &mut Vec<String>,
+) {
+    for (index, button) in buttons.iter_mut().enumerate() {
+        let path = format!("{}[{}]", path, index);
+        if button.get("type").and_then(serde_yaml::Value::as_str) == Some("menu") {
+            remove_unknown_menu_fields(button, &path, true, removed);
+        }
+        if let Some(position) = button.get_mut("position") {
+            let fields = struct_fields::<ButtonPosition>();
+            remove_unknown_fields(position, fields, &format!("{}.position", path), removed);
+        }
+    }
+}
+
+/// Removes the keys of a mapping that aren't among `fields`.
+///
+/// # Arguments
+///
+/// * `value` - The mapping to change in place; other values are left as they are
+/// * `fields` - The keys to keep
+/// * `path` - The path of `value`, for the warnings
+/// * `removed` - Receives the paths of the removed fields
+fn remove_unknown_fields(
+    value: &mut serde_yaml::Value,
+    fields: &[&str],
+    path: &str,
+    removed: &mut Vec<String>,
+) {
+    let Some(mapping) = value.as_mapping_mut() else {
+        return;
+    };
+    let unknown: Vec<String> = mapping
+        .keys()
+        .filter_map(serde_yaml::Value::as_str)
+        .filter(|key| !fields.contains(key))
+        .map(str::to_string)
+        .collect();
+    for key in unknown {
+        mapping.remove(key.as_str());
+        removed.push(if path.is_empty() {
+            key
+        } else {
+            format!("{}.{}", path, key)
+        });
+    }
+}
+
+/// Returns the field names a struct deriving `Deserialize` accepts.
+///
+/// The derived implementation passes them to `deserialize_struct`, so a
+/// deserializer that only records them keeps the list in sync with the type.
+fn struct_fields<T: serde::de::DeserializeOwned>() -> &'static [&'static str] {
+    struct FieldNames<'a>(&'a mut &'static [&'static str]);
+
+    impl<'de> serde::Deserializer<'de> for FieldNames<'_> {
+        type Error = serde::de::value::Error;
+
+        fn deserialize_any<V: serde::de::Visitor<'de>>(
+            self,
+            _visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            Err(serde::de::Error::custom("not a struct"))
+        }
+
+        fn deserialize_struct<V: serde::de::Visitor<'de>>(
+            self,
+            _name: &'static str,
+            fields: &'static [&'static str],
+            _visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            *self.0 = fields;
+            Err(serde::de::Error::custom("fields recorded"))
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+            bytes byte_buf option unit unit_struct newtype_struct seq tuple
+            tuple_struct map enum identifier ignored_any
+        }
+    }
+
+    let mut fields: &'static [&'static str] = &[];
+    let _ = T::deserialize(FieldNames(&mut fields));
+    fields
+}
+
+/// Upgrades a parsed configuration to [`CONFIG_VERSION`] in place.
+///
+/// Runs the migration of every version between the configuration's
+/// `version` (1 if missing) and the current one, then sets `version` to the
+/// current one. Configurations for a newer version are rejected rather than
+/// read with fields this build doesn't understand.
+///
+/// # Arguments
+///
+/// * `value` - The configuration as parsed from YAML or JSON
+///
+/// # Returns
+///
+/// Warnings about deprecated fields that were rewritten, or an error if the
+/// version is invalid or newer than this build supports
+///
+/// # Example
+///
+/// ```
+/// # use streamdeck_homeassistant::config::{migrate_config, CONFIG_VERSION};
+/// let mut value: serde_yaml::Value = serde_yaml::from_str("url: ws://ha/api/websocket").unwrap();
+/// assert!(migrate_config(&mut value).unwrap().is_empty());
+/// assert_eq!(value["version"].as_u64(), Some(CONFIG_VERSION as u64));
+///
+/// let mut newer: serde_yaml::Value = serde_yaml::from_str("version: 1000").unwrap();
+/// assert!(migrate_config(&mut newer).is_err());
+/// ```
+pub fn migrate_config(
+    value: &mut serde_yaml::Value,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    migrate(value, &MIGRATIONS)
+}
+
+/// Runs `migrations` on a configuration, the first upgrading version 1.
+///
+/// The current version is the one after the last migration, so tests can
+/// pass migrations of their own.
+fn migrate(
+    value: &mut serde_yaml::Value,
+    migrations: &[Migration],
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let current = migrations.len() as u64 + 1;
+    let config = value
+        .as_mapping_mut()
+        .ok_or("The configuration must be a mapping of settings")?;
+    let version = match config.get("version") {
+        None => 1,
+        Some(version) => version
+            .as_u64()
+            .filter(|version| *version >= 1)
+            .ok_or("version: must be a whole number of at least 1")?,
+    };
+    if version > current {
+        return Err(format!(
+            "version: the configuration is for version {}, but this build only reads up to version {}; \
+             update streamdeck-homeassistant",
+            version, current
+        )
+        .into());
+    }
+    let mut warnings = Vec::new();
+    for migration in &migrations[version as usize - 1..] {
+        migration(config, &mut warnings);
+    }
+    config.insert("version".into(), current.into());
+    Ok(warnings)
+}
+
+/// Loads a YAML file, replacing `!include` tags with the files they name.
+///
+/// # Arguments
+///
+/// * `path` - Path to the YAML file
+/// * `stack` - The files currently being included, used to detect cycles
+fn load_yaml(
+    path: &std::path::Path,
+    stack: &mut Vec<std::path::PathBuf>,
+) -> Result<serde_yaml::Value, Box<dyn std::error::Error>> {
+    let path = path
+        .canonicalize()
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    if stack.contains(&path) {
+        let chain: Vec<String> = stack
+            .iter()
+            .chain(std::iter::once(&path))
+            .map(|path| path.display().to_string())
+            .collect();
+        return Err(format!("Circular include: {}", chain.join(" -> ")).into());
+    }
+    let text = std::fs::read_to_string(&path)?;
+    let mut value: serde_yaml::Value = serde_yaml::from_str(&text)
+        .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+    let directory = path.parent().unwrap_or(std::path::Path::new(".")).to_path_buf();
+    stack.push(path);
+    resolve_includes(&mut value, &directory, stack)?;
+    stack.pop();
+    value.apply_merge()?;
+    Ok(value)
+}
+
+/// Replaces every `!include` tag within a YAML value with the named file.
+///
+/// # Arguments
+///
+/// * `value` - The YAML value to resolve in place
+/// * `directory` - The directory include paths are relative to
+/// * `stack` - The files currently being included, used to detect cycles
+fn resolve_includes(
+    value: &mut serde_yaml::Value,
+    directory: &std::path::Path,
+    stack: &mut Vec<std::path::PathBuf>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match value {
+        serde_yaml::Value::Tagged(tagged) if tagged.tag == "include" => {
+            let file = match &tagged.value {
+                serde_yaml::Value::String(file) => directory.join(file),
+                _ => return Err("!include needs a file path".into()),
+            };
+            *value = load_yaml(&file, stack)?;
+        }
+        serde_yaml::Value::Tagged(tagged) => resolve_includes(&mut tagged.value, directory, stack)?,
+        serde_yaml::Value::Sequence(items) => {
+            for item in items {
+                resolve_includes(item, directory, stack)?;
+            }
+        }
+        serde_yaml::Value::Mapping(mapping) => {
+            for (_, item) in mapping.iter_mut() {
+                resolve_includes(item, directory, stack)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lenient(text: &str) -> (Result<HomeAssistantConfig, String>, Vec<String>) {
+        let value: serde_yaml::Value = serde_yaml::from_str(text).unwrap();
+        let mut warnings = Vec::new();
+        let config = from_value_lenient(value, |warning| warnings.push(warning))
+            .map_err(|e| e.to_string());
+        (config, warnings)
+    }
+
+    /// Creates an empty directory for the files of a test.
+    fn test_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "streamdeck-homeassistant-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn load_config_resolves_nested_includes() {
+        let dir = test_dir("nested-include");
+        std::fs::create_dir_all(dir.join("rooms/lights")).unwrap();
+        std::fs::write(
+            dir.join("config.yaml"),
+            "url: ws://ha/api/websocket\nmenu:\n  name: Home\n  buttons: !include rooms/bedroom.yaml\n",
+        )
+        .unwrap();
+        // Paths are relative to the including file
+        std::fs::write(
+            dir.join("rooms/bedroom.yaml"),
+            "- type: menu\n  name: Bedroom\n  buttons: !include lights/bed.yaml\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("rooms/lights/bed.yaml"),
+            "- type: toggle\n  entity_id: light.bed\n",
+        )
+        .unwrap();
+
+        let config = load_config(dir.join("config.yaml").to_str().unwrap()).unwrap();
+        let HomeAssistantButton::Menu(bedroom) = &config.menu.buttons[0] else {
+            panic!("expected the bedroom menu");
+        };
+        assert_eq!(bedroom.name, "Bedroom");
+        assert_eq!(bedroom.buttons[0].entity_id(), Some("light.bed"));
+    }
+
+    #[test]
+    fn load_config_rejects_circular_includes() {
+        let dir = test_dir("circular-include");
+        std::fs::write(
+            dir.join("config.yaml"),
+            "url: ws://ha/api/websocket\nmenu:\n  name: Home\n  buttons: !include a.yaml\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("a.yaml"), "- !include b.yaml\n").unwrap();
+        std::fs::write(dir.join("b.yaml"), "!include a.yaml\n").unwrap();
+
+        let error = load_config(dir.join("config.yaml").to_str().unwrap())
+            .unwrap_err()
+            .to_string();
+        assert!(error.starts_with("Circular include: "), "{}", error);
+        // The chain names every file down to the one included again
+        assert!(error.contains("a.yaml -> ") && error.contains("b.yaml -> "), "{}", error);
+        assert!(error.ends_with("a.yaml"), "{}", error);
+    }
+
+    #[test]
+    fn load_config_merges_with_overrides() {
+        let dir = test_dir("merge");
+        std::fs::write(
+            dir.join("config.yaml"),
+            r#"url: ws://ha/api/websocket
+menu:
+  name: Home
+  buttons:
+    - &bed
+      type: toggle
+      entity_id: light.bed
+      name: Bed
+      cooldown_secs: 5
+    - <<: *bed
+      name: Reading
+    - <<: !include hallway.yaml
+      entity_id: light.hallway_spots
+"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("hallway.yaml"),
+            "type: toggle\nentity_id: light.hallway\nname: Hallway\n",
+        )
+        .unwrap();
+
+        let config = load_config(dir.join("config.yaml").to_str().unwrap()).unwrap();
+        let buttons = &config.menu.buttons;
+        assert_eq!(buttons[1].name(), Some("Reading"));
+        assert_eq!(buttons[1].entity_id(), Some("light.bed"));
+        assert_eq!(buttons[1].cooldown(), Some(std::time::Duration::from_secs(5)));
+        // Keys next to a merged include win over the included ones
+        assert_eq!(buttons[2].name(), Some("Hallway"));
+        assert_eq!(buttons[2].entity_id(), Some("light.hallway_spots"));
+    }
+
+    #[test]
+    fn cooldown_applies_to_every_actionable_button() {
+        let menu: HomeAssistantMenu = serde_yaml::from_str(
+            r#"
+name: Home
+buttons:
+  - { type: input_boolean, entity_id: input_boolean.guest, cooldown_secs: 3 }
+  - { type: timed_on, entity_id: fan.bath, duration_secs: 60, cooldown_secs: 3 }
+  - { type: lock, entity_id: lock.door, cooldown_secs: 3 }
+  - { type: refresh, name: Refresh, cooldown_secs: 3 }
+  - { type: input_select, entity_id: input_select.mode, cooldown_secs: 3 }
+  - { type: select, entity_id: select.program, cooldown_secs: 3 }
+  - { type: fire_event, name: Scene, event_type: desk_scene_cycle, cooldown_secs: 3 }
+  - { type: lock, entity_id: lock.back_door, cooldown_secs: 0 }
+"#,
+        )
+        .unwrap();
+        let cooldowns: Vec<_> = menu.buttons.iter().map(HomeAssistantButton::cooldown).collect();
+        let three = Some(std::time::Duration::from_secs(3));
+        assert_eq!(cooldowns, [three, three, three, three, three, three, three, None]);
+    }
+
+    #[test]
+    fn migrate_runs_every_step_after_the_configured_version() {
+        fn rename_label(config: &mut serde_yaml::Mapping, warnings: &mut Vec<String>) {
+            if let Some(label) = config.remove("label") {
+                config.insert("name".into(), label);
+                warnings.push("label: renamed to name".to_string());
+            }
+        }
+        fn drop_legacy(config: &mut serde_yaml::Mapping, warnings: &mut Vec<String>) {
+            if config.remove("legacy").is_some() {
+                warnings.push("legacy: no longer used".to_string());
+            }
+        }
+        let migrations: [Migration; 2] = [rename_label, drop_legacy];
+
+        let mut value: serde_yaml::Value =
+            serde_yaml::from_str("label: Home\nlegacy: true").unwrap();
+        let warnings = migrate(&mut value, &migrations).unwrap();
+        assert_eq!(warnings, ["label: renamed to name", "legacy: no longer used"]);
+        assert_eq!(value["name"].as_str(), Some("Home"));
+        assert_eq!(value["version"].as_u64(), Some(3));
+
+        // Version 2 already has `name`, so only the second step runs
+        let mut value: serde_yaml::Value =
+            serde_yaml::from_str("version: 2\nlabel: Home\nlegacy: true").unwrap();
+        let warnings = migrate(&mut value, &migrations).unwrap();
+        assert_eq!(warnings, ["legacy: no longer used"]);
+        assert_eq!(value["label"].as_str(), Some("Home"));
+        assert_eq!(value["version"].as_u64(), Some(3));
+
+        let mut value: serde_yaml::Value = serde_yaml::from_str("version: 4").unwrap();
+        assert!(migrate(&mut value, &migrations).is_err());
+        let mut value: serde_yaml::Value = serde_yaml::from_str("version: 0").unwrap();
+        assert!(migrate(&mut value, &migrations).is_err());
+    }
+
+    #[test]
+    fn load_config_reads_toml() {
+        let dir = test_dir("toml");
+        let path = dir.join("config.toml");
+        std::fs::write(
+            &path,
+            r#"url = "ws://ha/api/websocket"
+theme = "dark"
+
+[menu]
+name = "Home"
+
+[[menu.buttons]]
+type = "toggle"
+entity_id = "light.hallway"
+active_color = [255, 191, 0]
+position = { x = 0, y = 2 }
+
+[[menu.buttons]]
+type = "menu"
+name = "Kitchen"
+buttons = [{ type = "switch", entity_id = "switch.kettle", cooldown_secs = 10 }]
+"#,
+        )
+        .unwrap();
+        let config = load_config(path.to_str().unwrap()).unwrap();
+        assert_eq!(config.theme, ThemeName::Dark);
+        let buttons = &config.menu.buttons;
+        assert_eq!(buttons[0].entity_id(), Some("light.hallway"));
+        assert_eq!(buttons[0].position(), Some(ButtonPosition { x: 0, y: 2 }));
+        let HomeAssistantButton::Menu(kitchen) = &buttons[1] else {
+            panic!("expected the kitchen menu");
+        };
+        assert_eq!(kitchen.buttons[0].entity_id(), Some("switch.kettle"));
+        assert_eq!(
+            kitchen.buttons[0].cooldown(),
+            Some(std::time::Duration::from_secs(10))
+        );
+    }
+
+    #[test]
+    fn configurations_round_trip_through_every_format() {
+        let config: HomeAssistantConfig = serde_yaml::from_str(
+            r#"
+url: ws://ha/api/websocket
+theme: dark
+home_button: true
+favorites:
+  - { type: toggle, entity_id: script.good_night, name: Night }
+menu:
+  name: Home
+  buttons:
+    - { type: switch, entity_id: switch.fan, active_color: [255, 191, 0] }
+    - { type: toggle, entity_id: light.hall, transition: 1.5, position: { x: 4, y: 0 } }
+    - { type: summary, name: Avg, domain: sensor, aggregate: average }
+    - { type: fire_event, name: Scene, event_type: cycle, event_data: { source: deck } }
+    - { type: area, area_id: bedroom, name: Bedroom }
+    - type: menu
+      name: Kitchen
+      theme: light
+      buttons:
+        - { type: timed_on, entity_id: fan.bath, duration_secs: 600, cooldown_secs: 5 }
+"#,
+        )
+        .unwrap();
+        let expected = serde_json::to_value(&config).unwrap();
+
+        let dir = test_dir("round-trip");
+        let files = [
+            ("config.yaml", serde_yaml::to_string(&config).unwrap()),
+            ("config.json", serde_json::to_string(&config).unwrap()),
+            ("config.toml", toml::to_string(&config).unwrap()),
+        ];
+        for (file, text) in files {
+            let path = dir.join(file);
+            std::fs::write(&path, text).unwrap();
+            let loaded = load_config(path.to_str().unwrap()).unwrap();
+            assert_eq!(serde_json::to_value(&loaded).unwrap(), expected, "{}", file);
+        }
+    }
+
+    #[test]
+    fn load_config_reads_json() {
+        let dir = test_dir("json");
+        let path = dir.join("config.json");
+        std::fs::write(
+            &path,
+            r#"{
+                "url": "ws://ha/api/websocket",
+                "menu": {
+                    "name": "Home",
+                    "buttons": [{ "type": "switch", "entity_id": "switch.fan" }]
+                }
+            }"#,
+        )
+        .unwrap();
+        let config = load_config(path.to_str().unwrap()).unwrap();
+        assert_eq!(config.menu.name, "Home");
+        assert_eq!(config.menu.buttons[0].entity_id(), Some("switch.fan"));
+        assert_eq!(config.version, CONFIG_VERSION);
+
+        // JSON files are validated like YAML ones
+        std::fs::write(
+            &path,
+            r#"{ "url": "ws://ha/api/websocket", "menu": { "name": "", "buttons": [] } }"#,
+        )
+        .unwrap();
+        let error = load_config(path.to_str().unwrap()).unwrap_err();
+        assert!(error.to_string().contains("menu name must not be empty"), "{}", error);
+    }
+
+    #[test]
+    fn validate_reports_every_problem_of_a_button() {
+        let config: HomeAssistantConfig = serde_yaml::from_str(
+            "
+            url: ws://ha/api/websocket
+            menu:
+              name: Home
+              buttons:
+                - type: gauge
+                  entity_id: phone_battery
+                  min: 100
+                  max: 0
+                - type: toggle
+                  entity_id: switch.fan
+                  transition: -1
+            ",
+        )
+        .unwrap();
+        assert_eq!(
+            config.validate().unwrap_err().problems,
+            vec![
+                "Home: button 1 ('phone_battery'): gauge max must be greater than min",
+                "Home: button 1 ('phone_battery'): entity_id 'phone_battery' should start with a domain prefix like 'light.'",
+                "Home: button 2 ('switch.fan'): transition must be zero or more seconds",
+                "Home: button 2 ('switch.fan'): transition only works with lights",
+            ]
+        );
+    }
+
+    #[test]
+    fn struct_fields_lists_the_declared_fields() {
+        assert_eq!(struct_fields::<ButtonPosition>(), ["x", "y"]);
+        assert_eq!(
+            struct_fields::<HomeAssistantMenu>(),
+            ["name", "buttons", "theme", "position"]
+        );
+    }
+
+    #[test]
+    fn lenient_drops_unknown_fields() {
+        let (config, warnings) = lenient(
+            "
+            url: ws://ha/api/websocket
+            future_option: true
+            menu:
+              name: Home
+              color: blue
+              buttons:
+                - type: menu
+                  name: Lights
+                  color: red
+                  buttons: []
+                  position: { x: 1, y: 0, z: 2 }
+            favorites:
+              - type: toggle
+                entity_id: light.hallway
+                position: { x: 0, y: 0, z: 1 }
+            ",
+        );
+        assert!(config.is_ok(), "{:?}", config.err());
+        assert_eq!(
+            warnings,
+            vec![
+                "ignoring unknown field future_option",
+                "ignoring unknown field menu.color",
+                "ignoring unknown field menu.buttons[0].color",
+                "ignoring unknown field menu.buttons[0].position.z",
+                "ignoring unknown field favorites[0].position.z",
+            ]
+        );
+    }
+
+    #[test]
+    fn lenient_keeps_other_errors() {
+        let (config, warnings) = lenient("menu: { name: Home, buttons: [] }");
+        assert!(config.unwrap_err().contains("missing field `url`"));
+        assert!(warnings.is_empty());
+    }
+}
@@ -0,0 +1,131 @@
+//! Extension point for button types that aren't built in.
+//!
+//! A `custom` button in the configuration names a handler, which is looked
+//! up in the `ButtonHandlers` registered in the `PluginContext` when the
+//! button is rendered or pressed.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use streamdeck_oxide::plugins::PluginContext;
+
+use crate::backend::HassBackend;
+
+/// The configuration of a custom button, passed to its handler.
+#[derive(Debug, Clone)]
+pub struct CustomButtonConfig {
+    /// The display name of the button
+    pub name: String,
+    /// The entity the button refers to, if any
+    pub entity_id: Option<String>,
+    /// Free-form options from the configuration, or null if none were given
+    pub options: serde_json::Value,
+}
+
+/// Handles presses of custom buttons.
+///
+/// Custom buttons are shown as toggles: `is_active` decides whether the
+/// button is highlighted, and `press` is called on every press.
+///
+/// # Example
+///
+/// ```no_run
+/// use streamdeck_homeassistant::custom::{ButtonHandler, CustomButtonConfig};
+/// use streamdeck_homeassistant::backend::HassBackend;
+///
+/// struct PausePrint;
+///
+/// #[async_trait::async_trait]
+/// impl ButtonHandler for PausePrint {
+///     async fn press(
+///         &self,
+///         hass: &dyn HassBackend,
+///         button: &CustomButtonConfig,
+///     ) -> Result<(), Box<dyn std::error::Error>> {
+///         hass.call_service("printer", "pause", Some(button.options.clone())).await
+///     }
+/// }
+/// ```
+#[async_trait::async_trait]
+pub trait ButtonHandler: Send + Sync {
+    /// Returns whether the button should be shown as active. Defaults to false.
+    ///
+    /// # Arguments
+    ///
+    /// * `hass` - The connection the button uses
+    /// * `button` - The button configuration
+    async fn is_active(
+        &self,
+        hass: &dyn HassBackend,
+        button: &CustomButtonConfig,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let _ = (hass, button);
+        Ok(false)
+    }
+
+    /// Handles a press of the button.
+    ///
+    /// # Arguments
+    ///
+    /// * `hass` - The connection the button uses
+    /// * `button` - The button configuration
+    async fn press(
+        &self,
+        hass: &dyn HassBackend,
+        button: &CustomButtonConfig,
+    ) -> Result<(), Box<dyn std::error::Error>>;
+}
+
+/// A registry of custom button handlers, keyed by the name used in the configuration.
+///
+/// Register this in the `PluginContext` alongside the HomeAssistant connection.
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::sync::Arc;
+/// # use streamdeck_homeassistant::custom::{ButtonHandler, ButtonHandlers};
+/// # fn example(pause_print: Arc<dyn ButtonHandler>) {
+/// let handlers = ButtonHandlers::new().with_handler("pause_print", pause_print);
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct ButtonHandlers {
+    handlers: BTreeMap<String, Arc<dyn ButtonHandler>>,
+}
+
+impl ButtonHandlers {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a handler under the given name.
+    pub fn with_handler<S: Into<String>>(mut self, name: S, handler: Arc<dyn ButtonHandler>) -> Self {
+        self.handlers.insert(name.into(), handler);
+        self
+    }
+
+    /// Gets a handler by name.
+    pub fn get(&self, name: &str) -> Option<Arc<dyn ButtonHandler>> {
+        self.handlers.get(name).cloned()
+    }
+}
+
+/// Looks up a custom button handler in the plugin context.
+///
+/// # Arguments
+///
+/// * `context` - The plugin context
+/// * `name` - The handler name from the configuration
+pub(crate) async fn get_handler(
+    context: &PluginContext,
+    name: &str,
+) -> Result<Arc<dyn ButtonHandler>, Box<dyn std::error::Error>> {
+    let handlers = context
+        .get_context::<ButtonHandlers>()
+        .await
+        .ok_or("Custom button used but no ButtonHandlers registered")?;
+    handlers
+        .get(name)
+        .ok_or_else(|| format!("Unknown custom button handler '{}'", name).into())
+}
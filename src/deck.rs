@@ -0,0 +1,445 @@
+//! Stream Deck device helpers.
+//!
+//! This module picks the grid size matching the connected Stream Deck at
+//! runtime, so the same configuration works on any supported device.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use tokio::sync::watch;
+
+use streamdeck_oxide::{
+    elgato_streamdeck::{info::Kind, AsyncStreamDeck},
+    generic_array::{
+        typenum::{U2, U3, U4, U5, U6, U8},
+        ArrayLength,
+    },
+    md_icons,
+    plugins::{Plugin, PluginContext, PluginNavigation},
+    run_with_external_triggers,
+    view::customizable::CustomizableView,
+    ButtonState, ExternalTrigger, RenderConfig, Theme, View,
+};
+
+use crate::{
+    buttons::StaticButton,
+    config::{HomeAssistantConfig, HomeAssistantMenu},
+    hass::HassError,
+    plugins::{get_config, HomeAssistantPlugin, MenuRefresh},
+};
+
+/// Calls a function generic over the grid size with the grid of a Stream Deck kind.
+macro_rules! dispatch {
+    ($kind:expr, $run:ident($($arg:expr),* $(,)?)) => {
+        match ($kind.column_count(), $kind.row_count()) {
+            _ if $kind == Kind::Pedal => Err("The Stream Deck Pedal has no display".into()),
+            (3, 2) => $run::<U3, U2>($($arg),*).await,
+            (4, 2) => $run::<U4, U2>($($arg),*).await,
+            (5, 3) => $run::<U5, U3>($($arg),*).await,
+            (6, 3) => $run::<U6, U3>($($arg),*).await,
+            (3, 5) => $run::<U3, U5>($($arg),*).await,
+            (8, 4) => $run::<U8, U4>($($arg),*).await,
+            (columns, rows) => Err(format!(
+                "Unsupported Stream Deck {:?} with a {}x{} key grid",
+                $kind, columns, rows
+            )
+            .into()),
+        }
+    };
+}
+
+/// How often to check whether a newly shown menu needs refreshing.
+const IDLE_REFRESH_CHECK: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Runs the HomeAssistant menu on a Stream Deck with a fixed grid size.
+///
+/// # Arguments
+///
+/// * `theme` - The theme used to render buttons
+/// * `render_config` - The render configuration for button images
+/// * `deck` - The connected Stream Deck
+/// * `context` - The plugin context holding the configuration and connection
+/// * `menu` - The menu displayed at startup
+pub async fn run_sized<W, H>(
+    theme: Theme,
+    render_config: RenderConfig,
+    deck: Arc<AsyncStreamDeck>,
+    context: PluginContext,
+    menu: HomeAssistantMenu,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    W: ArrayLength,
+    H: ArrayLength,
+{
+    run_menus::<W, H>(theme, render_config, deck, context, menu, None).await
+}
+
+/// Runs the HomeAssistant menu on a Stream Deck with a fixed grid size,
+/// applying every configuration sent to the `watch::Receiver<HomeAssistantConfig>`
+/// registered in the plugin context.
+///
+/// A reload returns to the new root menu, or its `default_menu`, and the
+/// other settings such as the theme, favorites and home button apply to
+/// every menu drawn afterwards. The plugin context, including the
+/// HomeAssistant connection and its state cache, is kept across reloads.
+///
+/// # Arguments
+///
+/// * `theme` - The theme used to render navigation buttons
+/// * `render_config` - The render configuration for button images
+/// * `deck` - The connected Stream Deck
+/// * `context` - The plugin context holding the configuration receiver and connection
+///
+/// # Returns
+///
+/// An error if the context holds no configuration receiver, or if running fails
+pub async fn run_sized_with_reload<W, H>(
+    theme: Theme,
+    render_config: RenderConfig,
+    deck: Arc<AsyncStreamDeck>,
+    context: PluginContext,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    W: ArrayLength,
+    H: ArrayLength,
+{
+    let mut configs = context
+        .get_context::<watch::Receiver<HomeAssistantConfig>>()
+        .await
+        .ok_or("Failed to get watch::Receiver<HomeAssistantConfig>")?
+        .as_ref()
+        .clone();
+    let menu = configs.borrow_and_update().menu.clone();
+    run_menus::<W, H>(theme, render_config, deck, context, menu, Some(configs)).await
+}
+
+/// Runs the HomeAssistant menu on a Stream Deck with a fixed grid size,
+/// returning to the new root menu whenever a configuration is sent.
+///
+/// # Arguments
+///
+/// * `theme` - The theme used to render buttons
+/// * `render_config` - The render configuration for button images
+/// * `deck` - The connected Stream Deck
+/// * `context` - The plugin context holding the configuration and connection
+/// * `menu` - The menu displayed at startup
+/// * `configs` - Receiver holding the current configuration, if it can be reloaded
+async fn run_menus<W, H>(
+    theme: Theme,
+    render_config: RenderConfig,
+    deck: Arc<AsyncStreamDeck>,
+    context: PluginContext,
+    menu: HomeAssistantMenu,
+    configs: Option<watch::Receiver<HomeAssistantConfig>>,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    W: ArrayLength,
+    H: ArrayLength,
+{
+    let (sender, receiver) =
+        tokio::sync::mpsc::channel::<ExternalTrigger<PluginNavigation<W, H>, W, H, PluginContext>>(1);
+    let refresh = MenuRefresh::new();
+    let start = get_config(&context)
+        .await
+        .and_then(|config| config.default_menu.clone());
+    sender
+        .send(root_trigger(menu, refresh.clone(), start.as_deref()))
+        .await?;
+
+    // Switch to the new root menu on every reload
+    let reload_sender = sender.clone();
+    let reload_refresh = refresh.clone();
+    let forward_reloads = async move {
+        if let Some(mut configs) = configs {
+            while configs.changed().await.is_ok() {
+                let config = configs.borrow_and_update().clone();
+                log::info!("Reloading menu '{}'", config.menu.name);
+                let trigger = root_trigger(
+                    config.menu,
+                    reload_refresh.clone(),
+                    config.default_menu.as_deref(),
+                );
+                if reload_sender.send(trigger).await.is_err() {
+                    break;
+                }
+            }
+        }
+        // Keep showing the last menu once nobody can send reloads
+        std::future::pending::<()>().await
+    };
+
+    // Re-render menus with rotating labels; the trigger is ignored once
+    // another plugin's view is on screen
+    let refresh_menus = async move {
+        loop {
+            let interval = refresh
+                .current()
+                .map(|(_, interval)| interval)
+                .unwrap_or(IDLE_REFRESH_CHECK);
+            tokio::time::sleep(interval).await;
+            if let Some((navigation, _)) = refresh.current() {
+                if sender.send(ExternalTrigger::new(navigation, false)).await.is_err() {
+                    break;
+                }
+            }
+        }
+        std::future::pending::<()>().await
+    };
+
+    tokio::select! {
+        result = run_with_external_triggers(theme, render_config, deck, context, receiver) => result,
+        _ = forward_reloads => unreachable!(),
+        _ = refresh_menus => unreachable!(),
+    }
+}
+
+/// Creates a trigger switching to the given root menu.
+///
+/// # Arguments
+///
+/// * `menu` - The root menu
+/// * `refresh` - Where menus ask to be refreshed
+/// * `start` - Name of the submenu to show instead, whose "Back" buttons lead
+///   up to the root menu
+fn root_trigger<W, H>(
+    menu: HomeAssistantMenu,
+    refresh: MenuRefresh<W, H>,
+    start: Option<&str>,
+) -> ExternalTrigger<PluginNavigation<W, H>, W, H, PluginContext>
+where
+    W: ArrayLength,
+    H: ArrayLength,
+{
+    let submenus: Vec<HomeAssistantMenu> = match start.map(|name| (name, menu.find_path(name))) {
+        Some((_, Some(path))) => path.into_iter().cloned().collect(),
+        Some((name, None)) => {
+            log::warn!("No menu is named '{}'; starting at '{}'", name, menu.name);
+            Vec::new()
+        }
+        None => Vec::new(),
+    };
+    let root = HomeAssistantPlugin {
+        menu,
+        back_navigation: None,
+        page: 0,
+        theme: None,
+        path: Vec::new(),
+        refresh: Some(refresh),
+        root: None,
+    };
+    let plugin = submenus
+        .into_iter()
+        .fold(root, |parent, submenu| parent.submenu(submenu));
+    ExternalTrigger::new(PluginNavigation::<W, H>::new(plugin), true)
+}
+
+/// Runs the HomeAssistant menu on a Stream Deck, choosing the grid size from its kind.
+///
+/// # Arguments
+///
+/// * `kind` - The kind of the connected Stream Deck
+/// * `theme` - The theme used to render buttons
+/// * `render_config` - The render configuration for button images
+/// * `deck` - The connected Stream Deck
+/// * `context` - The plugin context holding the configuration and connection
+/// * `menu` - The menu displayed at startup
+///
+/// # Returns
+///
+/// An error if the device kind has no supported key grid, or if running fails
+pub async fn run(
+    kind: Kind,
+    theme: Theme,
+    render_config: RenderConfig,
+    deck: Arc<AsyncStreamDeck>,
+    context: PluginContext,
+    menu: HomeAssistantMenu,
+) -> Result<(), Box<dyn std::error::Error>> {
+    dispatch!(kind, run_sized(theme, render_config, deck, context, menu))
+}
+
+/// Runs the HomeAssistant menu on a Stream Deck, choosing the grid size from
+/// its kind, and applies every configuration sent to the
+/// `watch::Receiver<HomeAssistantConfig>` registered in the plugin context.
+///
+/// The receiver takes the place of a fixed `HomeAssistantConfig` in the
+/// context. The `theme` argument only styles the navigation buttons; the
+/// configured theme of the current configuration styles everything else.
+///
+/// # Arguments
+///
+/// * `kind` - The kind of the connected Stream Deck
+/// * `theme` - The theme used to render navigation buttons
+/// * `render_config` - The render configuration for button images
+/// * `deck` - The connected Stream Deck
+/// * `context` - The plugin context holding the configuration receiver and connection
+///
+/// # Returns
+///
+/// An error if the device kind has no supported key grid, if the context
+/// holds no configuration receiver, or if running fails
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::{any::{Any, TypeId}, collections::BTreeMap, sync::Arc};
+/// # use streamdeck_homeassistant::{config::{self, HomeAssistantConfig}, deck, PersistentHassConnection};
+/// # use streamdeck_oxide::{elgato_streamdeck::{info::Kind, AsyncStreamDeck}, plugins::PluginContext, RenderConfig, Theme};
+/// # async fn example(kind: Kind, deck: Arc<AsyncStreamDeck>, hass: Arc<PersistentHassConnection>) -> Result<(), Box<dyn std::error::Error>> {
+/// let config = config::load_config("config.yaml")?;
+/// let (reload, configs) = tokio::sync::watch::channel(config);
+/// let context = PluginContext::new(BTreeMap::from([
+///     (
+///         TypeId::of::<tokio::sync::watch::Receiver<HomeAssistantConfig>>(),
+///         Box::new(Arc::new(configs)) as Box<dyn Any + Send + Sync>,
+///     ),
+///     (TypeId::of::<PersistentHassConnection>(), Box::new(hass)),
+/// ]));
+///
+/// // Elsewhere, e.g. on SIGHUP
+/// reload.send(config::load_config("config.yaml")?)?;
+///
+/// deck::run_with_reload(kind, Theme::light(), RenderConfig::default(), deck, context).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn run_with_reload(
+    kind: Kind,
+    theme: Theme,
+    render_config: RenderConfig,
+    deck: Arc<AsyncStreamDeck>,
+    context: PluginContext,
+) -> Result<(), Box<dyn std::error::Error>> {
+    dispatch!(kind, run_sized_with_reload(theme, render_config, deck, context))
+}
+
+/// A screen explaining why the menu can't be shown.
+#[derive(Clone)]
+struct ErrorScreen {
+    /// Short description of the error
+    headline: String,
+    /// Short suggestion how to fix it
+    hint: String,
+}
+
+#[async_trait::async_trait]
+impl<W, H> Plugin<W, H> for ErrorScreen
+where
+    W: ArrayLength,
+    H: ArrayLength,
+{
+    fn name(&self) -> &'static str {
+        "ErrorScreen"
+    }
+
+    async fn get_view(
+        &self,
+        _context: PluginContext,
+    ) -> Result<
+        Box<dyn View<W, H, PluginContext, PluginNavigation<W, H>>>,
+        Box<dyn std::error::Error>,
+    > {
+        let mut view = CustomizableView::new();
+        view.set_button(
+            0,
+            0,
+            StaticButton::new(
+                self.headline.clone(),
+                Some(md_icons::filled::ICON_ERROR),
+                ButtonState::Error,
+            ),
+        )?;
+        view.set_button(
+            1,
+            0,
+            StaticButton::new(
+                self.hint.clone(),
+                Some(md_icons::filled::ICON_BUILD),
+                ButtonState::Default,
+            ),
+        )?;
+        Ok(Box::new(view))
+    }
+}
+
+/// Shows an error on a Stream Deck with a fixed grid size until it disconnects.
+///
+/// # Arguments
+///
+/// * `theme` - The theme used to render buttons
+/// * `render_config` - The render configuration for button images
+/// * `deck` - The connected Stream Deck
+/// * `screen` - The error to show
+async fn show_error_sized<W, H>(
+    theme: Theme,
+    render_config: RenderConfig,
+    deck: Arc<AsyncStreamDeck>,
+    screen: ErrorScreen,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    W: ArrayLength,
+    H: ArrayLength,
+{
+    let (sender, receiver) =
+        tokio::sync::mpsc::channel::<ExternalTrigger<PluginNavigation<W, H>, W, H, PluginContext>>(1);
+    sender
+        .send(ExternalTrigger::new(PluginNavigation::<W, H>::new(screen), true))
+        .await?;
+    let context = PluginContext::new(BTreeMap::new());
+    run_with_external_triggers(theme, render_config, deck, context, receiver).await
+}
+
+/// Shows why the menu can't be shown on the Stream Deck itself, e.g. when
+/// connecting to HomeAssistant failed at startup.
+///
+/// A [`HassError`] is shown as a short headline and a hint such as
+/// "Auth failed" and "Check token"; other errors only say where to look.
+/// Runs until the Stream Deck disconnects, so it's usually raced against a
+/// shutdown signal.
+///
+/// # Arguments
+///
+/// * `kind` - The kind of the connected Stream Deck
+/// * `theme` - The theme used to render buttons
+/// * `render_config` - The render configuration for button images
+/// * `deck` - The connected Stream Deck
+/// * `error` - The error to show
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::sync::Arc;
+/// # use streamdeck_homeassistant::{deck, PersistentHassConnection};
+/// # use streamdeck_oxide::{elgato_streamdeck::{info::Kind, AsyncStreamDeck}, RenderConfig, Theme};
+/// # async fn example(kind: Kind, deck: Arc<AsyncStreamDeck>) -> Result<(), Box<dyn std::error::Error>> {
+/// let hass = match PersistentHassConnection::builder("ws://homeassistant.local:8123/api/websocket", "token")
+///     .build()
+///     .await
+/// {
+///     Ok(hass) => hass,
+///     Err(e) => {
+///         deck::show_error(kind, Theme::dark(), RenderConfig::default(), deck, e.as_ref()).await?;
+///         return Err(e);
+///     }
+/// };
+/// # Ok(())
+/// # }
+/// ```
+pub async fn show_error(
+    kind: Kind,
+    theme: Theme,
+    render_config: RenderConfig,
+    deck: Arc<AsyncStreamDeck>,
+    error: &(dyn std::error::Error + 'static),
+) -> Result<(), Box<dyn std::error::Error>> {
+    let screen = match error.downcast_ref::<HassError>() {
+        Some(error) => ErrorScreen {
+            headline: error.headline().to_string(),
+            hint: error.hint().to_string(),
+        },
+        None => ErrorScreen {
+            headline: "Error".to_string(),
+            hint: "Check logs".to_string(),
+        },
+    };
+    dispatch!(kind, show_error_sized(theme, render_config, deck, screen))
+}
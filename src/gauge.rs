@@ -0,0 +1,129 @@
+//! Gauge icons for buttons that display a level, such as a battery charge.
+//!
+//! Button icons are static SVG strings, so the gauges are generated once in
+//! fixed steps and reused instead of drawing a new image for every value.
+//! Battery levels use the Material battery icons instead.
+
+use std::sync::OnceLock;
+
+use streamdeck_oxide::md_icons;
+
+/// Number of distinct fill levels, not counting the empty gauge.
+const GAUGE_STEPS: usize = 20;
+
+/// Returns a horizontal bar icon filled to the given fraction.
+///
+/// The fraction is clamped to `[0, 1]` and rounded to the nearest 5%.
+///
+/// # Arguments
+///
+/// * `fraction` - How full the bar is, from 0.0 to 1.0
+///
+/// # Example
+///
+/// ```
+/// # use streamdeck_homeassistant::gauge::gauge_icon;
+/// assert_eq!(gauge_icon(0.5), gauge_icon(0.51));
+/// assert_ne!(gauge_icon(0.5), gauge_icon(0.6));
+/// assert_eq!(gauge_icon(2.0), gauge_icon(1.0));
+/// ```
+pub fn gauge_icon(fraction: f64) -> &'static str {
+    static ICONS: OnceLock<Vec<&'static str>> = OnceLock::new();
+    let icons = ICONS.get_or_init(|| {
+        (0..=GAUGE_STEPS)
+            .map(|step| &*Box::leak(gauge_svg(step as f64 / GAUGE_STEPS as f64).into_boxed_str()))
+            .collect()
+    });
+    let fraction = if fraction.is_nan() { 0.0 } else { fraction.clamp(0.0, 1.0) };
+    icons[(fraction * GAUGE_STEPS as f64).round() as usize]
+}
+
+/// Converts a value to a gauge fraction between `min` and `max`.
+///
+/// # Arguments
+///
+/// * `value` - The value to display
+/// * `min` - The value of an empty gauge
+/// * `max` - The value of a full gauge
+///
+/// # Returns
+///
+/// The fraction, or None if the range is empty
+///
+/// # Example
+///
+/// ```
+/// # use streamdeck_homeassistant::gauge::gauge_fraction;
+/// assert_eq!(gauge_fraction(64.0, 0.0, 255.0), Some(64.0 / 255.0));
+/// assert_eq!(gauge_fraction(-5.0, 0.0, 100.0), Some(0.0));
+/// assert_eq!(gauge_fraction(5.0, 10.0, 10.0), None);
+/// ```
+pub fn gauge_fraction(value: f64, min: f64, max: f64) -> Option<f64> {
+    if max <= min {
+        return None;
+    }
+    Some(((value - min) / (max - min)).clamp(0.0, 1.0))
+}
+
+/// Icons for battery levels from empty to nearly full, in equal steps.
+const BATTERY_BARS: [&str; 7] = [
+    md_icons::filled::ICON_BATTERY_0_BAR,
+    md_icons::filled::ICON_BATTERY_1_BAR,
+    md_icons::filled::ICON_BATTERY_2_BAR,
+    md_icons::filled::ICON_BATTERY_3_BAR,
+    md_icons::filled::ICON_BATTERY_4_BAR,
+    md_icons::filled::ICON_BATTERY_5_BAR,
+    md_icons::filled::ICON_BATTERY_6_BAR,
+];
+
+/// Level from which a battery is shown as full, in percent.
+const BATTERY_FULL: f64 = 95.0;
+
+/// Returns a battery icon for a charge level.
+///
+/// Levels below `low` get the alert icon, levels from 95% up the full
+/// battery, and everything in between one of seven bar icons.
+///
+/// # Arguments
+///
+/// * `level` - The charge in percent, from 0 to 100
+/// * `low` - The level in percent below which the battery needs attention
+///
+/// # Example
+///
+/// ```
+/// # use streamdeck_homeassistant::gauge::battery_icon;
+/// # use streamdeck_oxide::md_icons::filled::*;
+/// assert_eq!(battery_icon(100.0, 20.0), ICON_BATTERY_FULL);
+/// assert_eq!(battery_icon(50.0, 20.0), ICON_BATTERY_3_BAR);
+/// assert_eq!(battery_icon(20.0, 20.0), ICON_BATTERY_1_BAR);
+/// assert_eq!(battery_icon(19.0, 20.0), ICON_BATTERY_ALERT);
+/// assert_eq!(battery_icon(3.0, 0.0), ICON_BATTERY_0_BAR);
+/// assert_eq!(battery_icon(f64::NAN, 20.0), ICON_BATTERY_UNKNOWN);
+/// ```
+pub fn battery_icon(level: f64, low: f64) -> &'static str {
+    if level.is_nan() {
+        md_icons::filled::ICON_BATTERY_UNKNOWN
+    } else if level < low {
+        md_icons::filled::ICON_BATTERY_ALERT
+    } else if level >= BATTERY_FULL {
+        md_icons::filled::ICON_BATTERY_FULL
+    } else {
+        let bars = level.max(0.0) / BATTERY_FULL * BATTERY_BARS.len() as f64;
+        BATTERY_BARS[(bars as usize).min(BATTERY_BARS.len() - 1)]
+    }
+}
+
+/// Draws a 24x24 SVG of an outlined bar filled to the given fraction.
+fn gauge_svg(fraction: f64) -> String {
+    let width = 17.0 * fraction;
+    format!(
+        concat!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="24" height="24" viewBox="0 0 24 24">"#,
+            r#"<rect x="2" y="8" width="20" height="8" rx="1.5" fill="none" stroke="black" stroke-width="1.5"/>"#,
+            r#"<rect x="3.5" y="9.5" width="{:.2}" height="5" rx="0.5"/>"#,
+            r#"</svg>"#
+        ),
+        width
+    )
+}
@@ -0,0 +1,353 @@
+//! Label formatting for buttons that display entity state.
+//!
+//! Labels can be composed from a template with placeholders that are
+//! filled in from the cached entity state.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use hass_rs::HassEntity;
+
+use crate::{config::SummaryAggregate, hass::EntityState};
+
+/// Renders a label template against an entity.
+///
+/// Supported placeholders:
+///
+/// * `{state}` - The entity state
+/// * `{name}` - The button name from the configuration
+/// * `{attr:some_attribute}` - An entity attribute
+/// * `{last_changed}` - How long ago the state changed, e.g. "2m ago"
+///
+/// Missing attributes render as an empty string. Unknown placeholders are
+/// kept as-is.
+///
+/// # Arguments
+///
+/// * `template` - The label template
+/// * `name` - The button name
+/// * `entity` - The cached entity state
+///
+/// # Example
+///
+/// ```
+/// # use streamdeck_homeassistant::label::render_label;
+/// # let entity: hass_rs::HassEntity = serde_json::from_value(serde_json::json!({
+/// #     "entity_id": "sensor.living_room_temperature",
+/// #     "state": "21.3",
+/// #     "attributes": { "unit_of_measurement": "°C" },
+/// #     "last_changed": "",
+/// #     "last_updated": "",
+/// #     "context": null,
+/// # })).unwrap();
+/// let label = render_label("{name} {state}{attr:unit_of_measurement}", "Living Room", &entity);
+/// assert_eq!(label, "Living Room 21.3°C");
+/// ```
+pub fn render_label(template: &str, name: &str, entity: &HassEntity) -> String {
+    let mut label = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        label.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}') else {
+            rest = &rest[start..];
+            break;
+        };
+        let placeholder = &rest[start + 1..start + end];
+        match placeholder {
+            "state" => label.push_str(&entity.state),
+            "name" => label.push_str(name),
+            "last_changed" => label.push_str(&last_changed_text(entity)),
+            _ => match placeholder.strip_prefix("attr:") {
+                Some(attribute) => label.push_str(&attribute_text(entity, attribute)),
+                None => label.push_str(&rest[start..=start + end]),
+            },
+        }
+        rest = &rest[start + end + 1..];
+    }
+    label.push_str(rest);
+    label
+}
+
+/// Picks the template to show from a rotation that advances every `period`.
+///
+/// The choice depends only on the current time, so the label keeps rotating
+/// even when the button is rebuilt between refreshes.
+///
+/// # Arguments
+///
+/// * `templates` - The label templates to rotate through
+/// * `period` - How long each template is shown
+///
+/// # Returns
+///
+/// The current template, or None if there are no templates
+pub fn rotating_template(templates: &[String], period: Duration) -> Option<&str> {
+    if templates.is_empty() {
+        return None;
+    }
+    let elapsed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let step = elapsed.as_millis() / period.as_millis().max(1);
+    Some(&templates[(step % templates.len() as u128) as usize])
+}
+
+/// Decides whether a rendered visibility template shows its button.
+///
+/// Empty output and the usual false values, such as `False`, `0`, `off`,
+/// `no` and `None`, hide the button; anything else shows it.
+///
+/// # Arguments
+///
+/// * `rendered` - The text HomeAssistant rendered from the template
+///
+/// # Example
+///
+/// ```
+/// # use streamdeck_homeassistant::label::template_is_true;
+/// assert!(template_is_true("True"));
+/// assert!(template_is_true("3"));
+/// assert!(!template_is_true(" False\n"));
+/// assert!(!template_is_true("off"));
+/// assert!(!template_is_true(""));
+/// ```
+pub fn template_is_true(rendered: &str) -> bool {
+    let rendered = rendered.trim().to_lowercase();
+    !matches!(rendered.as_str(), "" | "false" | "0" | "off" | "no" | "none")
+}
+
+/// Combines the states of several entities into a single value for display.
+///
+/// `Count` counts the entities in the given state. The other aggregates use
+/// the numeric states, skipping entities that are e.g. `unavailable`, and
+/// append the first entity's unit.
+///
+/// # Arguments
+///
+/// * `entities` - The cached entity states
+/// * `aggregate` - How the states are combined
+/// * `state` - The state counted by `Count`
+///
+/// # Returns
+///
+/// The combined value, or "-" if no entity has a numeric state
+///
+/// # Example
+///
+/// ```
+/// # use streamdeck_homeassistant::{config::SummaryAggregate, label::summary_text};
+/// # let entity = |state: &str| -> hass_rs::HassEntity {
+/// #     serde_json::from_value(serde_json::json!({
+/// #         "entity_id": "sensor.temperature",
+/// #         "state": state,
+/// #         "attributes": { "unit_of_measurement": "°C" },
+/// #         "last_changed": "",
+/// #         "last_updated": "",
+/// #         "context": null,
+/// #     })).unwrap()
+/// # };
+/// let entities = [entity("20.5"), entity("22"), entity("unavailable")];
+/// assert_eq!(summary_text(&entities, SummaryAggregate::Average, "on"), "21.3°C");
+/// assert_eq!(summary_text(&entities, SummaryAggregate::Max, "on"), "22°C");
+/// assert_eq!(summary_text(&entities, SummaryAggregate::Count, "unavailable"), "1 unavailable");
+/// ```
+pub fn summary_text(entities: &[HassEntity], aggregate: SummaryAggregate, state: &str) -> String {
+    let values = entities.iter().filter_map(EntityState::numeric_state);
+    let value = match aggregate {
+        SummaryAggregate::Count => {
+            let count = entities.iter().filter(|entity| entity.state == state).count();
+            return format!("{} {}", count, state);
+        }
+        SummaryAggregate::Average => {
+            let (sum, count) =
+                values.fold((0.0, 0), |(sum, count), value| (sum + value, count + 1));
+            (count > 0).then(|| sum / count as f64)
+        }
+        SummaryAggregate::Min => values.reduce(f64::min),
+        SummaryAggregate::Max => values.reduce(f64::max),
+        SummaryAggregate::Sum => values.reduce(|sum, value| sum + value),
+    };
+    let Some(value) = value else {
+        return "-".to_string();
+    };
+    let unit = entities
+        .iter()
+        .find_map(|entity| entity.attr::<String>("unit_of_measurement"))
+        .unwrap_or_default();
+    // Whole numbers stay whole, everything else gets one decimal
+    let rounded = (value * 10.0).round() / 10.0;
+    match rounded.fract() == 0.0 {
+        true => format!("{:.0}{}", rounded, unit),
+        false => format!("{:.1}{}", rounded, unit),
+    }
+}
+
+/// Formats an entity attribute for display, or an empty string if missing.
+///
+/// # Arguments
+///
+/// * `entity` - The cached entity state
+/// * `attribute` - The attribute name
+pub fn attribute_text(entity: &HassEntity, attribute: &str) -> String {
+    match &entity.attributes[attribute] {
+        serde_json::Value::Null => String::new(),
+        serde_json::Value::String(text) => text.clone(),
+        value => value.to_string(),
+    }
+}
+
+/// Formats how long ago an entity's state changed, or an empty string if unknown.
+///
+/// # Arguments
+///
+/// * `entity` - The cached entity state
+pub fn last_changed_text(entity: &HassEntity) -> String {
+    let Some(changed) = parse_timestamp(&entity.last_changed) else {
+        return String::new();
+    };
+    // A clock slightly behind HomeAssistant's counts as just changed
+    let elapsed = SystemTime::now()
+        .duration_since(changed)
+        .unwrap_or_default();
+    relative_time(elapsed)
+}
+
+/// Formats a duration as a short relative time.
+///
+/// # Arguments
+///
+/// * `elapsed` - The time passed since the event
+///
+/// # Example
+///
+/// ```
+/// # use std::time::Duration;
+/// # use streamdeck_homeassistant::label::relative_time;
+/// assert_eq!(relative_time(Duration::from_secs(5)), "now");
+/// assert_eq!(relative_time(Duration::from_secs(150)), "2m ago");
+/// assert_eq!(relative_time(Duration::from_secs(3 * 86400)), "3d ago");
+/// ```
+pub fn relative_time(elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs();
+    match seconds {
+        0..=9 => "now".to_string(),
+        10..=59 => format!("{}s ago", seconds),
+        60..=3599 => format!("{}m ago", seconds / 60),
+        3600..=86399 => format!("{}h ago", seconds / 3600),
+        _ => format!("{}d ago", seconds / 86400),
+    }
+}
+
+/// Parses an ISO 8601 timestamp as sent by HomeAssistant.
+///
+/// Accepts timestamps like `2024-05-01T12:34:56.789012+00:00`, with optional
+/// fractional seconds and either `Z` or a `+HH:MM`/`-HH:MM` offset.
+///
+/// # Arguments
+///
+/// * `timestamp` - The timestamp text
+///
+/// # Returns
+///
+/// The point in time, or None if the text isn't a valid timestamp
+///
+/// # Example
+///
+/// ```
+/// # use std::time::{Duration, UNIX_EPOCH};
+/// # use streamdeck_homeassistant::label::parse_timestamp;
+/// let time = parse_timestamp("2024-05-01T14:00:00.250+02:00").unwrap();
+/// assert_eq!(time, UNIX_EPOCH + Duration::from_millis(1_714_564_800_250));
+/// ```
+pub fn parse_timestamp(timestamp: &str) -> Option<SystemTime> {
+    let (date, time) = timestamp.split_once('T')?;
+    let mut date_parts = date.splitn(3, '-');
+    let year: i64 = date_parts.next()?.parse().ok()?;
+    let month: i64 = date_parts.next()?.parse().ok()?;
+    let day: i64 = date_parts.next()?.parse().ok()?;
+
+    // Split off the offset, which follows the seconds
+    let (time, offset_seconds) = if let Some(time) = time.strip_suffix('Z') {
+        (time, 0)
+    } else {
+        let index = time.rfind(['+', '-'])?;
+        let (time, offset) = time.split_at(index);
+        let sign = if offset.starts_with('-') { -1 } else { 1 };
+        let (hours, minutes) = offset[1..].split_once(':')?;
+        let offset: i64 = hours.parse::<i64>().ok()? * 3600 + minutes.parse::<i64>().ok()? * 60;
+        (time, sign * offset)
+    };
+
+    let mut time_parts = time.splitn(3, ':');
+    let hour: i64 = time_parts.next()?.parse().ok()?;
+    let minute: i64 = time_parts.next()?.parse().ok()?;
+    let seconds: f64 = time_parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 {
+        return None;
+    }
+
+    let days = days_from_civil(year, month, day);
+    let whole_seconds = days * 86400 + hour * 3600 + minute * 60 - offset_seconds;
+    let timestamp = whole_seconds as f64 + seconds;
+    if timestamp < 0.0 {
+        return None;
+    }
+    Some(UNIX_EPOCH + Duration::from_secs_f64(timestamp))
+}
+
+/// Formats a point in time as an ISO 8601 timestamp in UTC, like HomeAssistant does.
+///
+/// # Arguments
+///
+/// * `time` - The point in time
+///
+/// # Example
+///
+/// ```
+/// # use std::time::{Duration, UNIX_EPOCH};
+/// # use streamdeck_homeassistant::label::{format_timestamp, parse_timestamp};
+/// let time = UNIX_EPOCH + Duration::from_micros(1_714_564_800_250_000);
+/// assert_eq!(format_timestamp(time), "2024-05-01T12:00:00.250000+00:00");
+/// assert_eq!(parse_timestamp(&format_timestamp(time)), Some(time));
+/// ```
+pub fn format_timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = since_epoch.as_secs() as i64;
+    let (year, month, day) = civil_from_days(seconds.div_euclid(86400));
+    let seconds_of_day = seconds.rem_euclid(86400);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:06}+00:00",
+        year,
+        month,
+        day,
+        seconds_of_day / 3600,
+        seconds_of_day / 60 % 60,
+        seconds_of_day % 60,
+        since_epoch.subsec_micros()
+    )
+}
+
+/// Finds the date of a day counted from 1970-01-01, the inverse of `days_from_civil`.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Counts the days from 1970-01-01 to a date in the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_index = (month + 9) % 12;
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
@@ -8,14 +8,26 @@
 //! - Connect to Home Assistant via WebSocket API
 //! - Control switches and lights
 //! - Support for RGB lights with color selection
+//! - Fan control with speed presets
 //! - Nested menu navigation
 //! - Persistent connection with automatic reconnection
 
+pub mod backend;
+pub mod buttons;
 pub mod config;
+pub mod custom;
+pub mod deck;
+pub mod gauge;
 pub mod plugins;
+pub mod weather;
 pub mod hass;
+pub mod label;
+pub mod metrics;
+pub mod mock;
 
 // Re-export main types for convenience
 pub use config::{HomeAssistantConfig, HomeAssistantMenu, HomeAssistantButton};
-pub use hass::PersistentHassConnection;
+pub use backend::HassBackend;
+pub use hass::{ConnectionStatus, EntityFilter, EntityState, HassConnections, PersistentHassConnection, TokenSource};
+pub use mock::MockHassConnection;
 pub use plugins::HomeAssistantPlugin;
\ No newline at end of file
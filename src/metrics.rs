@@ -0,0 +1,93 @@
+//! Connection health metrics.
+//!
+//! Metrics are opt-in and collected with atomic counters, so a connection
+//! built without them pays nothing beyond an `Option` check.
+
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+/// Counters describing the health of a HomeAssistant connection.
+#[derive(Debug, Default)]
+pub(crate) struct ConnectionMetrics {
+    successful_fetches: AtomicU64,
+    failed_fetches: AtomicU64,
+    reconnects: AtomicU64,
+    service_calls: AtomicU64,
+    failed_service_calls: AtomicU64,
+    /// Total latency of successful service calls, in microseconds
+    service_latency_us: AtomicU64,
+}
+
+impl ConnectionMetrics {
+    /// Records the outcome of a state fetch.
+    pub(crate) fn record_fetch(&self, success: bool) {
+        let counter = if success {
+            &self.successful_fetches
+        } else {
+            &self.failed_fetches
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records a successful reconnect of either client.
+    pub(crate) fn record_reconnect(&self) {
+        self.reconnects.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records a service call, including any retry after reconnecting.
+    ///
+    /// # Arguments
+    ///
+    /// * `latency` - How long the call took
+    /// * `success` - Whether the call succeeded
+    pub(crate) fn record_service_call(&self, latency: Duration, success: bool) {
+        self.service_calls.fetch_add(1, Ordering::Relaxed);
+        if success {
+            self.service_latency_us
+                .fetch_add(latency.as_micros() as u64, Ordering::Relaxed);
+        } else {
+            self.failed_service_calls.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Takes a consistent-enough copy of the counters.
+    pub(crate) fn snapshot(&self) -> MetricsSnapshot {
+        let service_calls = self.service_calls.load(Ordering::Relaxed);
+        let failed_service_calls = self.failed_service_calls.load(Ordering::Relaxed);
+        let successful_calls = service_calls.saturating_sub(failed_service_calls);
+        let average_service_latency = (successful_calls > 0).then(|| {
+            Duration::from_micros(
+                self.service_latency_us.load(Ordering::Relaxed) / successful_calls,
+            )
+        });
+        MetricsSnapshot {
+            successful_fetches: self.successful_fetches.load(Ordering::Relaxed),
+            failed_fetches: self.failed_fetches.load(Ordering::Relaxed),
+            reconnects: self.reconnects.load(Ordering::Relaxed),
+            service_calls,
+            failed_service_calls,
+            average_service_latency,
+        }
+    }
+}
+
+/// A point-in-time copy of a connection's metrics.
+///
+/// All counters start at zero when the connection is built and only grow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    /// State fetches that succeeded
+    pub successful_fetches: u64,
+    /// State fetches that failed
+    pub failed_fetches: u64,
+    /// Clients replaced after a failure
+    pub reconnects: u64,
+    /// Service calls made, whether they succeeded or not
+    pub service_calls: u64,
+    /// Service calls that failed, including after retrying
+    pub failed_service_calls: u64,
+    /// Mean duration of successful service calls, or None before the first one
+    pub average_service_latency: Option<Duration>,
+}
@@ -0,0 +1,347 @@
+//! A stand-in for HomeAssistant with canned states.
+//!
+//! `MockHassConnection` lets configurations be rendered and clicked through
+//! without a live instance, e.g. in CI or while writing a configuration.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+use hass_rs::HassEntity;
+use tokio::task::JoinHandle;
+
+use crate::{
+    backend::{BackendFuture, HassBackend},
+    hass::{debounce_press, HassError},
+};
+
+/// A service call recorded by `MockHassConnection`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServiceCall {
+    /// The domain of the service
+    pub domain: String,
+    /// The service that was called
+    pub service: String,
+    /// The service data, if any
+    pub data: Option<serde_json::Value>,
+}
+
+/// An event fired through `MockHassConnection`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FiredEvent {
+    /// The event type
+    pub event_type: String,
+    /// The event data, if any
+    pub event_data: Option<serde_json::Value>,
+}
+
+/// A HomeAssistant connection that serves canned states and records service calls.
+///
+/// `turn_on`, `turn_off` and `toggle` calls update the state of the targeted
+/// entities, so toggles behave as they would against a live instance. Other
+/// calls and fired events are only recorded. Templates are returned unrendered.
+///
+/// Register it in the `PluginContext` through [`HassConnections`](crate::hass::HassConnections)
+/// in place of a `PersistentHassConnection`.
+///
+/// # Example
+///
+/// ```
+/// # use streamdeck_homeassistant::mock::MockHassConnection;
+/// let hass = MockHassConnection::new()
+///     .with_state("light.kitchen", "on", serde_json::json!({ "friendly_name": "Kitchen" }))
+///     .with_state("sensor.temperature", "21.5", serde_json::json!({ "unit_of_measurement": "°C" }));
+/// ```
+#[derive(Default)]
+pub struct MockHassConnection {
+    states: RwLock<BTreeMap<String, HassEntity>>,
+    calls: Mutex<Vec<ServiceCall>>,
+    events: Mutex<Vec<FiredEvent>>,
+    /// Entities asked to be refreshed on their own
+    refreshed: Mutex<Vec<String>>,
+    timers: Mutex<BTreeMap<String, JoinHandle<()>>>,
+    /// When each cooling down button can be pressed again
+    cooldowns: Mutex<BTreeMap<String, Instant>>,
+    temperature_unit: Option<String>,
+    /// Entity IDs of each area, keyed by area ID
+    areas: BTreeMap<String, Vec<String>>,
+    /// Time within which repeated presses are rejected; zero accepts every press
+    debounce: Duration,
+    /// When each key was last pressed
+    last_presses: Mutex<BTreeMap<String, Instant>>,
+}
+
+impl MockHassConnection {
+    /// Creates a mock connection without any entities.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an entity with the given state and attributes.
+    ///
+    /// # Arguments
+    ///
+    /// * `entity_id` - The entity ID
+    /// * `state` - The state, e.g. "on"
+    /// * `attributes` - The attributes as a JSON object
+    pub fn with_state(self, entity_id: &str, state: &str, attributes: serde_json::Value) -> Self {
+        self.set_state(entity_id, state, attributes);
+        self
+    }
+
+    /// Sets the temperature unit reported as the HomeAssistant configuration, e.g. "°F".
+    pub fn with_temperature_unit(mut self, unit: &str) -> Self {
+        self.temperature_unit = Some(unit.to_string());
+        self
+    }
+
+    /// Rejects presses repeating the last accepted one within `debounce`, like a live connection.
+    pub fn with_debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    /// Adds an area with the given entities, e.g. for `area` buttons.
+    ///
+    /// # Arguments
+    ///
+    /// * `area_id` - The area ID, e.g. "living_room"
+    /// * `entity_ids` - The entities assigned to the area
+    pub fn with_area(mut self, area_id: &str, entity_ids: &[&str]) -> Self {
+        let mut entity_ids: Vec<String> = entity_ids.iter().map(|id| id.to_string()).collect();
+        entity_ids.sort();
+        self.areas.insert(area_id.to_string(), entity_ids);
+        self
+    }
+
+    /// Sets the state and attributes of an entity, adding it if needed.
+    pub fn set_state(&self, entity_id: &str, state: &str, attributes: serde_json::Value) {
+        let entity = HassEntity {
+            entity_id: entity_id.to_string(),
+            last_changed: String::new(),
+            state: state.to_string(),
+            attributes,
+            last_updated: String::new(),
+            context: None,
+        };
+        if let Ok(mut states) = self.states.write() {
+            states.insert(entity_id.to_string(), entity);
+        }
+    }
+
+    /// Returns the service calls made so far, oldest first.
+    pub fn calls(&self) -> Vec<ServiceCall> {
+        self.calls.lock().map(|calls| calls.clone()).unwrap_or_default()
+    }
+
+    /// Returns the events fired so far, oldest first.
+    pub fn events(&self) -> Vec<FiredEvent> {
+        self.events.lock().map(|events| events.clone()).unwrap_or_default()
+    }
+
+    /// Returns the entities refreshed on their own so far, oldest first.
+    pub fn refreshed(&self) -> Vec<String> {
+        self.refreshed.lock().map(|refreshed| refreshed.clone()).unwrap_or_default()
+    }
+
+    /// Applies a turn_on, turn_off or toggle call to the cached states.
+    fn apply_call(&self, service: &str, data: Option<&serde_json::Value>) {
+        let entity_ids: Vec<&str> = match data.map(|data| &data["entity_id"]) {
+            Some(serde_json::Value::String(entity_id)) => vec![entity_id],
+            Some(serde_json::Value::Array(entity_ids)) => {
+                entity_ids.iter().filter_map(|id| id.as_str()).collect()
+            }
+            _ => return,
+        };
+        let Ok(mut states) = self.states.write() else {
+            return;
+        };
+        for entity_id in entity_ids {
+            if let Some(entity) = states.get_mut(entity_id) {
+                entity.state = match service {
+                    "turn_on" => "on",
+                    "turn_off" => "off",
+                    "toggle" if entity.state == "on" => "off",
+                    "toggle" => "on",
+                    _ => continue,
+                }
+                .to_string();
+            }
+        }
+    }
+}
+
+impl HassBackend for MockHassConnection {
+    fn get_state<'a>(&'a self, entity_id: &'a str) -> BackendFuture<'a, Option<HassEntity>> {
+        let state = self
+            .states
+            .read()
+            .ok()
+            .and_then(|states| states.get(entity_id).cloned());
+        Box::pin(async move { state })
+    }
+
+    fn has_states(&self) -> BackendFuture<'_, bool> {
+        let has_states = self.states.read().map(|states| !states.is_empty()).unwrap_or(false);
+        Box::pin(async move { has_states })
+    }
+
+    fn list_entities(&self) -> BackendFuture<'_, Vec<(String, Option<String>)>> {
+        let entities = self
+            .states
+            .read()
+            .map(|states| {
+                states
+                    .values()
+                    .map(|entity| {
+                        let friendly_name = entity.attributes["friendly_name"]
+                            .as_str()
+                            .map(str::to_string);
+                        (entity.entity_id.clone(), friendly_name)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Box::pin(async move { entities })
+    }
+
+    fn area_entities<'a>(
+        &'a self,
+        area_id: &'a str,
+    ) -> BackendFuture<'a, Result<Vec<String>, HassError>> {
+        let entity_ids = self
+            .areas
+            .get(area_id)
+            .cloned()
+            .ok_or_else(|| HassError::Request(format!("No area with ID '{}'", area_id)));
+        Box::pin(async move { entity_ids })
+    }
+
+    fn fetch_states(&self) -> BackendFuture<'_, Result<(), String>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    // The canned states are always current, so refreshing only records the request
+    fn refresh_entities<'a>(
+        &'a self,
+        entity_ids: &'a [String],
+    ) -> BackendFuture<'a, Result<(), HassError>> {
+        if let Ok(mut refreshed) = self.refreshed.lock() {
+            refreshed.extend_from_slice(entity_ids);
+        }
+        Box::pin(async { Ok(()) })
+    }
+
+    fn temperature_unit(&self) -> BackendFuture<'_, Option<String>> {
+        let unit = self.temperature_unit.clone();
+        Box::pin(async move { unit })
+    }
+
+    fn call_service<'a>(
+        &'a self,
+        domain: &'a str,
+        service: &'a str,
+        data: Option<serde_json::Value>,
+    ) -> BackendFuture<'a, Result<(), Box<dyn std::error::Error>>> {
+        self.apply_call(service, data.as_ref());
+        if let Ok(mut calls) = self.calls.lock() {
+            calls.push(ServiceCall {
+                domain: domain.to_string(),
+                service: service.to_string(),
+                data,
+            });
+        }
+        Box::pin(async { Ok(()) })
+    }
+
+    fn fire_event<'a>(
+        &'a self,
+        event_type: &'a str,
+        event_data: Option<serde_json::Value>,
+    ) -> BackendFuture<'a, Result<(), HassError>> {
+        if let Ok(mut events) = self.events.lock() {
+            events.push(FiredEvent {
+                event_type: event_type.to_string(),
+                event_data,
+            });
+        }
+        Box::pin(async { Ok(()) })
+    }
+
+    // Templates can't be rendered without HomeAssistant, so show them as written
+    fn render_template<'a>(
+        &'a self,
+        template: &'a str,
+    ) -> BackendFuture<'a, Result<String, HassError>> {
+        Box::pin(async move { Ok(template.to_string()) })
+    }
+
+    fn is_connected(&self) -> bool {
+        true
+    }
+
+    fn reconnect(&self) -> BackendFuture<'_, Result<(), Box<dyn std::error::Error>>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn accept_press(&self, key: &str) -> bool {
+        let Ok(mut last_presses) = self.last_presses.lock() else {
+            return true;
+        };
+        debounce_press(&mut last_presses, key, Instant::now(), self.debounce)
+    }
+
+    fn start_cooldown(&self, key: &str, duration: Duration) {
+        if let Ok(mut cooldowns) = self.cooldowns.lock() {
+            cooldowns.insert(key.to_string(), Instant::now() + duration);
+        }
+    }
+
+    fn cooldown_remaining(&self, key: &str) -> Option<Duration> {
+        let cooldowns = self.cooldowns.lock().ok()?;
+        let remaining = cooldowns.get(key)?.checked_duration_since(Instant::now())?;
+        (!remaining.is_zero()).then_some(remaining)
+    }
+
+    fn start_off_timer(self: Arc<Self>, domain: &str, entity_id: &str, delay: Duration) {
+        let connection = self.clone();
+        let domain = domain.to_string();
+        let entity_id = entity_id.to_string();
+        let key = entity_id.clone();
+        let task = tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            if let Ok(mut timers) = connection.timers.lock() {
+                timers.remove(&entity_id);
+            }
+            let data = Some(serde_json::json!({ "entity_id": entity_id }));
+            connection.call_service(&domain, "turn_off", data).await.ok();
+        });
+        if let Ok(mut timers) = self.timers.lock() {
+            if let Some(previous) = timers.insert(key, task) {
+                previous.abort();
+            }
+        }
+    }
+
+    fn cancel_off_timer(&self, entity_id: &str) -> bool {
+        let Ok(mut timers) = self.timers.lock() else {
+            return false;
+        };
+        match timers.remove(entity_id) {
+            Some(task) => {
+                task.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn has_off_timer(&self, entity_id: &str) -> bool {
+        self.timers
+            .lock()
+            .map(|timers| timers.contains_key(entity_id))
+            .unwrap_or(false)
+    }
+}
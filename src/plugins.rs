@@ -3,26 +3,135 @@
 //! This module contains the main plugin implementation and specialized
 //! plugins for different types of HomeAssistant entities.
 
+pub mod alarm;
+pub mod area;
+pub mod climate;
+pub mod cover;
+pub mod fan;
+pub mod humidifier;
+pub mod number;
 pub mod rgb;
-use std::sync::Arc;
+pub mod scenes;
+pub mod vacuum;
+pub mod water_heater;
 
+use std::{
+    ops::Range,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use hass_rs::HassEntity;
 use resvg::tiny_skia::Color;
+use tokio::sync::watch;
+
 use streamdeck_oxide::{
-    generic_array::{
-        typenum::{U3, U5},
-        ArrayLength,
-    },
+    generic_array::ArrayLength,
     md_icons,
     plugins::{Plugin, PluginContext, PluginNavigation},
-    view::customizable::{ClickButton, CustomizableView, ToggleButton},
-    Theme, View,
+    view::customizable::{CustomButton, CustomizableView, ToggleButton},
+    Button, ButtonState, Theme, View,
 };
 
 use crate::{
-    config::{HomeAssistantButton, HomeAssistantConfig, HomeAssistantMenu},
-    hass::PersistentHassConnection,
+    backend::HassBackend,
+    buttons::{
+        ensure_available, Disconnected, FeedbackButton, IconLabelButton, IgnoredPress, LabelButton,
+        LabelToggleButton, StaticButton,
+    },
+    config::{ButtonPosition, HomeAssistantButton, HomeAssistantConfig, HomeAssistantMenu, ThemeName},
+    custom::{get_handler, CustomButtonConfig},
+    gauge::{battery_icon, gauge_fraction, gauge_icon},
+    hass::{EntityState, HassConnections, PersistentHassConnection},
+    label::{
+        attribute_text, last_changed_text, render_label, rotating_template, summary_text,
+        template_is_true,
+    },
+    weather::condition_icon,
 };
 
+/// The view type produced by the HomeAssistant menu.
+type MenuView<W, H> = CustomizableView<W, H, PluginContext, PluginNavigation<W, H>>;
+
+/// Resolves the HomeAssistant connection for a button from the plugin context.
+///
+/// Looks up `instance` in the registered `HassConnections`, which may hold a
+/// `MockHassConnection` for dry runs. Without a registry, the single
+/// `PersistentHassConnection` in the context is used when no instance is
+/// requested.
+///
+/// # Arguments
+///
+/// * `context` - The plugin context
+/// * `instance` - Optional name of the HomeAssistant instance
+pub(crate) async fn get_connection(
+    context: &PluginContext,
+    instance: Option<&str>,
+) -> Result<Arc<dyn HassBackend>, Box<dyn std::error::Error>> {
+    if let Some(connections) = context.get_context::<HassConnections>().await {
+        return match connections.get(instance) {
+            Some(connection) => Ok(connection),
+            None => Err(format!(
+                "Unknown HomeAssistant instance '{}'",
+                instance.unwrap_or_default()
+            )
+            .into()),
+        };
+    }
+    match instance {
+        None => match context.get_context::<PersistentHassConnection>().await {
+            Some(connection) => Ok(connection),
+            None => Err("Failed to get PersistentHassConnection".into()),
+        },
+        Some(name) => Err(format!(
+            "HomeAssistant instance '{}' requested but no HassConnections registered",
+            name
+        )
+        .into()),
+    }
+}
+
+/// Gets the current configuration from the plugin context.
+///
+/// A `watch::Receiver<HomeAssistantConfig>`, as used by
+/// [`deck::run_with_reload`](crate::deck::run_with_reload), takes precedence
+/// over a fixed `HomeAssistantConfig`, so menus drawn after a reload use the
+/// new settings.
+///
+/// # Arguments
+///
+/// * `context` - The plugin context
+pub(crate) async fn get_config(context: &PluginContext) -> Option<Arc<HomeAssistantConfig>> {
+    if let Some(configs) = context
+        .get_context::<watch::Receiver<HomeAssistantConfig>>()
+        .await
+    {
+        return Some(Arc::new(configs.borrow().clone()));
+    }
+    context.get_context::<HomeAssistantConfig>().await
+}
+
+/// Resolves the connection for a button like `get_connection`, failing with
+/// `Disconnected` while HomeAssistant is unreachable.
+///
+/// Toggle buttons use this so an outage shows as an offline indicator rather
+/// than stale states or failed presses.
+///
+/// # Arguments
+///
+/// * `context` - The plugin context
+/// * `instance` - Optional name of the HomeAssistant instance
+pub(crate) async fn get_online_connection(
+    context: &PluginContext,
+    instance: Option<&str>,
+) -> Result<Arc<dyn HassBackend>, Box<dyn std::error::Error>> {
+    let hass = get_connection(context, instance).await?;
+    if !hass.is_connected() {
+        return Err(Box::new(Disconnected));
+    }
+    Ok(hass)
+}
+
 /// Main plugin for HomeAssistant integration.
 ///
 /// This plugin renders a menu of HomeAssistant entities on the Stream Deck
@@ -33,6 +142,297 @@ pub struct HomeAssistantPlugin<W: ArrayLength, H: ArrayLength> {
     pub menu: HomeAssistantMenu,
     /// Optional navigation to return to when "Back" is pressed
     pub back_navigation: Option<PluginNavigation<W, H>>,
+    /// Zero-based page shown when the menu has more buttons than keys
+    pub page: usize,
+    /// Theme inherited from the parent menu; None uses the theme passed to the Stream Deck
+    pub theme: Option<ThemeName>,
+    /// Names of the parent menus, starting at the root
+    pub path: Vec<String>,
+    /// Where menus with rotating labels ask to be refreshed; None disables refreshing
+    pub refresh: Option<MenuRefresh<W, H>>,
+    /// Navigation to the root menu; None for the root menu itself
+    pub root: Option<PluginNavigation<W, H>>,
+}
+
+impl<W: ArrayLength, H: ArrayLength> HomeAssistantPlugin<W, H> {
+    /// Returns the path of this menu: the names of its parents followed by its own name.
+    pub fn breadcrumb_path(&self) -> Vec<String> {
+        let mut path = self.path.clone();
+        path.push(self.menu.name.clone());
+        path
+    }
+
+    /// Returns the plugin showing a submenu of this menu, whose "Back" button leads here.
+    ///
+    /// # Arguments
+    ///
+    /// * `menu` - The submenu to show
+    pub fn submenu(&self, menu: HomeAssistantMenu) -> Self {
+        HomeAssistantPlugin {
+            menu,
+            back_navigation: Some(PluginNavigation::new(self.clone())),
+            page: 0,
+            theme: self.menu.theme.or(self.theme),
+            path: self.breadcrumb_path(),
+            refresh: self.refresh.clone(),
+            root: Some(self.root_navigation()),
+        }
+    }
+
+    /// Returns the navigation to the root menu, which is this menu if it has no parent.
+    pub fn root_navigation(&self) -> PluginNavigation<W, H> {
+        match &self.root {
+            Some(root) => root.clone(),
+            None => PluginNavigation::new(HomeAssistantPlugin {
+                page: 0,
+                ..self.clone()
+            }),
+        }
+    }
+}
+
+/// The menu on screen that needs refreshing, and how often.
+type RefreshRequest<W, H> = (PluginNavigation<W, H>, Duration);
+
+/// Tracks whether the menu on screen needs to be refreshed periodically.
+///
+/// A menu showing buttons with rotating labels or cooldowns registers
+/// itself when it is displayed, and any other menu clears the registration.
+/// `deck::run` re-renders the registered menu at the requested interval.
+#[derive(Clone)]
+pub struct MenuRefresh<W: ArrayLength, H: ArrayLength> {
+    current: Arc<std::sync::Mutex<Option<RefreshRequest<W, H>>>>,
+}
+
+impl<W: ArrayLength, H: ArrayLength> MenuRefresh<W, H> {
+    /// Creates a tracker without a menu to refresh.
+    pub fn new() -> Self {
+        MenuRefresh {
+            current: Arc::new(std::sync::Mutex::new(None)),
+        }
+    }
+
+    /// Returns the menu to refresh and the interval, if the menu on screen needs it.
+    pub fn current(&self) -> Option<RefreshRequest<W, H>> {
+        self.current.lock().ok().and_then(|current| current.clone())
+    }
+
+    /// Registers the menu on screen, or clears the registration with None.
+    fn set(&self, request: Option<RefreshRequest<W, H>>) {
+        if let Ok(mut current) = self.current.lock() {
+            *current = request;
+        }
+    }
+}
+
+impl<W: ArrayLength, H: ArrayLength> Default for MenuRefresh<W, H> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Applies an optional theme to a button, keeping the global theme when None.
+trait WithOptionalTheme: Sized {
+    fn with_optional_theme(self, theme: Option<Theme>) -> Self;
+}
+
+impl WithOptionalTheme for ToggleButton<PluginContext> {
+    fn with_optional_theme(self, theme: Option<Theme>) -> Self {
+        match theme {
+            Some(theme) => self.with_theme(theme),
+            None => self,
+        }
+    }
+}
+
+impl WithOptionalTheme for FeedbackButton<PluginContext> {
+    fn with_optional_theme(self, theme: Option<Theme>) -> Self {
+        match theme {
+            Some(theme) => self.with_theme(theme),
+            None => self,
+        }
+    }
+}
+
+impl WithOptionalTheme for LabelToggleButton<PluginContext> {
+    fn with_optional_theme(self, theme: Option<Theme>) -> Self {
+        match theme {
+            Some(theme) => self.with_theme(theme),
+            None => self,
+        }
+    }
+}
+
+impl WithOptionalTheme for IconLabelButton<PluginContext> {
+    fn with_optional_theme(self, theme: Option<Theme>) -> Self {
+        match theme {
+            Some(theme) => self.with_theme(theme),
+            None => self,
+        }
+    }
+}
+
+impl WithOptionalTheme for LabelButton<PluginContext> {
+    fn with_optional_theme(self, theme: Option<Theme>) -> Self {
+        match theme {
+            Some(theme) => self.with_theme(theme),
+            None => self,
+        }
+    }
+}
+
+/// Where a button's cooldown is tracked and how long it lasts.
+struct Cooldown {
+    /// Identifies the button across redraws of the menu
+    key: String,
+    /// The HomeAssistant instance keeping track of the cooldown
+    instance: Option<String>,
+    /// How long presses are ignored after a successful press
+    duration: Duration,
+}
+
+/// Ignores presses of a button for a while after a successful press.
+///
+/// The cooldown is kept by the connection, so it survives the menu being
+/// redrawn. While it runs, the button is grayed out and counts down.
+struct CooldownButton<B> {
+    /// The wrapped button
+    inner: B,
+    /// The cooldown settings
+    cooldown: Cooldown,
+    /// When the button can be pressed again, as of the last fetch or click
+    ready_at: std::sync::Mutex<Option<Instant>>,
+}
+
+impl<B> CooldownButton<B> {
+    /// Remembers the remaining cooldown for drawing the button.
+    fn set_remaining(&self, remaining: Option<Duration>) {
+        if let Ok(mut ready_at) = self.ready_at.lock() {
+            *ready_at = remaining.map(|remaining| Instant::now() + remaining);
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<B> CustomButton<PluginContext> for CooldownButton<B>
+where
+    B: CustomButton<PluginContext>,
+{
+    fn get_state(&self) -> Button {
+        let ready_at = self.ready_at.lock().ok().and_then(|ready_at| *ready_at);
+        let remaining =
+            ready_at.and_then(|ready_at| ready_at.checked_duration_since(Instant::now()));
+        match remaining {
+            Some(remaining) if !remaining.is_zero() => self
+                .inner
+                .get_state()
+                .updated_text(format!("{}s", remaining.as_secs_f64().ceil()))
+                .updated_state(ButtonState::Inactive),
+            _ => self.inner.get_state(),
+        }
+    }
+
+    async fn fetch(&self, context: &PluginContext) -> Result<(), Box<dyn std::error::Error>> {
+        self.inner.fetch(context).await?;
+        let hass = get_connection(context, self.cooldown.instance.as_deref()).await?;
+        self.set_remaining(hass.cooldown_remaining(&self.cooldown.key));
+        Ok(())
+    }
+
+    async fn click(&self, context: &PluginContext) -> Result<(), Box<dyn std::error::Error>> {
+        let hass = get_connection(context, self.cooldown.instance.as_deref()).await?;
+        if let Some(remaining) = hass.cooldown_remaining(&self.cooldown.key) {
+            log::debug!("Ignoring press of '{}' during cooldown", self.cooldown.key);
+            self.set_remaining(Some(remaining));
+            return Ok(());
+        }
+        self.inner.click(context).await?;
+        hass.start_cooldown(&self.cooldown.key, self.cooldown.duration);
+        self.set_remaining(Some(self.cooldown.duration));
+        Ok(())
+    }
+}
+
+/// Places a button in the view, wrapped in a `CooldownButton` if it has a cooldown.
+fn set_button_with_cooldown<W, H, B>(
+    view: &mut MenuView<W, H>,
+    x: usize,
+    y: usize,
+    cooldown: Option<Cooldown>,
+    button: B,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    W: ArrayLength,
+    H: ArrayLength,
+    B: CustomButton<PluginContext>,
+{
+    match cooldown {
+        Some(cooldown) => view.set_button(
+            x,
+            y,
+            CooldownButton {
+                inner: button,
+                cooldown,
+                ready_at: std::sync::Mutex::new(None),
+            },
+        ),
+        None => view.set_button(x, y, button),
+    }
+}
+
+/// Formats the label of an on/off toggle.
+///
+/// Dimmable lights that are on show their brightness, e.g. "Lamp 60%".
+///
+/// # Arguments
+///
+/// * `name` - The button name
+/// * `on` - Whether the entity is on
+/// * `entity` - The cached entity state
+fn toggle_label(name: &str, on: bool, entity: &HassEntity) -> String {
+    match entity.brightness_pct() {
+        Some(brightness) if on => format!("{} {:.0}%", name, brightness),
+        _ => name.to_string(),
+    }
+}
+
+/// An option chosen on a select button that the state cache doesn't show yet.
+struct PendingOption {
+    /// The cached state when the option was chosen
+    cached: String,
+    /// The option chosen
+    chosen: String,
+}
+
+/// Returns the option a select button shows: the chosen one while the
+/// cache still holds the state it was chosen from, else the cached state.
+///
+/// # Arguments
+///
+/// * `pending` - The option chosen by the last press, cleared once the cache changes
+/// * `cached` - The cached state of the entity
+fn shown_option(pending: &std::sync::Mutex<Option<PendingOption>>, cached: &str) -> String {
+    let Ok(mut pending) = pending.lock() else {
+        return cached.to_string();
+    };
+    match pending.as_ref() {
+        Some(option) if option.cached == cached => option.chosen.clone(),
+        _ => {
+            *pending = None;
+            cached.to_string()
+        }
+    }
+}
+
+/// Returns the option after `current`, wrapping around to the first.
+///
+/// An unknown current option is followed by the first option.
+fn next_option(options: &[String], current: &str) -> Option<String> {
+    let next = match options.iter().position(|option| option == current) {
+        Some(index) => (index + 1) % options.len(),
+        None => 0,
+    };
+    options.get(next).cloned()
 }
 
 /// Adds a button to the view based on the HomeAssistant button configuration.
@@ -43,123 +443,1627 @@ pub struct HomeAssistantPlugin<W: ArrayLength, H: ArrayLength> {
 /// * `x` - The x coordinate on the Stream Deck
 /// * `y` - The y coordinate on the Stream Deck
 /// * `item` - The button configuration
-/// * `back_navigation` - Optional navigation for nested menus
+/// * `name` - The display name of the button
+/// * `parent` - The menu the button is on, which "Back" returns to
+/// * `base_theme` - The theme the menu is drawn with, also used under custom colors
+///
+/// Navigation buttons always use the global theme, since `streamdeck-oxide`
+/// doesn't support theming them individually.
 fn add_button<W, H>(
-    view: &mut CustomizableView<W, H, PluginContext, PluginNavigation<W, H>>,
+    view: &mut MenuView<W, H>,
     x: usize,
     y: usize,
     item: &HomeAssistantButton,
-    back_navigation: &Option<PluginNavigation<W, H>>,
+    name: &str,
+    parent: &HomeAssistantPlugin<W, H>,
+    base_theme: ThemeName,
 ) -> Result<(), Box<dyn std::error::Error>>
 where
     W: ArrayLength,
     H: ArrayLength,
 {
+    let back_navigation = Some(PluginNavigation::<W, H>::new(parent.clone()));
+    // Set explicitly rather than left to the Stream Deck's theme, so it follows reloads
+    let button_theme = Some(base_theme.to_theme());
+    let cooldown = item.cooldown().map(|duration| {
+        let mut path = parent.breadcrumb_path();
+        path.push(name.to_string());
+        Cooldown {
+            key: path.join("/"),
+            instance: item.instance().map(str::to_string),
+            duration,
+        }
+    });
     match item {
-        HomeAssistantButton::Switch { entity_id, name } => {
+        HomeAssistantButton::Switch {
+            entity_id,
+            active_color,
+            instance,
+            ..
+        }
+        | HomeAssistantButton::Toggle {
+            entity_id,
+            active_color,
+            instance,
+            ..
+        }
+        | HomeAssistantButton::InputBoolean {
+            entity_id,
+            active_color,
+            instance,
+            ..
+        } => {
+            // Validation guarantees a domain prefix, e.g. "light" for "light.kitchen"
+            let domain = entity_id
+                .split_once('.')
+                .map(|(domain, _)| domain.to_string())
+                .ok_or_else(|| format!("Entity ID '{}' has no domain", entity_id))?;
+            let entity_id = entity_id.clone();
+            let instance = instance.clone();
+            let entity_id_2 = entity_id.clone();
+            let instance_2 = instance.clone();
+            let entity_id_3 = entity_id.clone();
+            let instance_3 = instance.clone();
+            let domain_2 = domain.clone();
+            let transition = match item {
+                HomeAssistantButton::Toggle { transition, .. } => *transition,
+                _ => None,
+            };
+            let button_name = name.to_string();
+            let (icon, active_icon) = match item {
+                HomeAssistantButton::InputBoolean { .. } => (
+                    md_icons::filled::ICON_CHECK_BOX_OUTLINE_BLANK,
+                    md_icons::filled::ICON_CHECK_BOX,
+                ),
+                _ => (
+                    md_icons::filled::ICON_TOGGLE_OFF,
+                    md_icons::filled::ICON_TOGGLE_ON,
+                ),
+            };
+            let button = LabelToggleButton::new(
+                name,
+                Some(icon),
+                move |ctx: PluginContext| {
+                    let entity_id = entity_id.clone();
+                    let instance = instance.clone();
+                    let button_name = button_name.clone();
+                    async move {
+                        let hass = get_online_connection(&ctx, instance.as_deref()).await?;
+                        let state = hass
+                            .get_state(&entity_id)
+                            .await
+                            .ok_or("Failed to get state")?;
+                        ensure_available(&state.state)?;
+
+                        let on = state.is_on();
+                        Ok((on, toggle_label(&button_name, on, &state)))
+                    }
+                },
+                move |ctx, value| {
+                    let entity_id = entity_id_2.clone();
+                    let instance = instance_2.clone();
+                    let domain = domain.clone();
+                    async move {
+                        let hass = get_online_connection(&ctx, instance.as_deref()).await?;
+                        // Ignore quick repeated presses until the first one is reflected
+                        if !hass.accept_press(&entity_id) {
+                            return Err(IgnoredPress.into());
+                        }
+                        hass.call_service(
+                            &domain,
+                            if value { "turn_on" } else { "turn_off" },
+                            Some(rgb::with_transition(
+                                serde_json::json!({ "entity_id": entity_id }),
+                                transition,
+                            )),
+                        )
+                        .await
+                        .map_err(|e| e.to_string())?;
+                        Ok(())
+                    }
+                },
+            )
+            .when_active(Some(active_icon))
+            .with_optional_theme(
+                active_color
+                    .map(|color| active_color_theme(base_theme, color))
+                    .or(button_theme),
+            );
+
+            // Lights on show their color, unless a fixed color is configured
+            let button = match (domain_2.as_str(), active_color) {
+                ("light", None) => {
+                    let entity_id = entity_id_3;
+                    let instance = instance_3;
+                    button.with_active_theme(move |ctx: PluginContext| {
+                        let entity_id = entity_id.clone();
+                        let instance = instance.clone();
+                        async move {
+                            let hass = get_connection(&ctx, instance.as_deref()).await.ok()?;
+                            let state = hass.get_state(&entity_id).await?;
+                            let color = rgb::light_color(&state)?;
+                            Some(active_color_theme(base_theme, color))
+                        }
+                    })
+                }
+                _ => button,
+            };
+            set_button_with_cooldown(view, x, y, cooldown, button)
+        }
+        HomeAssistantButton::Momentary {
+            entity_id,
+            pulse_ms,
+            instance,
+            ..
+        } => {
+            let domain = entity_id
+                .split_once('.')
+                .map(|(domain, _)| domain.to_string())
+                .ok_or_else(|| format!("Entity ID '{}' has no domain", entity_id))?;
+            let entity_id = entity_id.clone();
+            let instance = instance.clone();
+            let pulse = std::time::Duration::from_millis(*pulse_ms);
+            set_button_with_cooldown(
+                view,
+                x,
+                y,
+                cooldown,
+                FeedbackButton::new(
+                    name,
+                    Some(md_icons::filled::ICON_TOUCH_APP),
+                    move |ctx: PluginContext| {
+                        let entity_id = entity_id.clone();
+                        let instance = instance.clone();
+                        let domain = domain.clone();
+                        async move {
+                            let hass = get_connection(&ctx, instance.as_deref()).await?;
+                            let data = Some(serde_json::json!({ "entity_id": entity_id }));
+                            hass.call_service(&domain, "turn_on", data.clone())
+                                .await
+                                .map_err(|e| e.to_string())?;
+                            tokio::time::sleep(pulse).await;
+                            hass.call_service(&domain, "turn_off", data)
+                                .await
+                                .map_err(|e| e.to_string())?;
+                            Ok(())
+                        }
+                    },
+                )
+                .errors_only()
+                .with_optional_theme(button_theme),
+            )
+        }
+        HomeAssistantButton::Button {
+            entity_id,
+            instance,
+            ..
+        } => {
+            let entity_id = entity_id.clone();
+            let instance = instance.clone();
+            set_button_with_cooldown(
+                view,
+                x,
+                y,
+                cooldown,
+                FeedbackButton::new(
+                    name,
+                    Some(md_icons::filled::ICON_RADIO_BUTTON_CHECKED),
+                    move |ctx: PluginContext| {
+                        let entity_id = entity_id.clone();
+                        let instance = instance.clone();
+                        async move {
+                            let hass = get_connection(&ctx, instance.as_deref()).await?;
+                            hass.call_service(
+                                "button",
+                                "press",
+                                Some(serde_json::json!({ "entity_id": entity_id })),
+                            )
+                            .await
+                            .map_err(|e| e.to_string())?;
+                            Ok(())
+                        }
+                    },
+                )
+                .errors_only()
+                .with_optional_theme(button_theme),
+            )
+        }
+        HomeAssistantButton::TimedOn {
+            entity_id,
+            duration_secs,
+            instance,
+            ..
+        } => {
+            let domain = entity_id
+                .split_once('.')
+                .map(|(domain, _)| domain.to_string())
+                .ok_or_else(|| format!("Entity ID '{}' has no domain", entity_id))?;
+            let entity_id = entity_id.clone();
+            let instance = instance.clone();
+            let entity_id_2 = entity_id.clone();
+            let instance_2 = instance.clone();
+            let duration = std::time::Duration::from_secs(*duration_secs);
+            let button_name = name.to_string();
+            set_button_with_cooldown(
+                view,
+                x,
+                y,
+                cooldown,
+                LabelToggleButton::new(
+                    name,
+                    Some(md_icons::filled::ICON_TIMER),
+                    move |ctx: PluginContext| {
+                        let entity_id = entity_id.clone();
+                        let instance = instance.clone();
+                        let button_name = button_name.clone();
+                        async move {
+                            let hass = get_online_connection(&ctx, instance.as_deref()).await?;
+                            Ok((hass.has_off_timer(&entity_id), button_name))
+                        }
+                    },
+                    move |ctx, value| {
+                        let entity_id = entity_id_2.clone();
+                        let instance = instance_2.clone();
+                        let domain = domain.clone();
+                        async move {
+                            let hass = get_online_connection(&ctx, instance.as_deref()).await?;
+                            if !hass.accept_press(&entity_id) {
+                                return Err(IgnoredPress.into());
+                            }
+                            let data = Some(serde_json::json!({ "entity_id": entity_id }));
+                            if value {
+                                hass.call_service(&domain, "turn_on", data)
+                                    .await
+                                    .map_err(|e| e.to_string())?;
+                                hass.start_off_timer(&domain, &entity_id, duration);
+                            } else {
+                                hass.cancel_off_timer(&entity_id);
+                                hass.call_service(&domain, "turn_off", data)
+                                    .await
+                                    .map_err(|e| e.to_string())?;
+                            }
+                            Ok(())
+                        }
+                    },
+                )
+                .with_optional_theme(button_theme),
+            )
+        }
+        HomeAssistantButton::Group { instance, .. }
+        | HomeAssistantButton::MenuToggle { instance, .. } => {
+            let (entity_ids, domain) = match item {
+                HomeAssistantButton::Group {
+                    entity_ids, domain, ..
+                } => (entity_ids.clone(), domain.clone()),
+                // Follows the menu, so entities added to it are included too
+                _ => (
+                    parent.menu.toggle_entity_ids(instance.as_deref()),
+                    "homeassistant".to_string(),
+                ),
+            };
+            if entity_ids.is_empty() {
+                return Err(match item {
+                    HomeAssistantButton::Group { .. } => {
+                        format!("Group '{}' lists no entities", name)
+                    }
+                    _ => "No switches, toggles or lights in this menu".to_string(),
+                }
+                .into());
+            }
+            let instance = instance.clone();
+            let entity_ids_2 = entity_ids.clone();
+            let instance_2 = instance.clone();
+            let button_name = name.to_string();
+            set_button_with_cooldown(
+                view,
+                x,
+                y,
+                cooldown,
+                LabelToggleButton::new(
+                    name,
+                    Some(md_icons::filled::ICON_TOGGLE_OFF),
+                    move |ctx: PluginContext| {
+                        let entity_ids = entity_ids.clone();
+                        let instance = instance.clone();
+                        let button_name = button_name.clone();
+                        async move {
+                            let hass = get_online_connection(&ctx, instance.as_deref()).await?;
+
+                            // The group reads as on if any of its entities is on
+                            for entity_id in &entity_ids {
+                                if let Some(state) = hass.get_state(entity_id).await {
+                                    if state.is_on() {
+                                        return Ok((true, button_name));
+                                    }
+                                }
+                            }
+                            Ok((false, button_name))
+                        }
+                    },
+                    move |ctx, value| {
+                        let entity_ids = entity_ids_2.clone();
+                        let instance = instance_2.clone();
+                        let domain = domain.clone();
+                        async move {
+                            let hass = get_online_connection(&ctx, instance.as_deref()).await?;
+                            if !hass.accept_press(&entity_ids.join(",")) {
+                                return Err(IgnoredPress.into());
+                            }
+                            hass.call_service_batch(
+                                &domain,
+                                if value { "turn_on" } else { "turn_off" },
+                                &entity_ids,
+                                None,
+                            )
+                            .await
+                            .map_err(|e| e.to_string())?;
+                            Ok(())
+                        }
+                    },
+                )
+                .when_active(Some(md_icons::filled::ICON_TOGGLE_ON))
+                .with_optional_theme(button_theme),
+            )
+        }
+        HomeAssistantButton::Lock { entity_id, instance, .. } => {
             let entity_id = entity_id.clone();
+            let instance = instance.clone();
             let entity_id_2 = entity_id.clone();
+            let instance_2 = instance.clone();
+            let button_name = name.to_string();
+            set_button_with_cooldown(
+                view,
+                x,
+                y,
+                cooldown,
+                LabelToggleButton::new(
+                    name,
+                    Some(md_icons::filled::ICON_LOCK_OPEN),
+                    move |ctx: PluginContext| {
+                        let entity_id = entity_id.clone();
+                        let instance = instance.clone();
+                        let button_name = button_name.clone();
+                        async move {
+                            let hass = get_online_connection(&ctx, instance.as_deref()).await?;
+                            let state = hass
+                                .get_state(&entity_id)
+                                .await
+                                .ok_or("Failed to get state")?;
+                            ensure_available(&state.state)?;
+
+                            // Transient states like "locking"/"unlocking" display as locked
+                            Ok((state.state != "unlocked", button_name))
+                        }
+                    },
+                    move |ctx, value| {
+                        let entity_id = entity_id_2.clone();
+                        let instance = instance_2.clone();
+                        async move {
+                            let hass = get_online_connection(&ctx, instance.as_deref()).await?;
+                            if !hass.accept_press(&entity_id) {
+                                return Err(IgnoredPress.into());
+                            }
+                            hass.call_service(
+                                "lock",
+                                if value { "lock" } else { "unlock" },
+                                Some(serde_json::json!({ "entity_id": entity_id })),
+                            )
+                            .await
+                            .map_err(|e| e.to_string())?;
+                            Ok(())
+                        }
+                    },
+                )
+                .when_active(Some(md_icons::filled::ICON_LOCK))
+                .with_optional_theme(button_theme),
+            )
+        }
+        HomeAssistantButton::Sensor {
+            entity_id,
+            unit,
+            label_template,
+            show_last_changed,
+            rotate_labels,
+            instance,
+            ..
+        } => {
+            let entity_id = entity_id.clone();
+            let instance = instance.clone();
+            let unit = unit.clone();
+            let show_last_changed = *show_last_changed;
+            let label_template = label_template.clone();
+            let rotate_labels = rotate_labels.clone();
+            let rotate_interval = item.rotate_interval();
+            let button_name = name.to_string();
+            view.set_button(
+                x,
+                y,
+                LabelButton::new(
+                    name,
+                    Some(md_icons::filled::ICON_SENSORS),
+                    move |ctx: PluginContext| {
+                        let entity_id = entity_id.clone();
+                        let instance = instance.clone();
+                        let unit = unit.clone();
+                        let label_template = label_template.clone();
+                        let rotate_labels = rotate_labels.clone();
+                        let button_name = button_name.clone();
+                        async move {
+                            let hass = get_connection(&ctx, instance.as_deref()).await?;
+                            let state = hass
+                                .get_state(&entity_id)
+                                .await
+                                .ok_or("Failed to get state")?;
+                            ensure_available(&state.state)?;
+
+                            let template = rotate_interval
+                                .and_then(|period| rotating_template(&rotate_labels, period))
+                                .or(label_template.as_deref());
+                            if let Some(template) = template {
+                                return Ok(render_label(template, &button_name, &state));
+                            }
+
+                            // Fall back to the unit reported by HomeAssistant
+                            let unit = unit.or_else(|| {
+                                state.attributes["unit_of_measurement"]
+                                    .as_str()
+                                    .map(str::to_string)
+                            });
+                            let label = match unit {
+                                Some(unit) => format!("{} {}", state.state, unit),
+                                None => state.state.clone(),
+                            };
+                            Ok(match show_last_changed {
+                                true => format!("{} ({})", label, last_changed_text(&state)),
+                                false => label,
+                            })
+                        }
+                    },
+                )
+                .with_optional_theme(button_theme),
+            )
+        }
+        HomeAssistantButton::Weather {
+            entity_id,
+            rotate_labels,
+            instance,
+            ..
+        } => {
+            let entity_id = entity_id.clone();
+            let instance = instance.clone();
+            let rotate_labels = rotate_labels.clone();
+            let rotate_interval = item.rotate_interval();
+            let button_name = name.to_string();
             view.set_button(
                 x,
                 y,
+                IconLabelButton::new(
+                    name,
+                    md_icons::filled::ICON_WB_SUNNY,
+                    move |ctx: PluginContext| {
+                        let entity_id = entity_id.clone();
+                        let instance = instance.clone();
+                        let rotate_labels = rotate_labels.clone();
+                        let button_name = button_name.clone();
+                        async move {
+                            let hass = get_connection(&ctx, instance.as_deref()).await?;
+                            let state = hass
+                                .get_state(&entity_id)
+                                .await
+                                .ok_or("Failed to get state")?;
+
+                            let icon = condition_icon(&state.state);
+                            let template = rotate_interval
+                                .and_then(|period| rotating_template(&rotate_labels, period));
+                            if let Some(template) = template {
+                                return Ok((render_label(template, &button_name, &state), icon));
+                            }
+                            let temperature = state.attr::<f64>("temperature");
+                            let unit = state.attr::<String>("temperature_unit").unwrap_or_default();
+                            Ok(match temperature {
+                                Some(temperature) => (format!("{:.0}{}", temperature, unit), icon),
+                                None => (state.state, icon),
+                            })
+                        }
+                    },
+                )
+                .with_optional_theme(button_theme),
+            )
+        }
+        HomeAssistantButton::Gauge {
+            entity_id,
+            attribute,
+            min,
+            max,
+            instance,
+            ..
+        } => {
+            let entity_id = entity_id.clone();
+            let instance = instance.clone();
+            let attribute = attribute.clone();
+            let (min, max) = (*min, *max);
+            view.set_button(
+                x,
+                y,
+                IconLabelButton::new(name, gauge_icon(0.0), move |ctx: PluginContext| {
+                    let entity_id = entity_id.clone();
+                    let instance = instance.clone();
+                    let attribute = attribute.clone();
+                    async move {
+                        let hass = get_connection(&ctx, instance.as_deref()).await?;
+                        let state = hass
+                            .get_state(&entity_id)
+                            .await
+                            .ok_or("Failed to get state")?;
+
+                        let (value, label) = match &attribute {
+                            Some(attribute) => {
+                                (state.attr::<f64>(attribute), attribute_text(&state, attribute))
+                            }
+                            None => {
+                                let unit = state.attr::<String>("unit_of_measurement");
+                                let label = match unit {
+                                    Some(unit) => format!("{} {}", state.state, unit),
+                                    None => state.state.clone(),
+                                };
+                                (state.numeric_state(), label)
+                            }
+                        };
+                        // Keep showing the text of non-numeric states, e.g. "unavailable"
+                        let fraction = value.and_then(|value| gauge_fraction(value, min, max));
+                        Ok((label, gauge_icon(fraction.unwrap_or(0.0))))
+                    }
+                })
+                .with_optional_theme(button_theme),
+            )
+        }
+        HomeAssistantButton::Battery {
+            entity_id,
+            low,
+            instance,
+            ..
+        } => {
+            let entity_id = entity_id.clone();
+            let instance = instance.clone();
+            let low = *low;
+            view.set_button(
+                x,
+                y,
+                IconLabelButton::new(
+                    name,
+                    md_icons::filled::ICON_BATTERY_UNKNOWN,
+                    move |ctx: PluginContext| {
+                        let entity_id = entity_id.clone();
+                        let instance = instance.clone();
+                        async move {
+                            let hass = get_connection(&ctx, instance.as_deref()).await?;
+                            let state = hass
+                                .get_state(&entity_id)
+                                .await
+                                .ok_or("Failed to get state")?;
+
+                            // Devices report their charge as an attribute, battery sensors as the state
+                            let level = state
+                                .attr::<f64>("battery_level")
+                                .or_else(|| state.numeric_state());
+                            Ok(match level {
+                                Some(level) => (format!("{:.0}%", level), battery_icon(level, low)),
+                                None => (state.state, md_icons::filled::ICON_BATTERY_UNKNOWN),
+                            })
+                        }
+                    },
+                )
+                .with_alert_icon(md_icons::filled::ICON_BATTERY_ALERT)
+                .with_optional_theme(button_theme),
+            )
+        }
+        HomeAssistantButton::Summary {
+            entity_ids,
+            domain,
+            aggregate,
+            state,
+            instance,
+            ..
+        } => {
+            let entity_ids = entity_ids.clone();
+            let domain = domain.clone();
+            let aggregate = *aggregate;
+            let state = state.clone();
+            let instance = instance.clone();
+            let button_name = name.to_string();
+            view.set_button(
+                x,
+                y,
+                LabelButton::new(
+                    name,
+                    Some(md_icons::filled::ICON_DASHBOARD),
+                    move |ctx: PluginContext| {
+                        let mut entity_ids = entity_ids.clone();
+                        let domain = domain.clone();
+                        let state = state.clone();
+                        let instance = instance.clone();
+                        let button_name = button_name.clone();
+                        async move {
+                            let hass = get_connection(&ctx, instance.as_deref()).await?;
+                            if let Some(domain) = &domain {
+                                let prefix = format!("{}.", domain);
+                                let matching = hass
+                                    .list_entities()
+                                    .await
+                                    .into_iter()
+                                    .map(|(entity_id, _)| entity_id)
+                                    .filter(|entity_id| entity_id.starts_with(&prefix));
+                                for entity_id in matching {
+                                    if !entity_ids.contains(&entity_id) {
+                                        entity_ids.push(entity_id);
+                                    }
+                                }
+                            }
+                            let mut entities = Vec::with_capacity(entity_ids.len());
+                            for entity_id in &entity_ids {
+                                if let Some(entity) = hass.get_state(entity_id).await {
+                                    entities.push(entity);
+                                }
+                            }
+                            let summary = summary_text(&entities, aggregate, &state);
+                            Ok(format!("{} {}", button_name, summary))
+                        }
+                    },
+                )
+                .with_optional_theme(button_theme),
+            )
+        }
+        HomeAssistantButton::InputSelect { entity_id, instance, .. }
+        | HomeAssistantButton::Select { entity_id, instance, .. } => {
+            // Both domains have a select_next service, but under their own name
+            let domain = match item {
+                HomeAssistantButton::Select { .. } => "select",
+                _ => "input_select",
+            };
+            let entity_id = entity_id.clone();
+            let instance = instance.clone();
+            let entity_id_2 = entity_id.clone();
+            let instance_2 = instance.clone();
+            // The option chosen by the last press, shown until the cache catches up
+            let pending: Arc<std::sync::Mutex<Option<PendingOption>>> = Arc::default();
+            let pending_2 = pending.clone();
+            set_button_with_cooldown(
+                view,
+                x,
+                y,
+                cooldown,
+                LabelButton::new(
+                    name,
+                    Some(md_icons::filled::ICON_LIST),
+                    move |ctx: PluginContext| {
+                        let entity_id = entity_id.clone();
+                        let instance = instance.clone();
+                        let pending = pending.clone();
+                        async move {
+                            let hass = get_connection(&ctx, instance.as_deref()).await?;
+                            let state = hass
+                                .get_state(&entity_id)
+                                .await
+                                .ok_or("Failed to get state")?;
+
+                            Ok(shown_option(&pending, &state.state))
+                        }
+                    },
+                )
+                .on_click(move |ctx: PluginContext| {
+                    let entity_id = entity_id_2.clone();
+                    let instance = instance_2.clone();
+                    let pending = pending_2.clone();
+                    async move {
+                        let hass = get_connection(&ctx, instance.as_deref()).await?;
+                        if !hass.accept_press(&entity_id) {
+                            return Err(IgnoredPress.into());
+                        }
+                        let state = hass
+                            .get_state(&entity_id)
+                            .await
+                            .ok_or("Failed to get state")?;
+                        let current = shown_option(&pending, &state.state);
+                        hass.call_service(
+                            domain,
+                            "select_next",
+                            Some(serde_json::json!({
+                                "entity_id": entity_id,
+                                "cycle": true
+                            })),
+                        )
+                        .await
+                        .map_err(|e| e.to_string())?;
+
+                        // The cache is updated by the next poll; show the option right away
+                        let next = state
+                            .attr::<Vec<String>>("options")
+                            .and_then(|options| next_option(&options, &current));
+                        if let (Some(next), Ok(mut pending)) = (next, pending.lock()) {
+                            *pending = Some(PendingOption {
+                                cached: state.state,
+                                chosen: next,
+                            });
+                        }
+                        Ok(())
+                    }
+                })
+                .with_optional_theme(button_theme),
+            )
+        }
+        HomeAssistantButton::Template {
+            template,
+            refresh_secs,
+            instance,
+            ..
+        } => {
+            let template = template.clone();
+            let max_age = Duration::from_secs(*refresh_secs);
+            let instance = instance.clone();
+            view.set_button(
+                x,
+                y,
+                LabelButton::new(
+                    name,
+                    Some(md_icons::filled::ICON_DATA_OBJECT),
+                    move |ctx: PluginContext| {
+                        let template = template.clone();
+                        let instance = instance.clone();
+                        async move {
+                            let hass = get_connection(&ctx, instance.as_deref()).await?;
+                            let rendered = hass.render_template_cached(&template, max_age).await?;
+                            Ok(rendered.trim().to_string())
+                        }
+                    },
+                )
+                .with_optional_theme(button_theme),
+            )
+        }
+        HomeAssistantButton::Notify {
+            service,
+            title,
+            message,
+            instance,
+            ..
+        } => {
+            let service = service.clone();
+            let title = title.clone();
+            let message = message.clone();
+            let instance = instance.clone();
+            set_button_with_cooldown(
+                view,
+                x,
+                y,
+                cooldown,
+                FeedbackButton::new(
+                    name,
+                    Some(md_icons::filled::ICON_NOTIFICATIONS),
+                    move |ctx: PluginContext| {
+                        let service = service.clone();
+                        let title = title.clone();
+                        let message = message.clone();
+                        let instance = instance.clone();
+                        async move {
+                            let hass = get_connection(&ctx, instance.as_deref()).await?;
+                            let mut data = serde_json::json!({ "message": message });
+                            if let Some(title) = title {
+                                data["title"] = serde_json::Value::String(title);
+                            }
+                            hass.call_service("notify", &service, Some(data))
+                                .await
+                                .map_err(|e| e.to_string())?;
+                            Ok(())
+                        }
+                    },
+                )
+                .errors_only()
+                .with_optional_theme(button_theme),
+            )
+        }
+        HomeAssistantButton::FireEvent {
+            event_type,
+            event_data,
+            instance,
+            ..
+        } => {
+            let event_type = event_type.clone();
+            let event_data = event_data.clone();
+            let instance = instance.clone();
+            set_button_with_cooldown(
+                view,
+                x,
+                y,
+                cooldown,
+                FeedbackButton::new(
+                    name,
+                    Some(md_icons::filled::ICON_BOLT),
+                    move |ctx: PluginContext| {
+                        let event_type = event_type.clone();
+                        let event_data = event_data.clone();
+                        let instance = instance.clone();
+                        async move {
+                            let hass = get_connection(&ctx, instance.as_deref()).await?;
+                            hass.fire_event(&event_type, event_data)
+                                .await
+                                .map_err(|e| e.to_string())?;
+                            Ok(())
+                        }
+                    },
+                )
+                .errors_only()
+                .with_optional_theme(button_theme),
+            )
+        }
+        HomeAssistantButton::CameraSnapshot {
+            entity_id,
+            filename,
+            instance,
+            ..
+        } => {
+            let entity_id = entity_id.clone();
+            let filename = filename.clone();
+            let instance = instance.clone();
+            set_button_with_cooldown(
+                view,
+                x,
+                y,
+                cooldown,
+                FeedbackButton::new(
+                    name,
+                    Some(md_icons::filled::ICON_PHOTO_CAMERA),
+                    move |ctx: PluginContext| {
+                        let entity_id = entity_id.clone();
+                        let filename = filename.clone();
+                        let instance = instance.clone();
+                        async move {
+                            let hass = get_connection(&ctx, instance.as_deref()).await?;
+                            hass.call_service(
+                                "camera",
+                                "snapshot",
+                                Some(serde_json::json!({
+                                    "entity_id": entity_id,
+                                    "filename": filename
+                                })),
+                            )
+                            .await
+                            .map_err(|e| e.to_string())?;
+                            Ok(())
+                        }
+                    },
+                )
+                .errors_only()
+                .with_optional_theme(button_theme),
+            )
+        }
+        HomeAssistantButton::RgbLight {
+            entity_id,
+            instance,
+            transition,
+            ..
+        } => view.set_navigation(
+            x,
+            y,
+            PluginNavigation::new(rgb::RgbControllerPlugin {
+                entity_id: entity_id.clone(),
+                instance: instance.clone(),
+                back_navigation: back_navigation.clone(),
+                mode: rgb::RgbMode::Color,
+                transition: *transition,
+            }),
+            name,
+            Some(md_icons::filled::ICON_LIGHTBULB),
+        ),
+        HomeAssistantButton::Fan { entity_id, instance, .. } => view.set_navigation(
+            x,
+            y,
+            PluginNavigation::new(fan::FanControllerPlugin {
+                entity_id: entity_id.clone(),
+                instance: instance.clone(),
+                back_navigation: back_navigation.clone(),
+            }),
+            name,
+            Some(md_icons::filled::ICON_AIR),
+        ),
+        HomeAssistantButton::CoverPosition { entity_id, instance, .. } => view.set_navigation(
+            x,
+            y,
+            PluginNavigation::new(cover::CoverPositionPlugin {
+                entity_id: entity_id.clone(),
+                instance: instance.clone(),
+                back_navigation: back_navigation.clone(),
+            }),
+            name,
+            Some(md_icons::filled::ICON_BLINDS),
+        ),
+        HomeAssistantButton::CoverControl { entity_id, instance, .. } => view.set_navigation(
+            x,
+            y,
+            PluginNavigation::new(cover::CoverControlPlugin {
+                entity_id: entity_id.clone(),
+                instance: instance.clone(),
+                back_navigation: back_navigation.clone(),
+            }),
+            name,
+            Some(md_icons::filled::ICON_BLINDS),
+        ),
+        HomeAssistantButton::Custom {
+            handler,
+            entity_id,
+            options,
+            instance,
+            ..
+        } => {
+            let handler = handler.clone();
+            let instance = instance.clone();
+            let config = CustomButtonConfig {
+                name: name.to_string(),
+                entity_id: entity_id.clone(),
+                options: options.clone(),
+            };
+            let handler_2 = handler.clone();
+            let instance_2 = instance.clone();
+            let config_2 = config.clone();
+            set_button_with_cooldown(
+                view,
+                x,
+                y,
+                cooldown,
                 ToggleButton::new(
                     name,
-                    Some(md_icons::filled::ICON_TOGGLE_OFF),
+                    Some(md_icons::filled::ICON_EXTENSION),
                     move |ctx: PluginContext| {
-                        let entity_id = entity_id.clone();
+                        let handler = handler.clone();
+                        let instance = instance.clone();
+                        let config = config.clone();
                         async move {
-                            let hass = ctx
-                                .get_context::<PersistentHassConnection>()
-                                .await
-                                .ok_or("Failed to get PersistentHassConnection")?;
-                            let state = hass
-                                .get_state(&entity_id)
-                                .await
-                                .ok_or("Failed to get state")?;
-
-                            Ok(state.state == "on")
+                            let hass = get_connection(&ctx, instance.as_deref()).await?;
+                            let handler = get_handler(&ctx, &handler).await?;
+                            // Handler futures aren't Sync, so they run as their own task
+                            let active = tokio::spawn(async move {
+                                handler
+                                    .is_active(hass.as_ref(), &config)
+                                    .await
+                                    .map_err(|e| e.to_string())
+                            })
+                            .await??;
+                            Ok(active)
                         }
                     },
-                    move |ctx, value| {
-                        let entity_id = entity_id_2.clone();
+                    move |ctx, _value| {
+                        let handler = handler_2.clone();
+                        let instance = instance_2.clone();
+                        let config = config_2.clone();
                         async move {
-                            let hass = ctx
-                                .get_context::<PersistentHassConnection>()
-                                .await
-                                .ok_or("Failed to get PersistentHassConnection")?;
-                            hass.call_service(
-                                "switch",
-                                if value { "turn_on" } else { "turn_off" },
-                                Some(serde_json::json!({ "entity_id": entity_id })),
-                            )
-                            .await
-                            .map_err(|e| e.to_string())?;
+                            let hass = get_connection(&ctx, instance.as_deref()).await?;
+                            let handler = get_handler(&ctx, &handler).await?;
+                            tokio::spawn(async move {
+                                handler.press(hass.as_ref(), &config).await.map_err(|e| e.to_string())
+                            })
+                            .await??;
+                            Ok(())
+                        }
+                    },
+                )
+                .with_optional_theme(button_theme),
+            )
+        }
+        HomeAssistantButton::Refresh { instance, .. } => {
+            let instance = instance.clone();
+            set_button_with_cooldown(
+                view,
+                x,
+                y,
+                cooldown,
+                FeedbackButton::new(
+                    name,
+                    Some(md_icons::filled::ICON_REFRESH),
+                    move |ctx: PluginContext| {
+                        let instance = instance.clone();
+                        async move {
+                            let hass = get_connection(&ctx, instance.as_deref()).await?;
+                            hass.reconnect().await.map_err(|e| e.to_string())?;
                             Ok(())
                         }
                     },
                 )
-                .when_active(name, Some(md_icons::filled::ICON_TOGGLE_ON)),
+                .errors_only()
+                .with_optional_theme(button_theme),
             )
         }
-        HomeAssistantButton::RgbLight { entity_id, name } => view.set_navigation(
+        HomeAssistantButton::Alarm {
+            entity_id,
+            code,
+            instance,
+            ..
+        } => view.set_navigation(
             x,
             y,
-            PluginNavigation::new(rgb::RgbControllerPlugin {
+            PluginNavigation::new(alarm::AlarmPanelPlugin {
                 entity_id: entity_id.clone(),
+                instance: instance.clone(),
+                code: code.clone(),
                 back_navigation: back_navigation.clone(),
             }),
             name,
-            Some(md_icons::filled::ICON_LIGHTBULB),
+            Some(md_icons::filled::ICON_SECURITY),
         ),
-        HomeAssistantButton::Menu(home_assistant_menu) => view.set_navigation(
+        HomeAssistantButton::Vacuum { entity_id, instance, .. } => view.set_navigation(
+            x,
+            y,
+            PluginNavigation::new(vacuum::VacuumPlugin {
+                entity_id: entity_id.clone(),
+                instance: instance.clone(),
+                back_navigation: back_navigation.clone(),
+            }),
+            name,
+            Some(md_icons::filled::ICON_CLEANING_SERVICES),
+        ),
+        HomeAssistantButton::Humidifier { entity_id, instance, .. } => view.set_navigation(
+            x,
+            y,
+            PluginNavigation::new(humidifier::HumidifierPlugin {
+                entity_id: entity_id.clone(),
+                instance: instance.clone(),
+                back_navigation: back_navigation.clone(),
+            }),
+            name,
+            Some(md_icons::filled::ICON_WATER_DROP),
+        ),
+        HomeAssistantButton::Climate {
+            entity_id,
+            step,
+            instance,
+            ..
+        } => view.set_navigation(
             x,
             y,
-            PluginNavigation::new(HomeAssistantPlugin {
-                menu: home_assistant_menu.clone(),
+            PluginNavigation::new(climate::ClimatePlugin {
+                entity_id: entity_id.clone(),
+                instance: instance.clone(),
+                step: *step,
+                back_navigation: back_navigation.clone(),
+            }),
+            name,
+            Some(md_icons::filled::ICON_THERMOSTAT),
+        ),
+        HomeAssistantButton::WaterHeater {
+            entity_id,
+            step,
+            instance,
+            ..
+        } => view.set_navigation(
+            x,
+            y,
+            PluginNavigation::new(water_heater::WaterHeaterPlugin {
+                entity_id: entity_id.clone(),
+                instance: instance.clone(),
+                step: *step,
+                back_navigation: back_navigation.clone(),
+            }),
+            name,
+            Some(md_icons::filled::ICON_WATER_DROP),
+        ),
+        HomeAssistantButton::InputNumber {
+            entity_id,
+            step,
+            instance,
+            ..
+        } => view.set_navigation(
+            x,
+            y,
+            PluginNavigation::new(number::InputNumberPlugin {
+                entity_id: entity_id.clone(),
+                instance: instance.clone(),
+                step: *step,
+                back_navigation: back_navigation.clone(),
+            }),
+            name,
+            Some(md_icons::filled::ICON_TUNE),
+        ),
+        HomeAssistantButton::SceneList { domain, instance, .. } => view.set_navigation(
+            x,
+            y,
+            PluginNavigation::new(scenes::SceneListPlugin {
+                domain: domain.clone(),
+                instance: instance.clone(),
+                page: 0,
                 back_navigation: back_navigation.clone(),
             }),
-            home_assistant_menu.name.clone(),
+            name,
+            Some(md_icons::filled::ICON_PALETTE),
+        ),
+        HomeAssistantButton::Area {
+            area_id, instance, ..
+        } => view.set_navigation(
+            x,
+            y,
+            PluginNavigation::new(area::AreaPlugin {
+                parent: parent.clone(),
+                area_id: area_id.clone(),
+                name: name.to_string(),
+                instance: instance.clone(),
+            }),
+            name,
+            Some(md_icons::filled::ICON_HOME),
+        ),
+        HomeAssistantButton::Menu(home_assistant_menu) => view.set_navigation(
+            x,
+            y,
+            PluginNavigation::new(parent.submenu(home_assistant_menu.clone())),
+            name,
             Some(md_icons::filled::ICON_MENU),
         ),
     }
 }
 
+/// Creates a theme highlighting active buttons with the given color.
+///
+/// The other colors are those of the base theme, so only the active state
+/// looks different. The text color is chosen to stay readable on light
+/// colors such as amber.
+///
+/// # Arguments
+///
+/// * `base` - The theme the menu is drawn with
+/// * `color` - The active background color as (r, g, b) components (0-255)
+fn active_color_theme(base: ThemeName, color: (u8, u8, u8)) -> Theme {
+    let (r, g, b) = color;
+    let active_foreground = if rgb::luminance(color) > rgb::LIGHT_BACKGROUND_LUMINANCE {
+        Color::from_rgba8(20, 20, 25, 255)
+    } else {
+        Color::from_rgba8(255, 255, 255, 255)
+    };
+
+    // Mirrors Theme::light() and Theme::dark(), whose colors can't be read back
+    let (background, inactive, pressed, error, foreground) = match base {
+        ThemeName::Light => (
+            Color::from_rgba8(240, 240, 245, 255),
+            Color::from_rgba8(200, 200, 210, 255),
+            Color::from_rgba8(0, 180, 180, 255),
+            Color::from_rgba8(255, 59, 48, 255),
+            Color::from_rgba8(30, 30, 30, 255),
+        ),
+        ThemeName::Dark => (
+            Color::from_rgba8(20, 20, 25, 255),
+            Color::from_rgba8(41, 41, 51, 255),
+            Color::from_rgba8(51, 217, 230, 255),
+            Color::from_rgba8(255, 89, 0, 255),
+            Color::from_rgba8(242, 242, 255, 255),
+        ),
+    };
+    Theme::new(
+        background,
+        Color::from_rgba8(r, g, b, 255),
+        inactive,
+        pressed,
+        error,
+        foreground,
+        active_foreground,
+    )
+}
+
+/// Resolves the display name of a button.
+///
+/// Uses the configured name, falling back to the entity's cached
+/// `friendly_name` and then to the entity ID. The cache may still be
+/// empty on the first render, so the entity ID is shown until the next
+/// time the menu is opened.
+///
+/// # Arguments
+///
+/// * `context` - The plugin context
+/// * `item` - The button configuration
+async fn button_name(context: &PluginContext, item: &HomeAssistantButton) -> String {
+    if let Some(name) = item.name() {
+        return name.to_string();
+    }
+    let Some(entity_id) = item.entity_id() else {
+        return String::new();
+    };
+    let hass = get_connection(context, item.instance()).await.ok();
+    if let Some(hass) = hass {
+        if let Some(state) = hass.get_state(entity_id).await {
+            if let Some(friendly_name) = state.attributes["friendly_name"].as_str() {
+                return friendly_name.to_string();
+            }
+        }
+    }
+    entity_id.to_string()
+}
+
+/// Fetches the lazy entities used by some buttons, which the regular update leaves out.
+///
+/// Failures are only logged; the buttons then show the last known states.
+///
+/// # Arguments
+///
+/// * `context` - The plugin context holding the connections
+/// * `config` - The configuration listing the lazy entities
+/// * `items` - The buttons about to be shown
+async fn refresh_lazy_entities<'a>(
+    context: &PluginContext,
+    config: &HomeAssistantConfig,
+    items: impl Iterator<Item = &'a HomeAssistantButton>,
+) {
+    if config.lazy_entities.is_empty() {
+        return;
+    }
+    let mut by_instance: std::collections::BTreeMap<Option<&str>, Vec<String>> =
+        std::collections::BTreeMap::new();
+    for item in items {
+        let lazy = item
+            .entity_ids()
+            .into_iter()
+            .filter(|entity_id| config.lazy_entities.iter().any(|lazy| lazy == entity_id));
+        by_instance
+            .entry(item.instance())
+            .or_default()
+            .extend(lazy.map(str::to_string));
+    }
+    for (instance, entity_ids) in by_instance {
+        if entity_ids.is_empty() {
+            continue;
+        }
+        let hass = get_connection(context, instance).await.map_err(|e| e.to_string());
+        let refreshed = match hass {
+            Ok(hass) => hass.refresh_entities(&entity_ids).await.map_err(|e| e.to_string()),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = refreshed {
+            log::warn!("Failed to refresh {}: {}", entity_ids.join(", "), e);
+        }
+    }
+}
+
+/// Decides whether a button is shown, rendering its visibility template if it has one.
+///
+/// Buttons stay visible if the template can't be rendered, so a broken
+/// template doesn't make them disappear without a trace.
+async fn is_visible(context: &PluginContext, item: &HomeAssistantButton) -> bool {
+    let HomeAssistantButton::Template {
+        visible: Some(visible),
+        name,
+        refresh_secs,
+        ..
+    } = item
+    else {
+        return true;
+    };
+    let hass = get_connection(context, item.instance()).await.map_err(|e| e.to_string());
+    let rendered = match hass {
+        Ok(hass) => hass
+            .render_template_cached(visible, Duration::from_secs(*refresh_secs))
+            .await
+            .map_err(|e| e.to_string()),
+        Err(e) => Err(e),
+    };
+    match rendered {
+        Ok(rendered) => template_is_true(&rendered),
+        Err(e) => {
+            log::warn!("Showing button '{}', its visibility template failed: {}", name, e);
+            true
+        }
+    }
+}
+
+/// Lists the entities of a button that don't exist in HomeAssistant.
+///
+/// Nothing is reported before the first state fetch, since the cache can't
+/// tell missing entities apart from ones that haven't been loaded yet.
+///
+/// # Arguments
+///
+/// * `context` - The plugin context
+/// * `item` - The button configuration
+async fn missing_entities(context: &PluginContext, item: &HomeAssistantButton) -> Vec<String> {
+    let Some(hass) = get_connection(context, item.instance()).await.ok() else {
+        return Vec::new();
+    };
+    if !hass.has_states().await {
+        return Vec::new();
+    }
+    let mut missing = Vec::new();
+    for entity_id in item.entity_ids() {
+        if hass.get_state(entity_id).await.is_none() {
+            missing.push(entity_id.to_string());
+        }
+    }
+    missing
+}
+
+/// Where the buttons of one menu page go on the deck.
+///
+/// Slots are key indices counted row by row from the top left.
+#[derive(Debug, Default, PartialEq)]
+struct MenuLayout {
+    /// Indices of the menu buttons shown on this page, with their slots
+    buttons: Vec<(usize, usize)>,
+    /// Slots of the favorites that fit, in order
+    favorites: Range<usize>,
+    /// Slot of the "Home" button, if requested and there is room
+    home: Option<usize>,
+    /// Indices of pinned buttons whose key is reserved or off the deck
+    skipped: Vec<usize>,
+    /// Slot of the "Previous" button, if there is an earlier page
+    previous: Option<usize>,
+    /// Slot of the "Next" button, if there is a later page
+    next: Option<usize>,
+    /// The page shown, clamped to the last page
+    page: usize,
+    /// Number of unpinned buttons that fit on no page
+    overflow: usize,
+}
+
+/// Lays out a menu page on a deck of the given size.
+///
+/// Favorites sit in fixed slots just before the last key, so they don't move
+/// when navigating, followed by the "Home" button on their left. The last
+/// key is kept for the back button. Pinned
+/// buttons keep their key on every page while the rest fill the remaining
+/// keys, with two of them used for "Previous" and "Next" when paginated.
+///
+/// # Arguments
+///
+/// * `width` - Number of columns on the deck
+/// * `height` - Number of rows on the deck
+/// * `positions` - The pinned position of each menu button, if any
+/// * `favorites` - Number of configured favorites
+/// * `back` - Whether the menu has a back button
+/// * `home` - Whether the menu has a "Home" button
+/// * `page` - The requested page
+fn menu_layout(
+    width: usize,
+    height: usize,
+    positions: &[Option<ButtonPosition>],
+    favorites: usize,
+    back: bool,
+    home: bool,
+    page: usize,
+) -> MenuLayout {
+    let keys = width * height;
+    let max_reserved = width.saturating_sub(1).min(keys.saturating_sub(2));
+    let home = home && max_reserved > 0;
+    let favorites = favorites.min(max_reserved - usize::from(home));
+    let favorite_slots = keys.saturating_sub(1 + favorites)..keys.saturating_sub(1);
+    let home_slot = home.then(|| favorite_slots.start - 1);
+
+    let mut free_slots: Vec<usize> = (0..keys)
+        .filter(|slot| !favorite_slots.contains(slot) && Some(*slot) != home_slot)
+        .filter(|slot| !back || *slot != keys - 1)
+        .collect();
+
+    let mut layout = MenuLayout {
+        favorites: favorite_slots,
+        home: home_slot,
+        ..MenuLayout::default()
+    };
+    let mut pinned = Vec::new();
+    let mut unpinned = Vec::new();
+    for (index, position) in positions.iter().enumerate() {
+        let Some(position) = position else {
+            unpinned.push(index);
+            continue;
+        };
+        let slot = position.y * width + position.x;
+        match free_slots.iter().position(|free| *free == slot) {
+            Some(free) if position.x < width => {
+                free_slots.remove(free);
+                pinned.push((index, slot));
+            }
+            _ => layout.skipped.push(index),
+        }
+    }
+    let available = free_slots.len();
+
+    // Paginated menus need at least one slot left over for buttons
+    let paginated = unpinned.len() > available && available > 2;
+    let page_size = if paginated { available - 2 } else { available };
+    if !paginated {
+        layout.overflow = unpinned.len().saturating_sub(available);
+    }
+    let page_count = match page_size {
+        0 => 1,
+        page_size => unpinned.len().div_ceil(page_size).max(1),
+    };
+    layout.page = page.min(page_count - 1);
+
+    layout.buttons = unpinned
+        .into_iter()
+        .skip(layout.page * page_size)
+        .take(page_size)
+        .zip(free_slots.iter().copied())
+        .chain(pinned)
+        .collect();
+    if paginated {
+        if layout.page > 0 {
+            layout.previous = Some(free_slots[available - 2]);
+        }
+        if layout.page + 1 < page_count {
+            layout.next = Some(free_slots[available - 1]);
+        }
+    }
+    layout
+}
+
 /// Generates a menu view from a HomeAssistantPlugin configuration.
 ///
 /// # Arguments
 ///
 /// * `plugin` - The plugin configuration
+/// * `context` - The plugin context, used to look up entity names
 ///
 /// # Returns
 ///
 /// A customizable view with buttons configured according to the plugin
-fn generate_menu<W, H>(
+async fn generate_menu<W, H>(
     plugin: &HomeAssistantPlugin<W, H>,
-) -> Result<CustomizableView<W, H, PluginContext, PluginNavigation<W, H>>, Box<dyn std::error::Error>>
+    context: &PluginContext,
+) -> Result<MenuView<W, H>, Box<dyn std::error::Error>>
 where
     W: ArrayLength,
     H: ArrayLength,
 {
     let mut view = CustomizableView::new();
-    let back_navigation = Some(PluginNavigation::<W, H>::new(HomeAssistantPlugin::<W, H> {
-        menu: plugin.menu.clone(),
-        back_navigation: plugin.back_navigation.clone(),
-    }));
-    for (index, item) in plugin.menu.buttons.iter().enumerate() {
-        if index > W::to_usize() * H::to_usize() {
-            break;
+    let config = get_config(context).await;
+
+    // Without a menu theme, buttons are drawn with the configured top-level theme
+    let base_theme = match plugin.menu.theme.or(plugin.theme) {
+        Some(theme) => theme,
+        None => config.as_ref().map(|config| config.theme).unwrap_or_default(),
+    };
+
+    let favorites = config
+        .as_ref()
+        .map(|config| config.favorites.clone())
+        .unwrap_or_default();
+    let home = plugin.path.len() > 1 && config.as_ref().is_some_and(|config| config.home_button);
+    let mut buttons = Vec::with_capacity(plugin.menu.buttons.len());
+    for item in &plugin.menu.buttons {
+        if is_visible(context, item).await {
+            buttons.push(item);
+        }
+    }
+    let positions: Vec<_> = buttons.iter().map(|item| item.position()).collect();
+    let layout = menu_layout(
+        W::to_usize(),
+        H::to_usize(),
+        &positions,
+        favorites.len(),
+        plugin.back_navigation.is_some(),
+        home,
+        plugin.page,
+    );
+
+    if layout.favorites.len() < favorites.len() {
+        log::warn!(
+            "{} favorites configured but only {} fit on this Stream Deck; skipping the rest",
+            favorites.len(),
+            layout.favorites.len()
+        );
+    }
+    for &index in &layout.skipped {
+        let item = buttons[index];
+        if let Some(position) = item.position() {
+            log::warn!(
+                "Skipping button '{}' pinned to ({}, {}), which is reserved or off the deck",
+                item.name().unwrap_or_default(),
+                position.x,
+                position.y
+            );
+        }
+    }
+    if layout.overflow > 0 {
+        log::warn!(
+            "Menu '{}' has {} buttons that don't fit on this Stream Deck; skipping them",
+            plugin.menu.name,
+            layout.overflow
+        );
+    }
+
+    let favorites = &favorites[..layout.favorites.len()];
+    let placements: Vec<(&HomeAssistantButton, usize)> = layout
+        .buttons
+        .iter()
+        .map(|&(index, slot)| (buttons[index], slot))
+        .chain(favorites.iter().zip(layout.favorites.clone()))
+        .collect();
+
+    // Refresh as often as the fastest rotating label or cooldown on this page needs
+    if let Some(refresh) = &plugin.refresh {
+        let interval = placements
+            .iter()
+            .filter_map(|(item, _)| item.refresh_interval())
+            .min();
+        refresh.set(interval.map(|interval| (PluginNavigation::new(plugin.clone()), interval)));
+    }
+
+    if let Some(config) = &config {
+        refresh_lazy_entities(context, config, placements.iter().map(|(item, _)| *item)).await;
+    }
+
+    for (item, slot) in placements {
+        let x = slot % W::to_usize();
+        let y = slot / W::to_usize();
+        let name = button_name(context, item).await;
+        let missing = missing_entities(context, item).await;
+        let result = if missing.is_empty() {
+            add_button(&mut view, x, y, item, &name, plugin, base_theme)
+        } else {
+            log::warn!(
+                "Button '{}' refers to unknown entities: {}",
+                name,
+                missing.join(", ")
+            );
+            view.set_button(
+                x,
+                y,
+                StaticButton::new(
+                    name.clone(),
+                    Some(md_icons::filled::ICON_ERROR),
+                    ButtonState::Error,
+                ),
+            )
+        };
+
+        // One broken button shouldn't keep the rest of the menu from rendering
+        if let Err(e) = result {
+            log::warn!("Skipping button '{}' at ({}, {}): {}", name, x, y, e);
         }
-        let x = index % W::to_usize();
-        let y = index / W::to_usize();
-        add_button(&mut view, x, y, item, &back_navigation)?;
     }
 
+    if let Some(slot) = layout.previous {
+        view.set_navigation(
+            slot % W::to_usize(),
+            slot / W::to_usize(),
+            PluginNavigation::new(HomeAssistantPlugin::<W, H> {
+                menu: plugin.menu.clone(),
+                back_navigation: plugin.back_navigation.clone(),
+                page: layout.page - 1,
+                theme: plugin.theme,
+                path: plugin.path.clone(),
+                refresh: plugin.refresh.clone(),
+                root: plugin.root.clone(),
+            }),
+            "Previous",
+            Some(md_icons::filled::ICON_NAVIGATE_BEFORE),
+        )?;
+    }
+    if let Some(slot) = layout.next {
+        view.set_navigation(
+            slot % W::to_usize(),
+            slot / W::to_usize(),
+            PluginNavigation::new(HomeAssistantPlugin::<W, H> {
+                menu: plugin.menu.clone(),
+                back_navigation: plugin.back_navigation.clone(),
+                page: layout.page + 1,
+                theme: plugin.theme,
+                path: plugin.path.clone(),
+                refresh: plugin.refresh.clone(),
+                root: plugin.root.clone(),
+            }),
+            "Next",
+            Some(md_icons::filled::ICON_NAVIGATE_NEXT),
+        )?;
+    }
+
+    // Deep menus can skip straight back to the root
+    if let Some(slot) = layout.home {
+        view.set_navigation(
+            slot % W::to_usize(),
+            slot / W::to_usize(),
+            plugin.root_navigation(),
+            "Home",
+            Some(md_icons::filled::ICON_HOME),
+        )?;
+    }
+
+    // Views have no title, so the back button shows where it leads
     if let Some(back_navigation) = &plugin.back_navigation {
         view.set_navigation(
             W::to_usize() - 1,
             H::to_usize() - 1,
             back_navigation.clone(),
-            "Back",
+            back_label(&plugin.path),
             Some(md_icons::filled::ICON_MENU),
         )?;
     }
@@ -167,6 +2071,20 @@ where
     Ok(view)
 }
 
+/// Returns the label of a "Back" button: the name of the menu it leads to,
+/// marked with "… › " if that menu has parents of its own.
+///
+/// # Arguments
+///
+/// * `path` - Names of the parent menus, starting at the root
+fn back_label(path: &[String]) -> String {
+    match path {
+        [] => "Back".to_string(),
+        [parent] => parent.clone(),
+        [.., parent] => format!("… › {}", parent),
+    }
+}
+
 /// Implementation of the StreamDeck Plugin trait for HomeAssistantPlugin.
 #[async_trait::async_trait]
 impl<W, H> Plugin<W, H> for HomeAssistantPlugin<W, H>
@@ -185,10 +2103,301 @@ where
         Box<dyn View<W, H, PluginContext, PluginNavigation<W, H>>>,
         Box<dyn std::error::Error>,
     > {
-        let _config = context
-            .get_context::<HomeAssistantConfig>()
+        get_config(&context)
             .await
             .ok_or("Failed to get HomeAssistantConfig")?;
-        Ok(Box::new(generate_menu(self)?))
+        Ok(Box::new(generate_menu(self, &context).await?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        any::{Any, TypeId},
+        collections::BTreeMap,
+    };
+
+    use streamdeck_oxide::{
+        generic_array::typenum::{U3, U5},
+        NavigationEntry,
+    };
+
+    use super::*;
+    use crate::mock::MockHassConnection;
+
+    fn pin(x: usize, y: usize) -> Option<ButtonPosition> {
+        Some(ButtonPosition { x, y })
+    }
+
+    /// Creates a plugin context with the example configuration, backed by a mock connection.
+    fn example_context(hass: Arc<MockHassConnection>) -> PluginContext {
+        config_context(include_str!("../config.yaml"), hass)
+    }
+
+    /// Creates a plugin context with a YAML configuration, backed by a mock connection.
+    fn config_context(config: &str, hass: Arc<MockHassConnection>) -> PluginContext {
+        let config: HomeAssistantConfig = serde_yaml::from_str(config).unwrap();
+        PluginContext::new(BTreeMap::from([
+            (
+                TypeId::of::<HomeAssistantConfig>(),
+                Box::new(Arc::new(config)) as Box<dyn Any + Send + Sync>,
+            ),
+            (
+                TypeId::of::<HassConnections>(),
+                Box::new(Arc::new(HassConnections::new(hass))),
+            ),
+        ]))
+    }
+
+    #[tokio::test]
+    async fn example_config_runs_against_the_mock() {
+        let hass = Arc::new(
+            MockHassConnection::new()
+                .with_state("switch.bedroom_light_switch_1", "off", serde_json::json!({}))
+                .with_state("light.h619a", "on", serde_json::json!({}))
+                .with_state("switch.bedroom_light", "on", serde_json::json!({}))
+                .with_state("light.bedroom_rgb", "off", serde_json::json!({})),
+        );
+        let context = example_context(hass.clone());
+        let config = get_config(&context).await.unwrap();
+        let root = HomeAssistantPlugin::<U5, U3> {
+            menu: config.menu.clone(),
+            back_navigation: None,
+            page: 0,
+            theme: None,
+            path: Vec::new(),
+            refresh: None,
+            root: None,
+        };
+        let (sender, mut receiver) = tokio::sync::mpsc::channel(1);
+        let sender = Arc::new(sender);
+
+        let view = root.get_view(context.clone()).await.unwrap();
+        view.fetch_all(&context).await.unwrap();
+        view.render().await.unwrap();
+
+        // The first button turns the switch on through the mock
+        view.on_click(&context, 0, sender.clone()).await.unwrap();
+        let calls = hass.calls();
+        assert_eq!(calls.len(), 1);
+        assert_eq!((calls[0].domain.as_str(), calls[0].service.as_str()), ("switch", "turn_on"));
+        let state = HassBackend::get_state(hass.as_ref(), "switch.bedroom_light_switch_1").await;
+        assert_eq!(state.unwrap().state, "on");
+
+        // The third button opens the bedroom submenu
+        view.on_click(&context, 2, sender.clone()).await.unwrap();
+        let submenu = receiver.try_recv().unwrap().get_view(context.clone()).await.unwrap();
+        submenu.fetch_all(&context).await.unwrap();
+        submenu.render().await.unwrap();
+        submenu.on_click(&context, 0, sender.clone()).await.unwrap();
+        let calls = hass.calls();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(
+            calls[1].data,
+            Some(serde_json::json!({ "entity_id": "switch.bedroom_light" }))
+        );
+        assert_eq!(calls[1].service, "turn_off");
+
+        // Its last key leads back to the root menu
+        submenu.on_click(&context, 14, sender).await.unwrap();
+        let back = receiver.try_recv().unwrap().get_view(context.clone()).await.unwrap();
+        back.fetch_all(&context).await.unwrap();
+        back.render().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn opening_a_menu_refreshes_its_lazy_entities() {
+        let hass = Arc::new(
+            MockHassConnection::new()
+                .with_state("light.desk", "on", serde_json::json!({}))
+                .with_state("sensor.energy", "1.5", serde_json::json!({}))
+                .with_state("sensor.solar", "0.3", serde_json::json!({})),
+        );
+        let context = config_context(
+            "
+            url: ws://ha/api/websocket
+            only_cache_used_entities: true
+            lazy_entities: [sensor.energy, sensor.solar]
+            menu:
+              name: Home
+              buttons:
+                - type: toggle
+                  entity_id: light.desk
+                - type: sensor
+                  entity_id: sensor.energy
+                - type: menu
+                  name: Solar
+                  buttons:
+                    - type: sensor
+                      entity_id: sensor.solar
+            ",
+            hass.clone(),
+        );
+        let config = get_config(&context).await.unwrap();
+        let root = HomeAssistantPlugin::<U5, U3> {
+            menu: config.menu.clone(),
+            back_navigation: None,
+            page: 0,
+            theme: None,
+            path: Vec::new(),
+            refresh: None,
+            root: None,
+        };
+
+        root.get_view(context.clone()).await.unwrap();
+
+        // Entities of submenus wait until those open
+        assert_eq!(hass.refreshed(), ["sensor.energy"]);
+        let filter = config.entity_filter();
+        assert!(filter.entity_ids.contains("light.desk"));
+        assert!(!filter.entity_ids.contains("sensor.energy"));
+        assert!(filter.matches("sensor.energy"));
+    }
+
+    #[test]
+    fn back_label_names_the_parent() {
+        let path = |names: &[&str]| names.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+        assert_eq!(back_label(&[]), "Back");
+        assert_eq!(back_label(&path(&["Home"])), "Home");
+        assert_eq!(back_label(&path(&["Home", "Bedroom", "Lights"])), "… › Lights");
+    }
+
+    #[test]
+    fn next_option_cycles() {
+        let options = ["Home", "Away", "Sleep"].map(String::from);
+        assert_eq!(next_option(&options, "Home").as_deref(), Some("Away"));
+        assert_eq!(next_option(&options, "Sleep").as_deref(), Some("Home"));
+        assert_eq!(next_option(&options, "Party").as_deref(), Some("Home"));
+        assert_eq!(next_option(&[], "Home"), None);
+    }
+
+    #[test]
+    fn shown_option_waits_for_the_cache() {
+        let pending = std::sync::Mutex::new(Some(PendingOption {
+            cached: "Home".to_string(),
+            chosen: "Away".to_string(),
+        }));
+        assert_eq!(shown_option(&pending, "Home"), "Away");
+        assert_eq!(shown_option(&pending, "Sleep"), "Sleep");
+        assert!(pending.lock().unwrap().is_none());
+        assert_eq!(shown_option(&pending, "Home"), "Home");
+    }
+
+    #[test]
+    fn menu_layout_fills_keys_row_by_row() {
+        let layout = menu_layout(3, 2, &[None, None, None], 0, true, false, 0);
+        assert_eq!(layout.buttons, vec![(0, 0), (1, 1), (2, 2)]);
+        assert_eq!(layout.previous, None);
+        assert_eq!(layout.next, None);
+        assert_eq!(layout.overflow, 0);
+    }
+
+    #[test]
+    fn menu_layout_uses_last_key_without_back_button() {
+        let layout = menu_layout(3, 2, &[None; 6], 0, false, false, 0);
+        assert_eq!(layout.buttons.last(), Some(&(5, 5)));
+        assert_eq!(layout.next, None);
+    }
+
+    #[test]
+    fn menu_layout_paginates_overflowing_menus() {
+        // 5 free keys, 2 of them for navigation, leaves 3 buttons per page
+        let positions = [None; 7];
+        let first = menu_layout(3, 2, &positions, 0, true, false, 0);
+        assert_eq!(first.buttons, vec![(0, 0), (1, 1), (2, 2)]);
+        assert_eq!(first.previous, None);
+        assert_eq!(first.next, Some(4));
+
+        let second = menu_layout(3, 2, &positions, 0, true, false, 1);
+        assert_eq!(second.buttons, vec![(3, 0), (4, 1), (5, 2)]);
+        assert_eq!(second.previous, Some(3));
+        assert_eq!(second.next, Some(4));
+
+        let last = menu_layout(3, 2, &positions, 0, true, false, 2);
+        assert_eq!(last.buttons, vec![(6, 0)]);
+        assert_eq!(last.previous, Some(3));
+        assert_eq!(last.next, None);
+    }
+
+    #[test]
+    fn menu_layout_clamps_page() {
+        let layout = menu_layout(3, 2, &[None; 7], 0, true, false, 10);
+        assert_eq!(layout.page, 2);
+        assert_eq!(layout.buttons, vec![(6, 0)]);
+    }
+
+    #[test]
+    fn menu_layout_reports_overflow_when_too_small_to_paginate() {
+        let layout = menu_layout(1, 2, &[None; 3], 0, true, false, 0);
+        assert_eq!(layout.buttons, vec![(0, 0)]);
+        assert_eq!(layout.overflow, 2);
+        assert_eq!(layout.next, None);
+    }
+
+    #[test]
+    fn menu_layout_places_favorites_before_last_key() {
+        let layout = menu_layout(5, 3, &[None; 2], 2, true, false, 0);
+        assert_eq!(layout.favorites, 12..14);
+        assert_eq!(layout.buttons, vec![(0, 0), (1, 1)]);
+    }
+
+    #[test]
+    fn menu_layout_caps_favorites_to_one_row() {
+        let layout = menu_layout(3, 2, &[], 5, true, false, 0);
+        assert_eq!(layout.favorites, 3..5);
+    }
+
+    #[test]
+    fn menu_layout_skips_favorite_slots_when_paginating() {
+        // 3x2 with one favorite and a back button leaves 4 keys: 2 buttons per page
+        let layout = menu_layout(3, 2, &[None; 5], 1, true, false, 0);
+        assert_eq!(layout.favorites, 4..5);
+        assert_eq!(layout.buttons, vec![(0, 0), (1, 1)]);
+        assert_eq!(layout.next, Some(3));
+    }
+
+    #[test]
+    fn menu_layout_places_home_left_of_favorites() {
+        let layout = menu_layout(5, 3, &[None; 2], 2, true, true, 0);
+        assert_eq!(layout.favorites, 12..14);
+        assert_eq!(layout.home, Some(11));
+
+        let layout = menu_layout(5, 3, &[None; 2], 0, true, true, 0);
+        assert_eq!(layout.home, Some(13));
+    }
+
+    #[test]
+    fn menu_layout_makes_room_for_home_in_favorites_row() {
+        let layout = menu_layout(3, 2, &[], 5, true, true, 0);
+        assert_eq!(layout.favorites, 4..5);
+        assert_eq!(layout.home, Some(3));
+    }
+
+    #[test]
+    fn menu_layout_skips_home_on_single_column_decks() {
+        let layout = menu_layout(1, 3, &[None], 0, true, true, 0);
+        assert_eq!(layout.home, None);
+        assert_eq!(layout.buttons, vec![(0, 0)]);
+    }
+
+    #[test]
+    fn menu_layout_keeps_pinned_buttons_on_every_page() {
+        let positions = [None, pin(0, 0), None, None, None, None];
+        for page in 0..2 {
+            let layout = menu_layout(3, 2, &positions, 0, true, false, page);
+            assert!(layout.buttons.contains(&(1, 0)));
+            assert!(layout.buttons.iter().all(|&(index, slot)| index == 1 || slot != 0));
+        }
+        let first = menu_layout(3, 2, &positions, 0, true, false, 0);
+        assert_eq!(first.buttons, vec![(0, 1), (2, 2), (1, 0)]);
+        assert_eq!(first.next, Some(4));
+    }
+
+    #[test]
+    fn menu_layout_skips_reserved_and_off_deck_pins() {
+        let positions = [pin(2, 1), pin(3, 0), pin(0, 5), None];
+        let layout = menu_layout(3, 2, &positions, 0, true, false, 0);
+        assert_eq!(layout.skipped, vec![0, 1, 2]);
+        assert_eq!(layout.buttons, vec![(3, 0)]);
     }
 }
@@ -0,0 +1,146 @@
+//! Alarm control panel plugin for HomeAssistant.
+//!
+//! This plugin provides arm home, arm away and disarm buttons for alarm control panels in HomeAssistant.
+
+use streamdeck_oxide::{
+    generic_array::ArrayLength, md_icons, plugins::{Plugin, PluginContext, PluginNavigation}, view::customizable::{CustomizableView, ToggleButton}, View
+};
+
+use crate::{buttons::LabelButton, config::AlarmCode, plugins::get_connection};
+
+/// Plugin for controlling alarm control panels in HomeAssistant.
+///
+/// This plugin displays a button per alarm mode, highlighting the one matching
+/// the panel's current state, and a label showing the raw state such as
+/// "pending" or "triggered".
+#[derive(Clone)]
+pub struct AlarmPanelPlugin<W: ArrayLength, H: ArrayLength> {
+    /// Optional navigation to return to when "Back" is pressed
+    pub(crate) back_navigation: Option<PluginNavigation<W, H>>,
+    /// The entity ID of the alarm control panel to control
+    pub(crate) entity_id: String,
+    /// Optional name of the HomeAssistant instance the entity belongs to
+    pub(crate) instance: Option<String>,
+    /// The code sent with every service call
+    pub(crate) code: AlarmCode,
+}
+
+/// Alarm modes as (label, icon, service, state while active)
+const MODES: &[(&str, &str, &str, &str)] = &[
+    ("Arm Home", md_icons::filled::ICON_HOME, "alarm_arm_home", "armed_home"),
+    ("Arm Away", md_icons::filled::ICON_SHIELD, "alarm_arm_away", "armed_away"),
+    ("Disarm", md_icons::filled::ICON_LOCK_OPEN, "alarm_disarm", "disarmed"),
+];
+
+/// Implementation of the StreamDeck Plugin trait for AlarmPanelPlugin.
+#[async_trait::async_trait]
+impl<W, H> Plugin<W, H> for AlarmPanelPlugin<W, H>
+where
+    W: ArrayLength,
+    H: ArrayLength,
+{
+    fn name(&self) -> &'static str {
+        "AlarmPanelPlugin"
+    }
+
+    async fn get_view(
+        &self,
+        _context: PluginContext,
+    ) -> Result<
+        Box<dyn View<W, H, PluginContext, PluginNavigation<W, H>>>,
+        Box<dyn std::error::Error>,
+    > {
+        let mut view = CustomizableView::new();
+        let position = |index: usize| (index % W::to_usize(), index / W::to_usize());
+
+        // Add a button per alarm mode
+        for (index, &(mode_name, icon, service, active_state)) in MODES.iter().enumerate() {
+            let entity_id = self.entity_id.clone();
+            let instance = self.instance.clone();
+            let entity_id_2 = entity_id.clone();
+            let instance_2 = instance.clone();
+            let code = self.code.clone();
+
+            let (x, y) = position(index);
+            view.set_button(
+                x,
+                y,
+                ToggleButton::new(
+                    mode_name,
+                    Some(icon),
+                    move |ctx: PluginContext| {
+                        let entity_id = entity_id.clone();
+                        let instance = instance.clone();
+                        async move {
+                            let hass = get_connection(&ctx, instance.as_deref()).await?;
+                            let state = hass
+                                .get_state(&entity_id)
+                                .await
+                                .ok_or("Failed to get state")?;
+
+                            Ok(state.state == active_state)
+                        }
+                    },
+                    move |ctx, _value| {
+                        let entity_id = entity_id_2.clone();
+                        let instance = instance_2.clone();
+                        let code = code.clone();
+                        async move {
+                            let hass = get_connection(&ctx, instance.as_deref()).await?;
+                            hass.call_service(
+                                "alarm_control_panel",
+                                service,
+                                Some(serde_json::json!({
+                                    "entity_id": entity_id,
+                                    "code": code.expose()
+                                })),
+                            )
+                            .await
+                            .map_err(|e| e.to_string())?;
+                            Ok(())
+                        }
+                    },
+                ),
+            )?;
+        }
+
+        // Add the current state display
+        let entity_id = self.entity_id.clone();
+        let instance = self.instance.clone();
+        let (x, y) = position(MODES.len());
+        view.set_button(
+            x,
+            y,
+            LabelButton::new(
+                "State",
+                Some(md_icons::filled::ICON_SECURITY),
+                move |ctx: PluginContext| {
+                    let entity_id = entity_id.clone();
+                    let instance = instance.clone();
+                    async move {
+                        let hass = get_connection(&ctx, instance.as_deref()).await?;
+                        let state = hass
+                            .get_state(&entity_id)
+                            .await
+                            .ok_or("Failed to get state")?;
+
+                        Ok(state.state)
+                    }
+                },
+            ),
+        )?;
+
+        // Add back button
+        if let Some(back_navigation) = &self.back_navigation {
+            view.set_navigation(
+                W::to_usize() - 1,
+                H::to_usize() - 1,
+                back_navigation.clone(),
+                "Back",
+                Some(md_icons::filled::ICON_ARROW_BACK),
+            )?;
+        }
+
+        Ok(Box::new(view))
+    }
+}
@@ -0,0 +1,129 @@
+//! Area plugin for HomeAssistant.
+//!
+//! This plugin lists the entities assigned to a HomeAssistant area when it
+//! is opened, so devices moved between rooms show up without changing the
+//! configuration.
+
+use streamdeck_oxide::{
+    generic_array::ArrayLength,
+    plugins::{Plugin, PluginContext, PluginNavigation},
+    View,
+};
+
+use crate::{
+    config::{HomeAssistantButton, HomeAssistantMenu, AREA_DOMAINS},
+    plugins::{generate_menu, get_online_connection, HomeAssistantPlugin},
+};
+
+/// Plugin showing a toggle for every light, switch, fan and input boolean of an area.
+///
+/// The toggles are drawn as a submenu of the menu the area button is on,
+/// so paging, cooldowns and the "Back" button work as in any other menu.
+#[derive(Clone)]
+pub struct AreaPlugin<W: ArrayLength, H: ArrayLength> {
+    /// The menu the area button is on
+    pub(crate) parent: HomeAssistantPlugin<W, H>,
+    /// The ID of the area
+    pub(crate) area_id: String,
+    /// The name of the generated menu
+    pub(crate) name: String,
+    /// Optional name of the HomeAssistant instance the area belongs to
+    pub(crate) instance: Option<String>,
+}
+
+impl<W: ArrayLength, H: ArrayLength> AreaPlugin<W, H> {
+    /// Creates the menu of toggles for the given entities of the area.
+    ///
+    /// # Arguments
+    ///
+    /// * `entity_ids` - The entities of the area; other domains than
+    ///   [`AREA_DOMAINS`] are left out
+    fn menu(&self, entity_ids: Vec<String>) -> HomeAssistantMenu {
+        let buttons = entity_ids
+            .into_iter()
+            .filter(|entity_id| {
+                entity_id
+                    .split_once('.')
+                    .is_some_and(|(domain, _)| AREA_DOMAINS.contains(&domain))
+            })
+            .map(|entity_id| HomeAssistantButton::Toggle {
+                entity_id,
+                name: None,
+                active_color: None,
+                transition: None,
+                cooldown_secs: None,
+                instance: self.instance.clone(),
+                position: None,
+            })
+            .collect();
+        HomeAssistantMenu {
+            name: self.name.clone(),
+            buttons,
+            theme: None,
+            position: None,
+        }
+    }
+}
+
+/// Implementation of the StreamDeck Plugin trait for AreaPlugin.
+#[async_trait::async_trait]
+impl<W, H> Plugin<W, H> for AreaPlugin<W, H>
+where
+    W: ArrayLength,
+    H: ArrayLength,
+{
+    fn name(&self) -> &'static str {
+        "AreaPlugin"
+    }
+
+    async fn get_view(
+        &self,
+        context: PluginContext,
+    ) -> Result<
+        Box<dyn View<W, H, PluginContext, PluginNavigation<W, H>>>,
+        Box<dyn std::error::Error>,
+    > {
+        let hass = get_online_connection(&context, self.instance.as_deref()).await?;
+        let entity_ids = hass.area_entities(&self.area_id).await?;
+        let menu = self.parent.submenu(self.menu(entity_ids));
+        Ok(Box::new(generate_menu(&menu, &context).await?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use streamdeck_oxide::generic_array::typenum::{U3, U5};
+
+    use super::*;
+
+    #[test]
+    fn menu_toggles_area_entities_of_supported_domains() {
+        let plugin = AreaPlugin::<U5, U3> {
+            parent: HomeAssistantPlugin {
+                menu: HomeAssistantMenu {
+                    name: "Home".to_string(),
+                    buttons: Vec::new(),
+                    theme: None,
+                    position: None,
+                },
+                back_navigation: None,
+                page: 0,
+                theme: None,
+                path: Vec::new(),
+                refresh: None,
+                root: None,
+            },
+            area_id: "bedroom".to_string(),
+            name: "Bedroom".to_string(),
+            instance: None,
+        };
+        let menu = plugin.menu(
+            ["fan.ceiling", "light.bed", "sensor.temperature", "switch.heater"]
+                .map(String::from)
+                .to_vec(),
+        );
+        assert_eq!(menu.name, "Bedroom");
+        let entity_ids: Vec<_> = menu.buttons.iter().filter_map(|b| b.entity_id()).collect();
+        assert_eq!(entity_ids, ["fan.ceiling", "light.bed", "switch.heater"]);
+    }
+}
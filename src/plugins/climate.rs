@@ -0,0 +1,388 @@
+//! Climate controller plugin for HomeAssistant.
+//!
+//! This plugin provides an on/off toggle and target temperature buttons for thermostats in HomeAssistant.
+
+use std::sync::{Arc, Mutex};
+
+use hass_rs::HassEntity;
+use streamdeck_oxide::{
+    generic_array::ArrayLength, md_icons, plugins::{Plugin, PluginContext, PluginNavigation}, view::customizable::{CustomButton, CustomizableView}, Button, ButtonState, View
+};
+
+use crate::{
+    buttons::{FeedbackButton, IgnoredPress, LabelToggleButton},
+    hass::EntityState,
+    plugins::{
+        get_connection, get_online_connection,
+        number::{next_value, step_decimals},
+    },
+};
+
+/// Plugin for controlling thermostats in HomeAssistant.
+///
+/// This plugin displays minus and plus buttons around the target temperature
+/// and an on/off toggle. Steps, bounds and display follow the thermostat's
+/// temperature unit.
+#[derive(Clone)]
+pub struct ClimatePlugin<W: ArrayLength, H: ArrayLength> {
+    /// Optional navigation to return to when "Back" is pressed
+    pub(crate) back_navigation: Option<PluginNavigation<W, H>>,
+    /// The entity ID of the thermostat to control
+    pub(crate) entity_id: String,
+    /// Optional name of the HomeAssistant instance the entity belongs to
+    pub(crate) instance: Option<String>,
+    /// Degrees to add or subtract per press, in the thermostat's unit
+    pub(crate) step: Option<f64>,
+}
+
+/// A temperature unit used by HomeAssistant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemperatureUnit {
+    /// Degrees Celsius
+    Celsius,
+    /// Degrees Fahrenheit
+    Fahrenheit,
+}
+
+impl TemperatureUnit {
+    /// Parses a unit symbol such as "°C" or "°F".
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use streamdeck_homeassistant::plugins::climate::TemperatureUnit;
+    /// assert_eq!(TemperatureUnit::from_symbol("°F"), Some(TemperatureUnit::Fahrenheit));
+    /// assert_eq!(TemperatureUnit::from_symbol("C"), Some(TemperatureUnit::Celsius));
+    /// assert_eq!(TemperatureUnit::from_symbol("K"), None);
+    /// ```
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        match symbol.trim().trim_start_matches('°') {
+            "C" | "c" => Some(TemperatureUnit::Celsius),
+            "F" | "f" => Some(TemperatureUnit::Fahrenheit),
+            _ => None,
+        }
+    }
+
+    /// Returns the symbol used for display.
+    pub fn symbol(self) -> &'static str {
+        match self {
+            TemperatureUnit::Celsius => "°C",
+            TemperatureUnit::Fahrenheit => "°F",
+        }
+    }
+
+    /// Returns the step used when the thermostat doesn't report `target_temp_step`.
+    pub fn default_step(self) -> f64 {
+        match self {
+            TemperatureUnit::Celsius => 0.5,
+            TemperatureUnit::Fahrenheit => 1.0,
+        }
+    }
+
+    /// Returns the bounds used when the thermostat doesn't report `min_temp`/`max_temp`.
+    ///
+    /// These are HomeAssistant's defaults of 7-35 °C, with the Fahrenheit
+    /// bounds rounded to whole degrees.
+    pub fn default_bounds(self) -> (f64, f64) {
+        match self {
+            TemperatureUnit::Celsius => (7.0, 35.0),
+            TemperatureUnit::Fahrenheit => (45.0, 95.0),
+        }
+    }
+}
+
+/// How the target temperature of a thermostat can be changed, all in its unit.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Setpoint {
+    /// The domain whose `set_temperature` service is called
+    domain: &'static str,
+    unit: TemperatureUnit,
+    step: f64,
+    min: f64,
+    max: f64,
+}
+
+impl Setpoint {
+    /// Resolves the setpoint limits from the thermostat's attributes.
+    ///
+    /// The thermostat reports `min_temp`, `max_temp` and `target_temp_step` in
+    /// its own unit, so only missing values fall back to unit defaults.
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - The domain of the entity, e.g. "climate"
+    /// * `entity` - The cached thermostat state
+    /// * `unit` - The thermostat's temperature unit
+    /// * `step` - Optional step from the configuration
+    /// * `default_bounds` - The bounds used when the entity doesn't report them
+    pub(super) fn resolve(
+        domain: &'static str,
+        entity: &HassEntity,
+        unit: TemperatureUnit,
+        step: Option<f64>,
+        default_bounds: (f64, f64),
+    ) -> Self {
+        let (default_min, default_max) = default_bounds;
+        Setpoint {
+            domain,
+            unit,
+            step: step
+                .or_else(|| entity.attr("target_temp_step"))
+                .unwrap_or_else(|| unit.default_step()),
+            min: entity.attr("min_temp").unwrap_or(default_min),
+            max: entity.attr("max_temp").unwrap_or(default_max),
+        }
+    }
+
+    /// Formats a temperature with as many decimals as the step needs.
+    pub(super) fn format(&self, temperature: f64) -> String {
+        format!("{:.*}{}", step_decimals(self.step), temperature, self.unit.symbol())
+    }
+
+    /// Computes the next target temperature, rounded to the step's precision.
+    fn next(&self, current: f64, delta: f64) -> f64 {
+        let value = next_value(current, delta, Some(self.min), Some(self.max));
+        let factor = 10f64.powi(step_decimals(self.step) as i32);
+        (value * factor).round() / factor
+    }
+}
+
+/// The last known target temperature, shared between the temperature buttons.
+pub(super) type SharedTarget = Arc<Mutex<Option<f64>>>;
+
+/// A button displaying the target temperature of a thermostat.
+pub(super) struct TargetButton {
+    /// The entity ID of the thermostat
+    pub(super) entity_id: String,
+    /// Optional name of the HomeAssistant instance
+    pub(super) instance: Option<String>,
+    /// The target temperature shared with the minus and plus buttons
+    pub(super) target: SharedTarget,
+    /// The thermostat's unit and limits
+    pub(super) setpoint: Setpoint,
+}
+
+#[async_trait::async_trait]
+impl CustomButton<PluginContext> for TargetButton {
+    fn get_state(&self) -> Button {
+        let target = self.target.lock().ok().and_then(|target| *target);
+        let text = match target {
+            Some(target) => self.setpoint.format(target),
+            None => "-".to_string(),
+        };
+        Button::new(text, Some(md_icons::filled::ICON_THERMOSTAT), ButtonState::Default)
+    }
+
+    async fn fetch(&self, context: &PluginContext) -> Result<(), Box<dyn std::error::Error>> {
+        let hass = get_connection(context, self.instance.as_deref()).await?;
+        let state = hass
+            .get_state(&self.entity_id)
+            .await
+            .ok_or("Failed to get state")?;
+        let target = state.attr("temperature");
+        if let Ok(mut current) = self.target.lock() {
+            *current = target;
+        }
+        Ok(())
+    }
+
+    async fn click(&self, context: &PluginContext) -> Result<(), Box<dyn std::error::Error>> {
+        self.fetch(context).await
+    }
+}
+
+/// Creates a button that changes the target temperature by one step.
+///
+/// # Arguments
+///
+/// * `entity_id` - The entity ID of the thermostat
+/// * `instance` - Optional name of the HomeAssistant instance
+/// * `target` - The target temperature shared with the other buttons
+/// * `setpoint` - The thermostat's unit and limits
+/// * `direction` - -1.0 to lower the target, 1.0 to raise it
+/// * `text` - The button label
+/// * `icon` - The button icon
+pub(super) fn step_button(
+    entity_id: String,
+    instance: Option<String>,
+    target: SharedTarget,
+    setpoint: Setpoint,
+    direction: f64,
+    text: &str,
+    icon: &'static str,
+) -> FeedbackButton<PluginContext> {
+    FeedbackButton::new(text, Some(icon), move |ctx: PluginContext| {
+        let entity_id = entity_id.clone();
+        let instance = instance.clone();
+        let target = target.clone();
+        async move {
+            let hass = get_connection(&ctx, instance.as_deref()).await?;
+            let state = hass
+                .get_state(&entity_id)
+                .await
+                .ok_or("Failed to get state")?;
+
+            // Prefer the locally known target, since the cache lags behind fast presses
+            let known = target.lock().ok().and_then(|target| *target);
+            let current = known
+                .or_else(|| state.attr("temperature"))
+                .ok_or("Thermostat has no target temperature")?;
+            let new_target = setpoint.next(current, direction * setpoint.step);
+
+            hass.call_service(
+                setpoint.domain,
+                "set_temperature",
+                Some(serde_json::json!({
+                    "entity_id": entity_id,
+                    "temperature": new_target
+                })),
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+
+            if let Ok(mut current) = target.lock() {
+                *current = Some(new_target);
+            }
+            Ok(())
+        }
+    })
+    .errors_only()
+}
+
+/// Implementation of the StreamDeck Plugin trait for ClimatePlugin.
+#[async_trait::async_trait]
+impl<W, H> Plugin<W, H> for ClimatePlugin<W, H>
+where
+    W: ArrayLength,
+    H: ArrayLength,
+{
+    fn name(&self) -> &'static str {
+        "ClimatePlugin"
+    }
+
+    async fn get_view(
+        &self,
+        context: PluginContext,
+    ) -> Result<
+        Box<dyn View<W, H, PluginContext, PluginNavigation<W, H>>>,
+        Box<dyn std::error::Error>,
+    > {
+        let mut view = CustomizableView::new();
+
+        let hass = get_connection(&context, self.instance.as_deref()).await?;
+        let state = hass
+            .get_state(&self.entity_id)
+            .await
+            .ok_or("Failed to get state")?;
+
+        // Thermostats report temperatures in HomeAssistant's unit unless they say otherwise.
+        // Guessing wrong would apply Celsius steps to a Fahrenheit thermostat, so don't guess.
+        let symbol = match state.attr::<String>("temperature_unit") {
+            Some(symbol) => Some(symbol),
+            None => hass.temperature_unit().await,
+        };
+        let unit = symbol
+            .as_deref()
+            .and_then(TemperatureUnit::from_symbol)
+            .ok_or_else(|| format!("Unknown temperature unit for {}", self.entity_id))?;
+        let setpoint = Setpoint::resolve("climate", &state, unit, self.step, unit.default_bounds());
+
+        let target: SharedTarget = Arc::new(Mutex::new(None));
+
+        // Minus, target temperature and plus along the top row
+        view.set_button(
+            0,
+            0,
+            step_button(
+                self.entity_id.clone(),
+                self.instance.clone(),
+                target.clone(),
+                setpoint,
+                -1.0,
+                "Down",
+                md_icons::filled::ICON_REMOVE,
+            ),
+        )?;
+        view.set_button(
+            1,
+            0,
+            TargetButton {
+                entity_id: self.entity_id.clone(),
+                instance: self.instance.clone(),
+                target: target.clone(),
+                setpoint,
+            },
+        )?;
+        view.set_button(
+            2,
+            0,
+            step_button(
+                self.entity_id.clone(),
+                self.instance.clone(),
+                target,
+                setpoint,
+                1.0,
+                "Up",
+                md_icons::filled::ICON_ADD,
+            ),
+        )?;
+
+        // Add the on/off toggle button below
+        let entity_id = self.entity_id.clone();
+        let instance = self.instance.clone();
+        let entity_id_2 = entity_id.clone();
+        let instance_2 = instance.clone();
+        view.set_button(
+            0,
+            1,
+            LabelToggleButton::new(
+                "On/Off",
+                Some(md_icons::filled::ICON_POWER_SETTINGS_NEW),
+                move |ctx: PluginContext| {
+                    let entity_id = entity_id.clone();
+                    let instance = instance.clone();
+                    async move {
+                        let hass = get_online_connection(&ctx, instance.as_deref()).await?;
+                        let state = hass
+                            .get_state(&entity_id)
+                            .await
+                            .ok_or("Failed to get state")?;
+
+                        Ok((state.state != "off", "On/Off".to_string()))
+                    }
+                },
+                move |ctx, value| {
+                    let entity_id = entity_id_2.clone();
+                    let instance = instance_2.clone();
+                    async move {
+                        let hass = get_online_connection(&ctx, instance.as_deref()).await?;
+                        if !hass.accept_press(&entity_id) {
+                            return Err(IgnoredPress.into());
+                        }
+                        hass.call_service(
+                            "climate",
+                            if value { "turn_on" } else { "turn_off" },
+                            Some(serde_json::json!({ "entity_id": entity_id })),
+                        )
+                        .await
+                        .map_err(|e| e.to_string())?;
+                        Ok(())
+                    }
+                },
+            ),
+        )?;
+
+        // Add back button
+        if let Some(back_navigation) = &self.back_navigation {
+            view.set_navigation(
+                W::to_usize() - 1,
+                H::to_usize() - 1,
+                back_navigation.clone(),
+                "Back",
+                Some(md_icons::filled::ICON_ARROW_BACK),
+            )?;
+        }
+
+        Ok(Box::new(view))
+    }
+}
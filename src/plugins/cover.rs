@@ -0,0 +1,275 @@
+//! Cover plugins for HomeAssistant.
+//!
+//! These plugins provide preset position buttons and open/stop/close controls for covers such as blinds and shades in HomeAssistant.
+
+use streamdeck_oxide::{
+    generic_array::ArrayLength, md_icons, plugins::{Plugin, PluginContext, PluginNavigation}, view::customizable::{CustomizableView, ToggleButton}, View
+};
+
+use crate::{
+    buttons::{FeedbackButton, LabelButton},
+    hass::EntityState,
+    plugins::get_connection,
+};
+
+/// Plugin for setting the position of covers in HomeAssistant.
+///
+/// This plugin displays a row of position presets. The preset closest to
+/// the cover's current `current_position` is highlighted.
+#[derive(Clone)]
+pub struct CoverPositionPlugin<W: ArrayLength, H: ArrayLength> {
+    /// Optional navigation to return to when "Back" is pressed
+    pub(crate) back_navigation: Option<PluginNavigation<W, H>>,
+    /// The entity ID of the cover to control
+    pub(crate) entity_id: String,
+    /// Optional name of the HomeAssistant instance the entity belongs to
+    pub(crate) instance: Option<String>,
+}
+
+/// Predefined positions for the cover controller, as percentages open
+const POSITIONS: &[(&str, u8)] = &[
+    ("0%", 0),
+    ("25%", 25),
+    ("50%", 50),
+    ("75%", 75),
+    ("100%", 100),
+];
+
+/// Finds the index of the position preset closest to the given position.
+///
+/// # Arguments
+///
+/// * `position` - The current cover position in percent (0-100)
+///
+/// # Returns
+///
+/// The index into `POSITIONS`
+fn closest_position(position: f64) -> usize {
+    POSITIONS
+        .iter()
+        .enumerate()
+        .min_by(|(_, (_, a)), (_, (_, b))| {
+            let a = (*a as f64 - position).abs();
+            let b = (*b as f64 - position).abs();
+            a.total_cmp(&b)
+        })
+        .map(|(index, _)| index)
+        .unwrap_or(0)
+}
+
+/// Implementation of the StreamDeck Plugin trait for CoverPositionPlugin.
+#[async_trait::async_trait]
+impl<W, H> Plugin<W, H> for CoverPositionPlugin<W, H>
+where
+    W: ArrayLength,
+    H: ArrayLength,
+{
+    fn name(&self) -> &'static str {
+        "CoverPositionPlugin"
+    }
+
+    async fn get_view(
+        &self,
+        _context: PluginContext,
+    ) -> Result<
+        Box<dyn View<W, H, PluginContext, PluginNavigation<W, H>>>,
+        Box<dyn std::error::Error>,
+    > {
+        let mut view = CustomizableView::new();
+
+        // Add position buttons
+        let max_buttons = W::to_usize() * H::to_usize() - 1; // Reserve space for the back button
+        let positions_to_show = std::cmp::min(POSITIONS.len(), max_buttons);
+
+        for (index, &(position_name, position)) in POSITIONS.iter().take(positions_to_show).enumerate() {
+            let x = index % W::to_usize();
+            let y = index / W::to_usize();
+
+            let entity_id = self.entity_id.clone();
+            let instance = self.instance.clone();
+            let entity_id_2 = entity_id.clone();
+            let instance_2 = instance.clone();
+
+            view.set_button(
+                x,
+                y,
+                ToggleButton::new(
+                    position_name,
+                    Some(md_icons::filled::ICON_BLINDS),
+                    move |ctx: PluginContext| {
+                        let entity_id = entity_id.clone();
+                        let instance = instance.clone();
+                        async move {
+                            let hass = get_connection(&ctx, instance.as_deref()).await?;
+                            let state = hass
+                                .get_state(&entity_id)
+                                .await
+                                .ok_or("Failed to get state")?;
+                            let Some(current) = state.attr::<f64>("current_position") else {
+                                return Ok(false);
+                            };
+
+                            Ok(closest_position(current) == index)
+                        }
+                    },
+                    move |ctx, _value| {
+                        let entity_id = entity_id_2.clone();
+                        let instance = instance_2.clone();
+                        async move {
+                            let hass = get_connection(&ctx, instance.as_deref()).await?;
+                            hass.call_service(
+                                "cover",
+                                "set_cover_position",
+                                Some(serde_json::json!({
+                                    "entity_id": entity_id,
+                                    "position": position
+                                })),
+                            )
+                            .await
+                            .map_err(|e| e.to_string())?;
+
+                            Ok(())
+                        }
+                    },
+                ),
+            )?;
+        }
+
+        // Add back button
+        if let Some(back_navigation) = &self.back_navigation {
+            view.set_navigation(
+                W::to_usize() - 1,
+                H::to_usize() - 1,
+                back_navigation.clone(),
+                "Back",
+                Some(md_icons::filled::ICON_ARROW_BACK),
+            )?;
+        }
+
+        Ok(Box::new(view))
+    }
+}
+
+/// Plugin for opening, stopping and closing covers in HomeAssistant.
+///
+/// This plugin displays open, stop and close buttons along the top row, so a
+/// cover can be halted mid-travel, with the current position below.
+#[derive(Clone)]
+pub struct CoverControlPlugin<W: ArrayLength, H: ArrayLength> {
+    /// Optional navigation to return to when "Back" is pressed
+    pub(crate) back_navigation: Option<PluginNavigation<W, H>>,
+    /// The entity ID of the cover to control
+    pub(crate) entity_id: String,
+    /// Optional name of the HomeAssistant instance the entity belongs to
+    pub(crate) instance: Option<String>,
+}
+
+/// Creates a button calling a cover service without further data.
+///
+/// # Arguments
+///
+/// * `entity_id` - The entity ID of the cover
+/// * `instance` - Optional name of the HomeAssistant instance
+/// * `label` - The button label
+/// * `service` - The cover service to call, e.g. "stop_cover"
+/// * `icon` - The button icon
+fn service_button(
+    entity_id: String,
+    instance: Option<String>,
+    label: &str,
+    service: &'static str,
+    icon: &'static str,
+) -> FeedbackButton<PluginContext> {
+    FeedbackButton::new(label, Some(icon), move |ctx: PluginContext| {
+        let entity_id = entity_id.clone();
+        let instance = instance.clone();
+        async move {
+            let hass = get_connection(&ctx, instance.as_deref()).await?;
+            hass.call_service(
+                "cover",
+                service,
+                Some(serde_json::json!({ "entity_id": entity_id })),
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+            Ok(())
+        }
+    })
+    .errors_only()
+}
+
+/// Implementation of the StreamDeck Plugin trait for CoverControlPlugin.
+#[async_trait::async_trait]
+impl<W, H> Plugin<W, H> for CoverControlPlugin<W, H>
+where
+    W: ArrayLength,
+    H: ArrayLength,
+{
+    fn name(&self) -> &'static str {
+        "CoverControlPlugin"
+    }
+
+    async fn get_view(
+        &self,
+        _context: PluginContext,
+    ) -> Result<
+        Box<dyn View<W, H, PluginContext, PluginNavigation<W, H>>>,
+        Box<dyn std::error::Error>,
+    > {
+        let mut view = CustomizableView::new();
+
+        // Open, stop and close along the top row
+        let controls = [
+            ("Open", "open_cover", md_icons::filled::ICON_ARROW_UPWARD),
+            ("Stop", "stop_cover", md_icons::filled::ICON_STOP),
+            ("Close", "close_cover", md_icons::filled::ICON_ARROW_DOWNWARD),
+        ];
+        for (x, (label, service, icon)) in controls.into_iter().enumerate() {
+            view.set_button(
+                x,
+                0,
+                service_button(self.entity_id.clone(), self.instance.clone(), label, service, icon),
+            )?;
+        }
+
+        // Position below, or the state for covers that can't report one
+        let entity_id = self.entity_id.clone();
+        let instance = self.instance.clone();
+        view.set_button(
+            0,
+            1,
+            LabelButton::new(
+                "-",
+                Some(md_icons::filled::ICON_BLINDS),
+                move |ctx: PluginContext| {
+                    let entity_id = entity_id.clone();
+                    let instance = instance.clone();
+                    async move {
+                        let hass = get_connection(&ctx, instance.as_deref()).await?;
+                        let state = hass
+                            .get_state(&entity_id)
+                            .await
+                            .ok_or("Failed to get state")?;
+                        Ok(match state.attr::<f64>("current_position") {
+                            Some(position) => format!("{:.0}%", position),
+                            None => state.state,
+                        })
+                    }
+                },
+            ),
+        )?;
+
+        // Add back button
+        if let Some(back_navigation) = &self.back_navigation {
+            view.set_navigation(
+                W::to_usize() - 1,
+                H::to_usize() - 1,
+                back_navigation.clone(),
+                "Back",
+                Some(md_icons::filled::ICON_ARROW_BACK),
+            )?;
+        }
+
+        Ok(Box::new(view))
+    }
+}
@@ -0,0 +1,331 @@
+//! Fan controller plugin for HomeAssistant.
+//!
+//! This plugin provides an on/off toggle, preset speed buttons and oscillation and direction
+//! controls for fans in HomeAssistant.
+
+use streamdeck_oxide::{
+    generic_array::ArrayLength, md_icons, plugins::{Plugin, PluginContext, PluginNavigation}, view::customizable::{CustomizableView, ToggleButton}, View
+};
+
+use crate::{
+    buttons::{IgnoredPress, LabelButton, LabelToggleButton},
+    hass::EntityState,
+    plugins::{get_connection, get_online_connection},
+};
+
+/// Plugin for controlling fans in HomeAssistant.
+///
+/// This plugin displays an on/off toggle and a row of speed presets.
+/// The preset closest to the fan's current `percentage` is highlighted.
+/// Fans that report `oscillating` or `direction` also get buttons to
+/// toggle oscillation and reverse the direction.
+#[derive(Clone)]
+pub struct FanControllerPlugin<W: ArrayLength, H: ArrayLength> {
+    /// Optional navigation to return to when "Back" is pressed
+    pub(crate) back_navigation: Option<PluginNavigation<W, H>>,
+    /// The entity ID of the fan to control
+    pub(crate) entity_id: String,
+    /// Optional name of the HomeAssistant instance the entity belongs to
+    pub(crate) instance: Option<String>,
+}
+
+/// Predefined speed presets for the fan controller, as percentages
+const SPEEDS: &[(&str, u8)] = &[
+    ("Low", 33),
+    ("Medium", 66),
+    ("High", 100),
+];
+
+/// Finds the index of the speed preset closest to the given percentage.
+///
+/// # Arguments
+///
+/// * `percentage` - The current fan speed in percent (0-100)
+///
+/// # Returns
+///
+/// The index into `SPEEDS`, or None if the fan is not spinning
+fn closest_speed(percentage: f64) -> Option<usize> {
+    if percentage <= 0.0 {
+        return None;
+    }
+    SPEEDS
+        .iter()
+        .enumerate()
+        .min_by(|(_, (_, a)), (_, (_, b))| {
+            let a = (*a as f64 - percentage).abs();
+            let b = (*b as f64 - percentage).abs();
+            a.total_cmp(&b)
+        })
+        .map(|(index, _)| index)
+}
+
+/// Returns the direction opposite to a fan's current `direction`.
+///
+/// # Arguments
+///
+/// * `direction` - The current direction, "forward" or "reverse"
+fn reversed_direction(direction: &str) -> &'static str {
+    match direction {
+        "forward" => "reverse",
+        _ => "forward",
+    }
+}
+
+/// Implementation of the StreamDeck Plugin trait for FanControllerPlugin.
+#[async_trait::async_trait]
+impl<W, H> Plugin<W, H> for FanControllerPlugin<W, H>
+where
+    W: ArrayLength,
+    H: ArrayLength,
+{
+    fn name(&self) -> &'static str {
+        "FanControllerPlugin"
+    }
+
+    async fn get_view(
+        &self,
+        context: PluginContext,
+    ) -> Result<
+        Box<dyn View<W, H, PluginContext, PluginNavigation<W, H>>>,
+        Box<dyn std::error::Error>,
+    > {
+        let mut view = CustomizableView::new();
+
+        // Add the on/off toggle button at the top left
+        let entity_id = self.entity_id.clone();
+        let instance = self.instance.clone();
+        let entity_id_2 = entity_id.clone();
+        let instance_2 = instance.clone();
+        view.set_button(
+            0,
+            0,
+            LabelToggleButton::new(
+                "On/Off",
+                Some(md_icons::filled::ICON_MODE_FAN_OFF),
+                move |ctx: PluginContext| {
+                    let entity_id = entity_id.clone();
+                    let instance = instance.clone();
+                    async move {
+                        let hass = get_online_connection(&ctx, instance.as_deref()).await?;
+                        let state = hass
+                            .get_state(&entity_id)
+                            .await
+                            .ok_or("Failed to get state")?;
+
+                        Ok((state.is_on(), "On/Off".to_string()))
+                    }
+                },
+                move |ctx, value| {
+                    let entity_id = entity_id_2.clone();
+                    let instance = instance_2.clone();
+                    async move {
+                        let hass = get_online_connection(&ctx, instance.as_deref()).await?;
+                        if !hass.accept_press(&entity_id) {
+                            return Err(IgnoredPress.into());
+                        }
+                        hass.call_service(
+                            "fan",
+                            if value { "turn_on" } else { "turn_off" },
+                            Some(serde_json::json!({ "entity_id": entity_id })),
+                        )
+                        .await
+                        .map_err(|e| e.to_string())?;
+                        Ok(())
+                    }
+                },
+            )
+            .when_active(Some(md_icons::filled::ICON_AIR)),
+        )?;
+
+        // Add speed buttons
+        let max_buttons = W::to_usize() * H::to_usize() - 2; // Reserve space for on/off and back buttons
+        let speeds_to_show = std::cmp::min(SPEEDS.len(), max_buttons);
+
+        for (index, &(speed_name, percentage)) in SPEEDS.iter().take(speeds_to_show).enumerate() {
+            let button_index = index + 1; // Skip the first button (on/off)
+            let x = button_index % W::to_usize();
+            let y = button_index / W::to_usize();
+
+            let entity_id = self.entity_id.clone();
+            let instance = self.instance.clone();
+            let entity_id_2 = entity_id.clone();
+            let instance_2 = instance.clone();
+
+            view.set_button(
+                x,
+                y,
+                ToggleButton::new(
+                    speed_name,
+                    Some(md_icons::filled::ICON_SPEED),
+                    move |ctx: PluginContext| {
+                        let entity_id = entity_id.clone();
+                        let instance = instance.clone();
+                        async move {
+                            let hass = get_connection(&ctx, instance.as_deref()).await?;
+                            let state = hass
+                                .get_state(&entity_id)
+                                .await
+                                .ok_or("Failed to get state")?;
+                            if !state.is_on() {
+                                return Ok(false);
+                            }
+                            let current = state.attr::<f64>("percentage").unwrap_or(0.0);
+
+                            Ok(closest_speed(current) == Some(index))
+                        }
+                    },
+                    move |ctx, _value| {
+                        let entity_id = entity_id_2.clone();
+                        let instance = instance_2.clone();
+                        async move {
+                            let hass = get_connection(&ctx, instance.as_deref()).await?;
+
+                            // Setting a percentage also turns the fan on
+                            hass.call_service(
+                                "fan",
+                                "set_percentage",
+                                Some(serde_json::json!({
+                                    "entity_id": entity_id,
+                                    "percentage": percentage
+                                })),
+                            )
+                            .await
+                            .map_err(|e| e.to_string())?;
+
+                            Ok(())
+                        }
+                    },
+                ),
+            )?;
+        }
+
+        // Oscillation and direction follow the speeds, for fans that support them
+        let hass = get_connection(&context, self.instance.as_deref()).await?;
+        let state = hass.get_state(&self.entity_id).await;
+        let oscillates = state
+            .as_ref()
+            .is_some_and(|state| state.attr::<bool>("oscillating").is_some());
+        let reverses = state
+            .as_ref()
+            .is_some_and(|state| state.attr::<String>("direction").is_some());
+        let mut button_index = speeds_to_show + 1;
+
+        if oscillates && button_index <= max_buttons {
+            let entity_id = self.entity_id.clone();
+            let instance = self.instance.clone();
+            let entity_id_2 = entity_id.clone();
+            let instance_2 = instance.clone();
+            view.set_button(
+                button_index % W::to_usize(),
+                button_index / W::to_usize(),
+                ToggleButton::new(
+                    "Oscillate",
+                    Some(md_icons::filled::ICON_SWAP_HORIZ),
+                    move |ctx: PluginContext| {
+                        let entity_id = entity_id.clone();
+                        let instance = instance.clone();
+                        async move {
+                            let hass = get_connection(&ctx, instance.as_deref()).await?;
+                            let state = hass
+                                .get_state(&entity_id)
+                                .await
+                                .ok_or("Failed to get state")?;
+
+                            Ok(state.attr::<bool>("oscillating").unwrap_or(false))
+                        }
+                    },
+                    move |ctx, value| {
+                        let entity_id = entity_id_2.clone();
+                        let instance = instance_2.clone();
+                        async move {
+                            let hass = get_connection(&ctx, instance.as_deref()).await?;
+                            hass.call_service(
+                                "fan",
+                                "oscillate",
+                                Some(serde_json::json!({
+                                    "entity_id": entity_id,
+                                    "oscillating": value
+                                })),
+                            )
+                            .await
+                            .map_err(|e| e.to_string())?;
+                            Ok(())
+                        }
+                    },
+                ),
+            )?;
+            button_index += 1;
+        }
+
+        if reverses && button_index <= max_buttons {
+            let entity_id = self.entity_id.clone();
+            let instance = self.instance.clone();
+            let entity_id_2 = entity_id.clone();
+            let instance_2 = instance.clone();
+            view.set_button(
+                button_index % W::to_usize(),
+                button_index / W::to_usize(),
+                LabelButton::new(
+                    "Direction",
+                    Some(md_icons::filled::ICON_SYNC),
+                    move |ctx: PluginContext| {
+                        let entity_id = entity_id.clone();
+                        let instance = instance.clone();
+                        async move {
+                            let hass = get_connection(&ctx, instance.as_deref()).await?;
+                            let state = hass
+                                .get_state(&entity_id)
+                                .await
+                                .ok_or("Failed to get state")?;
+                            Ok(match state.attr::<String>("direction").as_deref() {
+                                Some("reverse") => "Reverse".to_string(),
+                                Some(_) => "Forward".to_string(),
+                                None => "Direction".to_string(),
+                            })
+                        }
+                    },
+                )
+                .on_click(move |ctx: PluginContext| {
+                    let entity_id = entity_id_2.clone();
+                    let instance = instance_2.clone();
+                    async move {
+                        let hass = get_connection(&ctx, instance.as_deref()).await?;
+                        let state = hass
+                            .get_state(&entity_id)
+                            .await
+                            .ok_or("Failed to get state")?;
+                        let direction = state.attr::<String>("direction").unwrap_or_default();
+                        hass.call_service(
+                            "fan",
+                            "set_direction",
+                            Some(serde_json::json!({
+                                "entity_id": entity_id,
+                                "direction": reversed_direction(&direction)
+                            })),
+                        )
+                        .await
+                        .map_err(|e| e.to_string())?;
+
+                        // Refresh the cache so the label shows the new direction right away
+                        hass.fetch_states().await?;
+                        Ok(())
+                    }
+                }),
+            )?;
+        }
+
+        // Add back button
+        if let Some(back_navigation) = &self.back_navigation {
+            view.set_navigation(
+                W::to_usize() - 1,
+                H::to_usize() - 1,
+                back_navigation.clone(),
+                "Back",
+                Some(md_icons::filled::ICON_ARROW_BACK),
+            )?;
+        }
+
+        Ok(Box::new(view))
+    }
+}
@@ -0,0 +1,253 @@
+//! Humidifier controller plugin for HomeAssistant.
+//!
+//! This plugin provides an on/off toggle and target humidity buttons for humidifiers in HomeAssistant.
+
+use std::sync::{Arc, Mutex};
+
+use streamdeck_oxide::{
+    generic_array::ArrayLength, md_icons, plugins::{Plugin, PluginContext, PluginNavigation}, view::customizable::{CustomButton, CustomizableView}, Button, ButtonState, View
+};
+
+use crate::{
+    buttons::{FeedbackButton, IgnoredPress, LabelToggleButton},
+    hass::EntityState,
+    plugins::{get_connection, get_online_connection, number::next_value},
+};
+
+/// Plugin for controlling humidifiers in HomeAssistant.
+///
+/// This plugin displays minus and plus buttons around the target humidity
+/// and an on/off toggle. The target is clamped to the humidifier's
+/// `min_humidity`/`max_humidity` attributes.
+#[derive(Clone)]
+pub struct HumidifierPlugin<W: ArrayLength, H: ArrayLength> {
+    /// Optional navigation to return to when "Back" is pressed
+    pub(crate) back_navigation: Option<PluginNavigation<W, H>>,
+    /// The entity ID of the humidifier to control
+    pub(crate) entity_id: String,
+    /// Optional name of the HomeAssistant instance the entity belongs to
+    pub(crate) instance: Option<String>,
+}
+
+/// Percentage points to add or subtract per press
+const HUMIDITY_STEP: f64 = 5.0;
+
+/// The last known target humidity, shared between the humidity buttons.
+type SharedHumidity = Arc<Mutex<Option<f64>>>;
+
+/// A button displaying the target humidity of a humidifier.
+struct HumidityButton {
+    /// The entity ID of the humidifier
+    entity_id: String,
+    /// Optional name of the HomeAssistant instance
+    instance: Option<String>,
+    /// The target humidity shared with the minus and plus buttons
+    humidity: SharedHumidity,
+}
+
+#[async_trait::async_trait]
+impl CustomButton<PluginContext> for HumidityButton {
+    fn get_state(&self) -> Button {
+        let humidity = self.humidity.lock().ok().and_then(|humidity| *humidity);
+        let text = match humidity {
+            Some(humidity) => format!("{:.0}%", humidity),
+            None => "-".to_string(),
+        };
+        Button::new(text, Some(md_icons::filled::ICON_WATER_DROP), ButtonState::Default)
+    }
+
+    async fn fetch(&self, context: &PluginContext) -> Result<(), Box<dyn std::error::Error>> {
+        let hass = get_connection(context, self.instance.as_deref()).await?;
+        let state = hass
+            .get_state(&self.entity_id)
+            .await
+            .ok_or("Failed to get state")?;
+        let humidity = state.attr("humidity");
+        if let Ok(mut current) = self.humidity.lock() {
+            *current = humidity;
+        }
+        Ok(())
+    }
+
+    async fn click(&self, context: &PluginContext) -> Result<(), Box<dyn std::error::Error>> {
+        self.fetch(context).await
+    }
+}
+
+/// Creates a button that changes the target humidity by the given delta.
+///
+/// # Arguments
+///
+/// * `entity_id` - The entity ID of the humidifier
+/// * `instance` - Optional name of the HomeAssistant instance
+/// * `humidity` - The target humidity shared with the other buttons
+/// * `delta` - The signed step to apply
+/// * `text` - The button label
+/// * `icon` - The button icon
+fn step_button(
+    entity_id: String,
+    instance: Option<String>,
+    humidity: SharedHumidity,
+    delta: f64,
+    text: &str,
+    icon: &'static str,
+) -> FeedbackButton<PluginContext> {
+    FeedbackButton::new(text, Some(icon), move |ctx: PluginContext| {
+        let entity_id = entity_id.clone();
+        let instance = instance.clone();
+        let humidity = humidity.clone();
+        async move {
+            let hass = get_connection(&ctx, instance.as_deref()).await?;
+            let state = hass
+                .get_state(&entity_id)
+                .await
+                .ok_or("Failed to get state")?;
+
+            // Prefer the locally known target, since the cache lags behind fast presses
+            let known = humidity.lock().ok().and_then(|humidity| *humidity);
+            let current = known
+                .or_else(|| state.attr("humidity"))
+                .ok_or("Humidifier has no target humidity")?;
+            let new_humidity = next_value(
+                current,
+                delta,
+                state.attr("min_humidity"),
+                state.attr("max_humidity"),
+            );
+
+            hass.call_service(
+                "humidifier",
+                "set_humidity",
+                Some(serde_json::json!({
+                    "entity_id": entity_id,
+                    "humidity": new_humidity
+                })),
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+
+            if let Ok(mut current) = humidity.lock() {
+                *current = Some(new_humidity);
+            }
+            Ok(())
+        }
+    })
+    .errors_only()
+}
+
+/// Implementation of the StreamDeck Plugin trait for HumidifierPlugin.
+#[async_trait::async_trait]
+impl<W, H> Plugin<W, H> for HumidifierPlugin<W, H>
+where
+    W: ArrayLength,
+    H: ArrayLength,
+{
+    fn name(&self) -> &'static str {
+        "HumidifierPlugin"
+    }
+
+    async fn get_view(
+        &self,
+        _context: PluginContext,
+    ) -> Result<
+        Box<dyn View<W, H, PluginContext, PluginNavigation<W, H>>>,
+        Box<dyn std::error::Error>,
+    > {
+        let mut view = CustomizableView::new();
+
+        let humidity: SharedHumidity = Arc::new(Mutex::new(None));
+
+        // Minus, target humidity and plus along the top row
+        view.set_button(
+            0,
+            0,
+            step_button(
+                self.entity_id.clone(),
+                self.instance.clone(),
+                humidity.clone(),
+                -HUMIDITY_STEP,
+                "Down",
+                md_icons::filled::ICON_REMOVE,
+            ),
+        )?;
+        view.set_button(
+            1,
+            0,
+            HumidityButton {
+                entity_id: self.entity_id.clone(),
+                instance: self.instance.clone(),
+                humidity: humidity.clone(),
+            },
+        )?;
+        view.set_button(
+            2,
+            0,
+            step_button(
+                self.entity_id.clone(),
+                self.instance.clone(),
+                humidity,
+                HUMIDITY_STEP,
+                "Up",
+                md_icons::filled::ICON_ADD,
+            ),
+        )?;
+
+        // Add the on/off toggle button below
+        let entity_id = self.entity_id.clone();
+        let instance = self.instance.clone();
+        let entity_id_2 = entity_id.clone();
+        let instance_2 = instance.clone();
+        view.set_button(
+            0,
+            1,
+            LabelToggleButton::new(
+                "On/Off",
+                Some(md_icons::filled::ICON_POWER_SETTINGS_NEW),
+                move |ctx: PluginContext| {
+                    let entity_id = entity_id.clone();
+                    let instance = instance.clone();
+                    async move {
+                        let hass = get_online_connection(&ctx, instance.as_deref()).await?;
+                        let state = hass
+                            .get_state(&entity_id)
+                            .await
+                            .ok_or("Failed to get state")?;
+
+                        Ok((state.is_on(), "On/Off".to_string()))
+                    }
+                },
+                move |ctx, value| {
+                    let entity_id = entity_id_2.clone();
+                    let instance = instance_2.clone();
+                    async move {
+                        let hass = get_online_connection(&ctx, instance.as_deref()).await?;
+                        if !hass.accept_press(&entity_id) {
+                            return Err(IgnoredPress.into());
+                        }
+                        hass.call_service(
+                            "humidifier",
+                            if value { "turn_on" } else { "turn_off" },
+                            Some(serde_json::json!({ "entity_id": entity_id })),
+                        )
+                        .await
+                        .map_err(|e| e.to_string())?;
+                        Ok(())
+                    }
+                },
+            ),
+        )?;
+
+        // Add back button
+        if let Some(back_navigation) = &self.back_navigation {
+            view.set_navigation(
+                W::to_usize() - 1,
+                H::to_usize() - 1,
+                back_navigation.clone(),
+                "Back",
+                Some(md_icons::filled::ICON_ARROW_BACK),
+            )?;
+        }
+
+        Ok(Box::new(view))
+    }
+}
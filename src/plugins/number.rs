@@ -0,0 +1,242 @@
+//! Input number controller plugin for HomeAssistant.
+//!
+//! This plugin provides increment/decrement buttons for `input_number` helpers in HomeAssistant.
+
+use std::sync::{Arc, Mutex};
+
+use streamdeck_oxide::{
+    generic_array::ArrayLength, md_icons, plugins::{Plugin, PluginContext, PluginNavigation}, view::customizable::{CustomButton, CustomizableView}, Button, ButtonState, View
+};
+
+use crate::{buttons::FeedbackButton, hass::EntityState, plugins::get_connection};
+
+/// Plugin for controlling `input_number` helpers in HomeAssistant.
+///
+/// This plugin displays minus and plus buttons around the current value.
+/// The value is clamped to the helper's `min`/`max` attributes.
+#[derive(Clone)]
+pub struct InputNumberPlugin<W: ArrayLength, H: ArrayLength> {
+    /// Optional navigation to return to when "Back" is pressed
+    pub(crate) back_navigation: Option<PluginNavigation<W, H>>,
+    /// The entity ID of the input number to control
+    pub(crate) entity_id: String,
+    /// Optional name of the HomeAssistant instance the entity belongs to
+    pub(crate) instance: Option<String>,
+    /// Amount to add or subtract per press; defaults to the helper's `step` attribute
+    pub(crate) step: Option<f64>,
+}
+
+/// The last known value of an input number, shared between its buttons.
+type SharedValue = Arc<Mutex<Option<f64>>>;
+
+/// A button displaying the current value of an input number.
+struct ValueButton {
+    /// The entity ID of the input number
+    entity_id: String,
+    /// Optional name of the HomeAssistant instance
+    instance: Option<String>,
+    /// The value shared with the minus and plus buttons
+    value: SharedValue,
+    /// Number of decimals to display
+    decimals: usize,
+}
+
+/// Counts the decimals needed to display multiples of a step.
+///
+/// # Arguments
+///
+/// * `step` - The increment used by the input number
+pub(super) fn step_decimals(step: f64) -> usize {
+    let step = step.to_string();
+    step.split_once('.').map(|(_, decimals)| decimals.len()).unwrap_or(0)
+}
+
+/// Computes the next value of an input number.
+///
+/// # Arguments
+///
+/// * `current` - The current value
+/// * `delta` - The signed step to apply
+/// * `min` - Optional lower bound
+/// * `max` - Optional upper bound
+pub(super) fn next_value(current: f64, delta: f64, min: Option<f64>, max: Option<f64>) -> f64 {
+    let mut value = current + delta;
+    if let Some(min) = min {
+        value = value.max(min);
+    }
+    if let Some(max) = max {
+        value = value.min(max);
+    }
+    value
+}
+
+#[async_trait::async_trait]
+impl CustomButton<PluginContext> for ValueButton {
+    fn get_state(&self) -> Button {
+        let value = self.value.lock().ok().and_then(|value| *value);
+        let text = match value {
+            Some(value) => format!("{:.*}", self.decimals, value),
+            None => "-".to_string(),
+        };
+        Button::new(text, Some(md_icons::filled::ICON_TUNE), ButtonState::Default)
+    }
+
+    async fn fetch(&self, context: &PluginContext) -> Result<(), Box<dyn std::error::Error>> {
+        let hass = get_connection(context, self.instance.as_deref()).await?;
+        let state = hass
+            .get_state(&self.entity_id)
+            .await
+            .ok_or("Failed to get state")?;
+        let value = state.numeric_state();
+        if let Ok(mut current) = self.value.lock() {
+            *current = value;
+        }
+        Ok(())
+    }
+
+    async fn click(&self, context: &PluginContext) -> Result<(), Box<dyn std::error::Error>> {
+        self.fetch(context).await
+    }
+}
+
+/// Creates a button that changes an input number by the given delta.
+///
+/// # Arguments
+///
+/// * `entity_id` - The entity ID of the input number
+/// * `instance` - Optional name of the HomeAssistant instance
+/// * `value` - The value shared with the other buttons
+/// * `delta` - The signed step to apply
+/// * `text` - The button label
+/// * `icon` - The button icon
+fn step_button(
+    entity_id: String,
+    instance: Option<String>,
+    value: SharedValue,
+    delta: f64,
+    text: &str,
+    icon: &'static str,
+) -> FeedbackButton<PluginContext> {
+    FeedbackButton::new(text, Some(icon), move |ctx: PluginContext| {
+        let entity_id = entity_id.clone();
+        let instance = instance.clone();
+        let value = value.clone();
+        async move {
+            let hass = get_connection(&ctx, instance.as_deref()).await?;
+            let state = hass
+                .get_state(&entity_id)
+                .await
+                .ok_or("Failed to get state")?;
+
+            // Prefer the locally known value, since the cache lags behind fast presses
+            let known = value.lock().ok().and_then(|value| *value);
+            let current = match known {
+                Some(current) => current,
+                None => state.numeric_state().ok_or("Input number has no value")?,
+            };
+            let new_value = next_value(
+                current,
+                delta,
+                state.attr("min"),
+                state.attr("max"),
+            );
+
+            hass.call_service(
+                "input_number",
+                "set_value",
+                Some(serde_json::json!({
+                    "entity_id": entity_id,
+                    "value": new_value
+                })),
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+
+            if let Ok(mut current) = value.lock() {
+                *current = Some(new_value);
+            }
+            Ok(())
+        }
+    })
+    .errors_only()
+}
+
+/// Implementation of the StreamDeck Plugin trait for InputNumberPlugin.
+#[async_trait::async_trait]
+impl<W, H> Plugin<W, H> for InputNumberPlugin<W, H>
+where
+    W: ArrayLength,
+    H: ArrayLength,
+{
+    fn name(&self) -> &'static str {
+        "InputNumberPlugin"
+    }
+
+    async fn get_view(
+        &self,
+        context: PluginContext,
+    ) -> Result<
+        Box<dyn View<W, H, PluginContext, PluginNavigation<W, H>>>,
+        Box<dyn std::error::Error>,
+    > {
+        let mut view = CustomizableView::new();
+
+        let hass = get_connection(&context, self.instance.as_deref()).await?;
+        let state = hass.get_state(&self.entity_id).await;
+        let step = self
+            .step
+            .or_else(|| state.and_then(|state| state.attr("step")))
+            .unwrap_or(1.0);
+
+        let value: SharedValue = Arc::new(Mutex::new(None));
+
+        // Minus, current value and plus along the top row
+        view.set_button(
+            0,
+            0,
+            step_button(
+                self.entity_id.clone(),
+                self.instance.clone(),
+                value.clone(),
+                -step,
+                "Down",
+                md_icons::filled::ICON_REMOVE,
+            ),
+        )?;
+        view.set_button(
+            1,
+            0,
+            ValueButton {
+                entity_id: self.entity_id.clone(),
+                instance: self.instance.clone(),
+                value: value.clone(),
+                decimals: step_decimals(step),
+            },
+        )?;
+        view.set_button(
+            2,
+            0,
+            step_button(
+                self.entity_id.clone(),
+                self.instance.clone(),
+                value,
+                step,
+                "Up",
+                md_icons::filled::ICON_ADD,
+            ),
+        )?;
+
+        // Add back button
+        if let Some(back_navigation) = &self.back_navigation {
+            view.set_navigation(
+                W::to_usize() - 1,
+                H::to_usize() - 1,
+                back_navigation.clone(),
+                "Back",
+                Some(md_icons::filled::ICON_ARROW_BACK),
+            )?;
+        }
+
+        Ok(Box::new(view))
+    }
+}
@@ -2,23 +2,50 @@
 //!
 //! This plugin provides a color picker interface for RGB lights in HomeAssistant.
 
+use std::sync::{Arc, Mutex};
+
+use hass_rs::HassEntity;
 use resvg::tiny_skia::Color;
 use streamdeck_oxide::{
-    generic_array::ArrayLength, md_icons, plugins::{Plugin, PluginContext, PluginNavigation}, view::customizable::{ClickButton, CustomizableView, ToggleButton}, Theme, View
+    generic_array::ArrayLength, md_icons, plugins::{Plugin, PluginContext, PluginNavigation}, view::customizable::{CustomizableView, ToggleButton}, Theme, View
 };
 
-use crate::hass::PersistentHassConnection;
+use crate::{
+    buttons::{ensure_available, FeedbackButton, IgnoredPress, LabelToggleButton},
+    hass::EntityState,
+    plugins::{get_connection, get_online_connection},
+};
 
 /// Plugin for controlling RGB lights in HomeAssistant.
 ///
 /// This plugin displays a grid of color buttons and an on/off toggle
-/// for controlling RGB lights.
+/// for controlling RGB lights, with further views for color temperature,
+/// brightness and effects.
 #[derive(Clone)]
 pub struct RgbControllerPlugin<W: ArrayLength, H: ArrayLength> {
     /// Optional navigation to return to when "Back" is pressed
     pub(crate) back_navigation: Option<PluginNavigation<W, H>>,
     /// The entity ID of the RGB light to control
     pub(crate) entity_id: String,
+    /// Optional name of the HomeAssistant instance the entity belongs to
+    pub(crate) instance: Option<String>,
+    /// Which set of presets to display
+    pub(crate) mode: RgbMode,
+    /// Seconds over which the light fades to a new state, if any
+    pub(crate) transition: Option<f32>,
+}
+
+/// The set of presets displayed by the RGB controller.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RgbMode {
+    /// Hue/saturation color presets
+    Color,
+    /// White color temperature presets
+    Temperature,
+    /// Brightness steps and presets
+    Brightness,
+    /// Effects from the light's `effect_list`
+    Effects,
 }
 
 /// Predefined colors for the RGB controller
@@ -37,15 +64,125 @@ const COLORS: &[(&str, (u8, u8, u8))] = &[
     ("Teal", (0, 128, 128)),
 ];
 
+/// A function computing the colors shown by the RGB controller.
+type PaletteProvider = dyn Fn() -> Vec<(String, (u8, u8, u8))> + Send + Sync;
+
+/// Replaces the predefined colors of the RGB controller.
+///
+/// Register this in the `PluginContext` alongside the HomeAssistant connection.
+/// The provider is called each time a light's color menu opens, so it can
+/// compute colors from state that changes at runtime, such as a theme.
+///
+/// # Example
+///
+/// ```
+/// # use streamdeck_homeassistant::plugins::rgb::ColorPalette;
+/// let palette = ColorPalette::new(|| {
+///     vec![
+///         ("Sunset".to_string(), (255, 94, 77)),
+///         ("Forest".to_string(), (34, 139, 34)),
+///     ]
+/// });
+/// ```
+#[derive(Clone)]
+pub struct ColorPalette {
+    provider: Arc<PaletteProvider>,
+}
+
+impl ColorPalette {
+    /// Creates a palette from a function returning named RGB colors.
+    pub fn new<F>(provider: F) -> Self
+    where
+        F: Fn() -> Vec<(String, (u8, u8, u8))> + Send + Sync + 'static,
+    {
+        Self {
+            provider: Arc::new(provider),
+        }
+    }
+
+    /// Returns the current colors of the palette.
+    pub fn colors(&self) -> Vec<(String, (u8, u8, u8))> {
+        (self.provider)()
+    }
+}
+
+/// Returns the colors to show, from a registered `ColorPalette` or the predefined colors.
+///
+/// # Arguments
+///
+/// * `context` - The plugin context
+async fn palette_colors(context: &PluginContext) -> Vec<(String, (u8, u8, u8))> {
+    match context.get_context::<ColorPalette>().await {
+        Some(palette) => palette.colors(),
+        None => COLORS
+            .iter()
+            .map(|&(name, color)| (name.to_string(), color))
+            .collect(),
+    }
+}
+
+/// Predefined color temperatures for the RGB controller, in Kelvin,
+/// with an approximate color used for the button background
+const COLOR_TEMPS: &[(&str, u32, (u8, u8, u8))] = &[
+    ("2000K", 2000, (255, 137, 14)),
+    ("2700K", 2700, (255, 169, 87)),
+    ("4000K", 4000, (255, 209, 163)),
+    ("6500K", 6500, (255, 249, 253)),
+];
+
+/// Number of perceptually even brightness steps between off and full brightness
+const BRIGHTNESS_STEPS: u8 = 10;
+
+/// Exponent relating perceived brightness to the light's output
+const BRIGHTNESS_GAMMA: f32 = 2.2;
+
+/// Perceived brightness presets, as fractions of full brightness
+const BRIGHTNESS_PRESETS: &[(&str, f32)] = &[
+    ("25%", 0.25),
+    ("50%", 0.5),
+    ("75%", 0.75),
+    ("100%", 1.0),
+];
+
+/// Luminance above which a background is considered light and gets dark text
+pub(super) const LIGHT_BACKGROUND_LUMINANCE: f32 = 0.6;
+
+/// Computes the perceived brightness of a color.
+///
+/// # Arguments
+///
+/// * `color` - The color as (r, g, b) components (0-255)
+///
+/// # Returns
+///
+/// The luminance between 0.0 (black) and 1.0 (white)
+pub(super) fn luminance(color: (u8, u8, u8)) -> f32 {
+    let (r, g, b) = color;
+    (0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32) / 255.0
+}
+
+/// Creates a theme showing the given color as the button background.
+///
+/// The text color is chosen to stay readable on light colors such as
+/// white or yellow.
+///
+/// # Arguments
+///
+/// * `color` - The background color as (r, g, b) components (0-255)
 fn get_button_theme(color: (u8, u8, u8)) -> Theme {
     let (r, g, b) = color;
+    let foreground = if luminance(color) > LIGHT_BACKGROUND_LUMINANCE {
+        Color::from_rgba8(20, 20, 25, 255)
+    } else {
+        Color::from_rgba8(242, 242, 255, 255)
+    };
     Theme::new(
         Color::from_rgba8(r, g, b, 255),
         Color::from_rgba8(235, 51, 148, 255),
         Color::from_rgba8(41, 41, 51, 255),
         Color::from_rgba8(51, 217, 230, 255),
         Color::from_rgba8(255, 89, 0, 255),
-        Color::from_rgba8(242, 242, 255, 255),
+        foreground,
         Color::from_rgba8(255, 255, 255, 255),
     )
 }
@@ -74,20 +211,19 @@ fn rgb_to_hsv(r: u8, g: u8, b: u8) -> (f32, f32, f32) {
     let delta = max - min;
     
     // Hue calculation
-    let mut h = if delta == 0.0 {
+    let h = if delta == 0.0 {
         0.0
     } else if max == r_f {
-        60.0 * (((g_f - b_f) / delta) % 6.0)
+        60.0 * ((g_f - b_f) / delta)
     } else if max == g_f {
         60.0 * (((b_f - r_f) / delta) + 2.0)
     } else {
         60.0 * (((r_f - g_f) / delta) + 4.0)
     };
     
-    // Ensure hue is non-negative
-    if h < 0.0 {
-        h += 360.0;
-    }
+    // Wrap reds with some blue into 0-360; rounding can land exactly on 360
+    let h = h.rem_euclid(360.0);
+    let h = if h >= 360.0 { 0.0 } else { h };
     
     // Saturation calculation
     let s = if max == 0.0 { 0.0 } else { delta / max };
@@ -98,6 +234,345 @@ fn rgb_to_hsv(r: u8, g: u8, b: u8) -> (f32, f32, f32) {
     (h, s * 100.0, v * 100.0)
 }
 
+/// Converts a hue and saturation at full value to RGB color values.
+///
+/// # Arguments
+///
+/// * `h` - Hue in degrees (0-360)
+/// * `s` - Saturation in percent (0-100)
+fn hs_to_rgb(h: f32, s: f32) -> (u8, u8, u8) {
+    let s = (s / 100.0).clamp(0.0, 1.0);
+    let h = h.rem_euclid(360.0) / 60.0;
+    let x = s * (1.0 - (h % 2.0 - 1.0).abs());
+    let min = 1.0 - s;
+    let (r, g, b) = match h as u8 {
+        0 => (s, x, 0.0),
+        1 => (x, s, 0.0),
+        2 => (0.0, s, x),
+        3 => (0.0, x, s),
+        4 => (x, 0.0, s),
+        _ => (s, 0.0, x),
+    };
+    let channel = |c: f32| ((c + min) * 255.0).round() as u8;
+    (channel(r), channel(g), channel(b))
+}
+
+/// Reads the current color of a light from its cached state.
+///
+/// Prefers `rgb_color` and falls back to `hs_color`, so white-only lights
+/// and lights that are off have no color.
+///
+/// # Arguments
+///
+/// * `entity` - The cached state of the light
+pub(super) fn light_color(entity: &HassEntity) -> Option<(u8, u8, u8)> {
+    if let Some([r, g, b]) = entity.attr::<[u8; 3]>("rgb_color") {
+        return Some((r, g, b));
+    }
+    let [h, s] = entity.attr::<[f32; 2]>("hs_color")?;
+    Some(hs_to_rgb(h, s))
+}
+
+/// How a light is sent a color.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ColorMode {
+    /// Hue and saturation, which HomeAssistant converts for RGB lights
+    Hs,
+    /// RGB with a white channel
+    Rgbw,
+    /// RGB with cold and warm white channels
+    Rgbww,
+}
+
+/// Looks up how a light is sent a color, falling back to `hs_color` if unknown.
+///
+/// # Arguments
+///
+/// * `context` - The plugin context holding the HomeAssistant connection
+/// * `entity_id` - The entity ID of the light
+/// * `instance` - Optional name of the HomeAssistant instance
+async fn light_color_mode(
+    context: &PluginContext,
+    entity_id: &str,
+    instance: Option<&str>,
+) -> ColorMode {
+    let Ok(hass) = get_connection(context, instance).await else {
+        return ColorMode::Hs;
+    };
+    match hass.get_state(entity_id).await {
+        Some(state) => color_mode(&state.attributes),
+        None => ColorMode::Hs,
+    }
+}
+
+/// Picks the color mode from a light's `supported_color_modes` attribute.
+///
+/// Lights with white channels get their white values set directly, since
+/// `hs_color` leaves the white channels off.
+///
+/// # Arguments
+///
+/// * `attributes` - The light's attributes
+fn color_mode(attributes: &serde_json::Value) -> ColorMode {
+    let modes: Vec<&str> = attributes["supported_color_modes"]
+        .as_array()
+        .map(|modes| modes.iter().filter_map(|mode| mode.as_str()).collect())
+        .unwrap_or_default();
+    if modes.contains(&"rgbww") {
+        ColorMode::Rgbww
+    } else if modes.contains(&"rgbw") {
+        ColorMode::Rgbw
+    } else {
+        ColorMode::Hs
+    }
+}
+
+/// Splits an RGB color into RGB and white channels.
+///
+/// The white shared by all three components is moved to the white channel.
+///
+/// # Returns
+///
+/// The `[r, g, b, w]` values for `rgbw_color`
+fn rgb_to_rgbw(r: u8, g: u8, b: u8) -> [u8; 4] {
+    let w = r.min(g).min(b);
+    [r - w, g - w, b - w, w]
+}
+
+/// Splits an RGB color into RGB, cold white and warm white channels.
+///
+/// The white shared by all three components goes to the white channels,
+/// leaning towards warm white for reddish colors and cold white for
+/// bluish ones. Neutral white uses both at full strength.
+///
+/// # Returns
+///
+/// The `[r, g, b, cw, ww]` values for `rgbww_color`
+fn rgb_to_rgbww(r: u8, g: u8, b: u8) -> [u8; 5] {
+    let [r, g, b, w] = rgb_to_rgbw(r, g, b);
+    let (warm, cool) = (r as f32, b as f32);
+    let (warm_share, cool_share) = if warm + cool == 0.0 {
+        (0.5, 0.5)
+    } else {
+        (warm / (warm + cool), cool / (warm + cool))
+    };
+    let channel = |share: f32| (w as f32 * (2.0 * share).min(1.0)).round() as u8;
+    [r, g, b, channel(cool_share), channel(warm_share)]
+}
+
+/// Builds the `light.turn_on` data setting a light to a color.
+///
+/// # Arguments
+///
+/// * `mode` - How the light is sent a color
+/// * `entity_id` - The entity ID of the light
+/// * `color` - The RGB color
+fn color_data(mode: ColorMode, entity_id: &str, (r, g, b): (u8, u8, u8)) -> serde_json::Value {
+    let (h, s, v) = rgb_to_hsv(r, g, b);
+    match mode {
+        ColorMode::Hs => serde_json::json!({
+            "entity_id": entity_id,
+            "hs_color": [h, s],
+            "brightness_pct": v
+        }),
+        ColorMode::Rgbw => serde_json::json!({
+            "entity_id": entity_id,
+            "rgbw_color": rgb_to_rgbw(r, g, b),
+            "brightness_pct": v
+        }),
+        ColorMode::Rgbww => serde_json::json!({
+            "entity_id": entity_id,
+            "rgbww_color": rgb_to_rgbww(r, g, b),
+            "brightness_pct": v
+        }),
+    }
+}
+
+/// Adds a fade time to `light.turn_on`/`light.turn_off` data.
+///
+/// # Arguments
+///
+/// * `data` - The service data, an object
+/// * `transition` - Seconds over which to fade, or None to leave the data unchanged
+pub(super) fn with_transition(mut data: serde_json::Value, transition: Option<f32>) -> serde_json::Value {
+    if let (Some(object), Some(transition)) = (data.as_object_mut(), transition) {
+        object.insert("transition".to_string(), serde_json::json!(transition));
+    }
+    data
+}
+
+/// Converts a perceived brightness level to the light's brightness.
+///
+/// Our eyes are more sensitive to changes in dim light, so evenly spaced
+/// levels are gamma-corrected to spread out at the top and bunch up at the
+/// bottom of the light's range.
+///
+/// # Arguments
+///
+/// * `level` - The perceived brightness between 0.0 and 1.0
+///
+/// # Returns
+///
+/// The HomeAssistant brightness (1-255)
+fn perceived_to_brightness(level: f32) -> u8 {
+    let brightness = level.clamp(0.0, 1.0).powf(BRIGHTNESS_GAMMA) * 255.0;
+    brightness.round().clamp(1.0, 255.0) as u8
+}
+
+/// Converts a light's brightness to a perceived brightness level.
+///
+/// # Arguments
+///
+/// * `brightness` - The HomeAssistant brightness (0-255)
+///
+/// # Returns
+///
+/// The perceived brightness between 0.0 and 1.0
+fn brightness_to_perceived(brightness: u8) -> f32 {
+    (brightness as f32 / 255.0).powf(1.0 / BRIGHTNESS_GAMMA)
+}
+
+/// Computes the brightness after moving a number of perceptual steps.
+///
+/// The result never drops below the first step, so dimming doesn't turn
+/// the light off.
+///
+/// # Arguments
+///
+/// * `brightness` - The current HomeAssistant brightness (0-255)
+/// * `steps` - The signed number of steps to move
+///
+/// # Returns
+///
+/// The new HomeAssistant brightness (1-255)
+fn step_brightness(brightness: u8, steps: i8) -> u8 {
+    let steps_total = BRIGHTNESS_STEPS as f32;
+    let current = (brightness_to_perceived(brightness) * steps_total).round() as i16;
+    let next = (current + steps as i16).clamp(1, BRIGHTNESS_STEPS as i16);
+    perceived_to_brightness(next as f32 / steps_total)
+}
+
+/// The last brightness set from the brightness view, shared between its step buttons.
+type SharedBrightness = Arc<Mutex<Option<u8>>>;
+
+/// Creates a button that changes the brightness of a light by perceptual steps.
+///
+/// # Arguments
+///
+/// * `entity_id` - The entity ID of the light
+/// * `instance` - Optional name of the HomeAssistant instance
+/// * `brightness` - The brightness shared with the other step button
+/// * `steps` - The signed number of steps per press
+/// * `transition` - Seconds over which the light fades, if any
+/// * `text` - The button label
+/// * `icon` - The button icon
+fn brightness_step_button(
+    entity_id: String,
+    instance: Option<String>,
+    brightness: SharedBrightness,
+    steps: i8,
+    transition: Option<f32>,
+    text: &str,
+    icon: &'static str,
+) -> FeedbackButton<PluginContext> {
+    FeedbackButton::new(text, Some(icon), move |ctx: PluginContext| {
+        let entity_id = entity_id.clone();
+        let instance = instance.clone();
+        let brightness = brightness.clone();
+        async move {
+            let hass = get_connection(&ctx, instance.as_deref()).await?;
+            let state = hass
+                .get_state(&entity_id)
+                .await
+                .ok_or("Failed to get state")?;
+
+            // Prefer the locally known brightness, since the cache lags behind fast presses
+            let known = brightness.lock().ok().and_then(|brightness| *brightness);
+            let current = known
+                .or_else(|| state.brightness())
+                .unwrap_or(0);
+            let new_brightness = step_brightness(current, steps);
+
+            hass.call_service(
+                "light",
+                "turn_on",
+                Some(with_transition(
+                    serde_json::json!({
+                        "entity_id": entity_id,
+                        "brightness": new_brightness
+                    }),
+                    transition,
+                )),
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+
+            if let Ok(mut current) = brightness.lock() {
+                *current = Some(new_brightness);
+            }
+            Ok(())
+        }
+    })
+    .errors_only()
+}
+
+/// Returns the color temperature presets supported by a light.
+///
+/// Presets outside the light's `min_color_temp_kelvin`/`max_color_temp_kelvin`
+/// range are skipped, since HomeAssistant rejects the whole service call for
+/// an unsupported temperature. Lights that don't report a range get no presets.
+///
+/// # Arguments
+///
+/// * `context` - The plugin context holding the HomeAssistant connection
+/// * `entity_id` - The entity ID of the light
+/// * `instance` - Optional name of the HomeAssistant instance
+async fn supported_color_temps(
+    context: &PluginContext,
+    entity_id: &str,
+    instance: Option<&str>,
+) -> Vec<(&'static str, u32, (u8, u8, u8))> {
+    let Ok(hass) = get_connection(context, instance).await else {
+        return Vec::new();
+    };
+    let Some(state) = hass.get_state(entity_id).await else {
+        return Vec::new();
+    };
+    let min = state.attr::<u64>("min_color_temp_kelvin");
+    let max = state.attr::<u64>("max_color_temp_kelvin");
+    match (min, max) {
+        (Some(min), Some(max)) => COLOR_TEMPS
+            .iter()
+            .filter(|(_, kelvin, _)| (min..=max).contains(&(*kelvin as u64)))
+            .copied()
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Gets the effects a light supports from its `effect_list`.
+///
+/// Lights without effects, or whose state isn't cached, get none.
+///
+/// # Arguments
+///
+/// * `context` - The plugin context holding the HomeAssistant connection
+/// * `entity_id` - The entity ID of the light
+/// * `instance` - Optional name of the HomeAssistant instance
+async fn light_effects(
+    context: &PluginContext,
+    entity_id: &str,
+    instance: Option<&str>,
+) -> Vec<String> {
+    let Ok(hass) = get_connection(context, instance).await else {
+        return Vec::new();
+    };
+    match hass.get_state(entity_id).await {
+        Some(state) => state.attr("effect_list").unwrap_or_default(),
+        None => Vec::new(),
+    }
+}
+
 /// Implementation of the StreamDeck Plugin trait for RgbControllerPlugin.
 #[async_trait::async_trait]
 impl<W, H> Plugin<W, H> for RgbControllerPlugin<W, H>
@@ -111,7 +586,7 @@ where
 
     async fn get_view(
         &self,
-        _context: PluginContext,
+        context: PluginContext,
     ) -> Result<
         Box<dyn View<W, H, PluginContext, PluginNavigation<W, H>>>,
         Box<dyn std::error::Error>,
@@ -120,39 +595,45 @@ where
         
         // Add the on/off toggle button at the top left
         let entity_id = self.entity_id.clone();
+        let instance = self.instance.clone();
         let entity_id_2 = entity_id.clone();
+        let instance_2 = instance.clone();
+        let transition = self.transition;
         view.set_button(
             0,
             0,
-            ToggleButton::new(
+            LabelToggleButton::new(
                 "On/Off",
                 Some(md_icons::filled::ICON_TOGGLE_OFF),
                 move |ctx: PluginContext| {
                     let entity_id = entity_id.clone();
+                    let instance = instance.clone();
                     async move {
-                        let hass = ctx
-                            .get_context::<PersistentHassConnection>()
-                            .await
-                            .ok_or("Failed to get PersistentHassConnection")?;
+                        let hass = get_online_connection(&ctx, instance.as_deref()).await?;
                         let state = hass
                             .get_state(&entity_id)
                             .await
                             .ok_or("Failed to get state")?;
+                        ensure_available(&state.state)?;
 
-                        Ok(state.state == "on")
+                        Ok((state.is_on(), "On/Off".to_string()))
                     }
                 },
                 move |ctx, value| {
                     let entity_id = entity_id_2.clone();
+                    let instance = instance_2.clone();
                     async move {
-                        let hass = ctx
-                            .get_context::<PersistentHassConnection>()
-                            .await
-                            .ok_or("Failed to get PersistentHassConnection")?;
+                        let hass = get_online_connection(&ctx, instance.as_deref()).await?;
+                        if !hass.accept_press(&entity_id) {
+                            return Err(IgnoredPress.into());
+                        }
                         hass.call_service(
                             "light",
                             if value { "turn_on" } else { "turn_off" },
-                            Some(serde_json::json!({ "entity_id": entity_id })),
+                            Some(with_transition(
+                                serde_json::json!({ "entity_id": entity_id }),
+                                transition,
+                            )),
                         )
                         .await
                         .map_err(|e| e.to_string())?;
@@ -160,58 +641,291 @@ where
                     }
                 },
             )
-            .when_active("On/Off", Some(md_icons::filled::ICON_TOGGLE_ON)),
+            .when_active(Some(md_icons::filled::ICON_TOGGLE_ON)),
         )?;
         
-        // Add color buttons
-        let max_buttons = W::to_usize() * H::to_usize() - 2; // Reserve space for on/off and back buttons
-        let colors_to_show = std::cmp::min(COLORS.len(), max_buttons);
-        
-        for (index, &(color_name, (r, g, b))) in COLORS.iter().take(colors_to_show).enumerate() {
-            let button_index = index + 1; // Skip the first button (on/off)
-            let x = button_index % W::to_usize();
-            let y = button_index / W::to_usize();
-            
-            let entity_id = self.entity_id.clone();
-            let (h, s, v) = rgb_to_hsv(r, g, b);
-            
-            view.set_button(
-                x,
-                y,
-                ClickButton::new(
-                    color_name,
-                    None, // No icon, will use color as background
-                    move |ctx: PluginContext| {
-                        let entity_id = entity_id.clone();
-                        let h = h;
-                        let s = s;
-                        let v = v;
-                        async move {
-                            let hass = ctx
-                                .get_context::<PersistentHassConnection>()
+        match self.mode {
+            RgbMode::Color => {
+                // Lights with effects get a button for them, left of the brightness button
+                let has_effects =
+                    !light_effects(&context, &self.entity_id, self.instance.as_deref()).await.is_empty();
+
+                // Add color buttons
+                let reserved = if has_effects { 5 } else { 4 }; // On/off, effects, brightness, temperature and back buttons
+                let max_buttons = (W::to_usize() * H::to_usize()).saturating_sub(reserved);
+                let colors = palette_colors(&context).await;
+                let mode =
+                    light_color_mode(&context, &self.entity_id, self.instance.as_deref()).await;
+                let colors_to_show = std::cmp::min(colors.len(), max_buttons);
+
+                for (index, (color_name, (r, g, b))) in colors.into_iter().take(colors_to_show).enumerate() {
+                    let button_index = index + 1; // Skip the first button (on/off)
+                    let x = button_index % W::to_usize();
+                    let y = button_index / W::to_usize();
+
+                    let instance = self.instance.clone();
+                    let data = with_transition(
+                        color_data(mode, &self.entity_id, (r, g, b)),
+                        self.transition,
+                    );
+
+                    view.set_button(
+                        x,
+                        y,
+                        FeedbackButton::new(
+                            &color_name,
+                            None, // No icon, the theme uses the color as background
+                            move |ctx: PluginContext| {
+                                let instance = instance.clone();
+                                let data = data.clone();
+                                async move {
+                                    let hass = get_connection(&ctx, instance.as_deref()).await?;
+
+                                    // Turn on the light with the selected color
+                                    hass.call_service("light", "turn_on", Some(data))
+                                    .await
+                                    .map_err(|e| e.to_string())?;
+
+                                    Ok(())
+                                }
+                            },
+                        ).errors_only().with_theme(get_button_theme((r, g, b)))
+                    )?;
+                }
+
+                // Add the temperature mode button next to the back button
+                let button_index = W::to_usize() * H::to_usize() - 2;
+                view.set_navigation(
+                    button_index % W::to_usize(),
+                    button_index / W::to_usize(),
+                    PluginNavigation::new(RgbControllerPlugin::<W, H> {
+                        entity_id: self.entity_id.clone(),
+                        instance: self.instance.clone(),
+                        back_navigation: Some(PluginNavigation::new(self.clone())),
+                        mode: RgbMode::Temperature,
+                        transition: self.transition,
+                    }),
+                    "Temp",
+                    Some(md_icons::filled::ICON_WB_SUNNY),
+                )?;
+
+                // Add the brightness mode button before it
+                let button_index = W::to_usize() * H::to_usize() - 3;
+                view.set_navigation(
+                    button_index % W::to_usize(),
+                    button_index / W::to_usize(),
+                    PluginNavigation::new(RgbControllerPlugin::<W, H> {
+                        entity_id: self.entity_id.clone(),
+                        instance: self.instance.clone(),
+                        back_navigation: Some(PluginNavigation::new(self.clone())),
+                        mode: RgbMode::Brightness,
+                        transition: self.transition,
+                    }),
+                    "Dim",
+                    Some(md_icons::filled::ICON_BRIGHTNESS_MEDIUM),
+                )?;
+
+                if has_effects {
+                    let button_index = W::to_usize() * H::to_usize() - 4;
+                    view.set_navigation(
+                        button_index % W::to_usize(),
+                        button_index / W::to_usize(),
+                        PluginNavigation::new(RgbControllerPlugin::<W, H> {
+                            entity_id: self.entity_id.clone(),
+                            instance: self.instance.clone(),
+                            back_navigation: Some(PluginNavigation::new(self.clone())),
+                            mode: RgbMode::Effects,
+                            transition: self.transition,
+                        }),
+                        "Effects",
+                        Some(md_icons::filled::ICON_AUTO_AWESOME),
+                    )?;
+                }
+            }
+            RgbMode::Temperature => {
+                // Add color temperature buttons
+                let max_buttons = W::to_usize() * H::to_usize() - 2; // Reserve space for on/off and back buttons
+                let temps = supported_color_temps(&context, &self.entity_id, self.instance.as_deref()).await;
+
+                for (index, &(temp_name, kelvin, color)) in temps.iter().take(max_buttons).enumerate() {
+                    let button_index = index + 1; // Skip the first button (on/off)
+                    let x = button_index % W::to_usize();
+                    let y = button_index / W::to_usize();
+
+                    let entity_id = self.entity_id.clone();
+                    let instance = self.instance.clone();
+                    let transition = self.transition;
+
+                    view.set_button(
+                        x,
+                        y,
+                        FeedbackButton::new(
+                            temp_name,
+                            None, // No icon, the theme uses the color as background
+                            move |ctx: PluginContext| {
+                                let entity_id = entity_id.clone();
+                                let instance = instance.clone();
+                                async move {
+                                    let hass = get_connection(&ctx, instance.as_deref()).await?;
+
+                                    // Turn on the light with the selected color temperature
+                                    hass.call_service(
+                                        "light",
+                                        "turn_on",
+                                        Some(with_transition(
+                                            serde_json::json!({
+                                                "entity_id": entity_id,
+                                                "color_temp_kelvin": kelvin
+                                            }),
+                                            transition,
+                                        )),
+                                    )
+                                    .await
+                                    .map_err(|e| e.to_string())?;
+
+                                    Ok(())
+                                }
+                            },
+                        ).errors_only().with_theme(get_button_theme(color))
+                    )?;
+                }
+            }
+            RgbMode::Brightness => {
+                let brightness: SharedBrightness = Arc::new(Mutex::new(None));
+                let max_buttons = W::to_usize() * H::to_usize() - 2; // Reserve space for on/off and back buttons
+                let mut buttons = vec![
+                    brightness_step_button(
+                        self.entity_id.clone(),
+                        self.instance.clone(),
+                        brightness.clone(),
+                        -1,
+                        self.transition,
+                        "Dimmer",
+                        md_icons::filled::ICON_BRIGHTNESS_LOW,
+                    ),
+                    brightness_step_button(
+                        self.entity_id.clone(),
+                        self.instance.clone(),
+                        brightness.clone(),
+                        1,
+                        self.transition,
+                        "Brighter",
+                        md_icons::filled::ICON_BRIGHTNESS_HIGH,
+                    ),
+                ];
+
+                // Presets are evenly spaced in perceived brightness
+                for &(preset_name, level) in BRIGHTNESS_PRESETS {
+                    let entity_id = self.entity_id.clone();
+                    let instance = self.instance.clone();
+                    let brightness = brightness.clone();
+                    let transition = self.transition;
+                    let value = perceived_to_brightness(level);
+                    buttons.push(FeedbackButton::new(
+                        preset_name,
+                        Some(md_icons::filled::ICON_LIGHTBULB),
+                        move |ctx: PluginContext| {
+                            let entity_id = entity_id.clone();
+                            let instance = instance.clone();
+                            let brightness = brightness.clone();
+                            async move {
+                                let hass = get_connection(&ctx, instance.as_deref()).await?;
+                                hass.call_service(
+                                    "light",
+                                    "turn_on",
+                                    Some(with_transition(
+                                        serde_json::json!({
+                                            "entity_id": entity_id,
+                                            "brightness": value
+                                        }),
+                                        transition,
+                                    )),
+                                )
                                 .await
-                                .ok_or("Failed to get PersistentHassConnection")?;
-                            
-                            // Turn on the light with the selected color
-                            hass.call_service(
-                                "light",
-                                "turn_on",
-                                Some(serde_json::json!({
-                                    "entity_id": entity_id,
-                                    "hs_color": [h, s],
-                                    "brightness_pct": v
-                                })),
-                            )
-                            .await
-                            .map_err(|e| e.to_string())?;
-                            
-                            Ok(())
-                        }
-                    },
-                ).with_theme(get_button_theme((r, g, b)))
-            )?;
+                                .map_err(|e| e.to_string())?;
+
+                                if let Ok(mut current) = brightness.lock() {
+                                    *current = Some(value);
+                                }
+                                Ok(())
+                            }
+                        },
+                    ).errors_only());
+                }
+
+                for (index, button) in buttons.into_iter().take(max_buttons).enumerate() {
+                    let button_index = index + 1; // Skip the first button (on/off)
+                    view.set_button(
+                        button_index % W::to_usize(),
+                        button_index / W::to_usize(),
+                        button,
+                    )?;
+                }
+            }
+            RgbMode::Effects => {
+                // Add effect buttons, highlighting the running effect
+                let max_buttons = W::to_usize() * H::to_usize() - 2; // Reserve space for on/off and back buttons
+                let effects = light_effects(&context, &self.entity_id, self.instance.as_deref()).await;
+
+                for (index, effect) in effects.into_iter().take(max_buttons).enumerate() {
+                    let button_index = index + 1; // Skip the first button (on/off)
+                    let x = button_index % W::to_usize();
+                    let y = button_index / W::to_usize();
+
+                    let entity_id = self.entity_id.clone();
+                    let instance = self.instance.clone();
+                    let entity_id_2 = entity_id.clone();
+                    let instance_2 = instance.clone();
+                    let effect_2 = effect.clone();
+
+                    view.set_button(
+                        x,
+                        y,
+                        ToggleButton::new(
+                            effect.clone(),
+                            Some(md_icons::filled::ICON_AUTO_AWESOME),
+                            move |ctx: PluginContext| {
+                                let entity_id = entity_id.clone();
+                                let instance = instance.clone();
+                                let effect = effect.clone();
+                                async move {
+                                    let hass = get_connection(&ctx, instance.as_deref()).await?;
+                                    let state = hass
+                                        .get_state(&entity_id)
+                                        .await
+                                        .ok_or("Failed to get state")?;
+                                    Ok(state.is_on()
+                                        && state.attr::<String>("effect").as_deref() == Some(effect.as_str()))
+                                }
+                            },
+                            move |ctx, _value| {
+                                let entity_id = entity_id_2.clone();
+                                let instance = instance_2.clone();
+                                let effect = effect_2.clone();
+                                async move {
+                                    let hass = get_connection(&ctx, instance.as_deref()).await?;
+
+                                    // Turn on the light with the selected effect
+                                    hass.call_service(
+                                        "light",
+                                        "turn_on",
+                                        Some(serde_json::json!({
+                                            "entity_id": entity_id,
+                                            "effect": effect
+                                        })),
+                                    )
+                                    .await
+                                    .map_err(|e| e.to_string())?;
+
+                                    Ok(())
+                                }
+                            },
+                        ),
+                    )?;
+                }
+            }
         }
-        
+
         // Add back button
         if let Some(back_navigation) = &self.back_navigation {
             view.set_navigation(
@@ -226,3 +940,159 @@ where
         Ok(Box::new(view))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Asserts that two hue/saturation/value triples are within rounding error.
+    fn assert_hsv(actual: (f32, f32, f32), expected: (f32, f32, f32)) {
+        let close = |a: f32, b: f32| (a - b).abs() < 0.01;
+        assert!(
+            close(actual.0, expected.0) && close(actual.1, expected.1) && close(actual.2, expected.2),
+            "expected {:?}, got {:?}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn perceived_to_brightness_clamps_to_the_lights_range() {
+        assert_eq!(perceived_to_brightness(0.0), 1);
+        assert_eq!(perceived_to_brightness(-0.5), 1);
+        assert_eq!(perceived_to_brightness(1.0), 255);
+        assert_eq!(perceived_to_brightness(1.5), 255);
+    }
+
+    #[test]
+    fn brightness_round_trips_through_perceived_levels() {
+        assert_eq!(brightness_to_perceived(0), 0.0);
+        assert_eq!(brightness_to_perceived(255), 1.0);
+        for step in 1..=BRIGHTNESS_STEPS {
+            let level = step as f32 / BRIGHTNESS_STEPS as f32;
+            let perceived = brightness_to_perceived(perceived_to_brightness(level));
+            assert!((perceived - level).abs() < 0.02, "level {} came back as {}", level, perceived);
+        }
+    }
+
+    #[test]
+    fn step_brightness_moves_by_perceived_steps() {
+        let lowest = step_brightness(1, -1);
+        assert_eq!(lowest, perceived_to_brightness(1.0 / BRIGHTNESS_STEPS as f32));
+        assert_eq!(step_brightness(lowest, -3), lowest);
+        assert_eq!(step_brightness(255, 1), 255);
+        assert_eq!(step_brightness(step_brightness(255, -1), 1), 255);
+        assert_eq!(step_brightness(1, BRIGHTNESS_STEPS as i8), 255);
+
+        // Gamma correction makes steps larger at the top of the range
+        let top_step = 255 - step_brightness(255, -1);
+        let bottom_step = step_brightness(lowest, 1) - lowest;
+        assert!(top_step > bottom_step);
+    }
+
+    #[test]
+    fn rgb_to_hsv_primaries() {
+        assert_hsv(rgb_to_hsv(255, 0, 0), (0.0, 100.0, 100.0));
+        assert_hsv(rgb_to_hsv(0, 255, 0), (120.0, 100.0, 100.0));
+        assert_hsv(rgb_to_hsv(0, 0, 255), (240.0, 100.0, 100.0));
+    }
+
+    #[test]
+    fn rgb_to_hsv_secondaries() {
+        assert_hsv(rgb_to_hsv(255, 255, 0), (60.0, 100.0, 100.0));
+        assert_hsv(rgb_to_hsv(0, 255, 255), (180.0, 100.0, 100.0));
+        assert_hsv(rgb_to_hsv(255, 0, 255), (300.0, 100.0, 100.0));
+    }
+
+    #[test]
+    fn rgb_to_hsv_grays() {
+        assert_hsv(rgb_to_hsv(0, 0, 0), (0.0, 0.0, 0.0));
+        assert_hsv(rgb_to_hsv(128, 128, 128), (0.0, 0.0, 128.0 / 255.0 * 100.0));
+        assert_hsv(rgb_to_hsv(255, 255, 255), (0.0, 0.0, 100.0));
+    }
+
+    #[test]
+    fn rgb_to_hsv_wraps_reds_with_blue() {
+        assert_hsv(rgb_to_hsv(255, 0, 128), (329.88, 100.0, 100.0));
+        let (h, _, _) = rgb_to_hsv(255, 0, 1);
+        assert!((0.0..360.0).contains(&h), "hue {} out of range", h);
+        assert!(h > 359.0);
+    }
+
+    #[test]
+    fn rgb_to_hsv_hue_is_always_in_range() {
+        for r in (0..=255).step_by(15) {
+            for g in (0..=255).step_by(15) {
+                for b in (0..=255).step_by(15) {
+                    let (h, s, v) = rgb_to_hsv(r, g, b);
+                    assert!((0.0..360.0).contains(&h), "hue {} for ({}, {}, {})", h, r, g, b);
+                    assert!((0.0..=100.0).contains(&s));
+                    assert!((0.0..=100.0).contains(&v));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn with_transition_adds_fade_time() {
+        let data = serde_json::json!({ "entity_id": "light.desk" });
+        assert_eq!(with_transition(data.clone(), None), data);
+        assert_eq!(
+            with_transition(data, Some(1.5)),
+            serde_json::json!({ "entity_id": "light.desk", "transition": 1.5 })
+        );
+    }
+
+    #[test]
+    fn hs_to_rgb_round_trips() {
+        for color in [(255, 0, 0), (0, 255, 0), (0, 0, 255), (255, 165, 0), (255, 0, 128)] {
+            let (h, s, _) = rgb_to_hsv(color.0, color.1, color.2);
+            assert_eq!(hs_to_rgb(h, s), color);
+        }
+    }
+
+    #[test]
+    fn color_mode_prefers_the_most_white_channels() {
+        let modes = |modes: serde_json::Value| serde_json::json!({ "supported_color_modes": modes });
+        assert!(color_mode(&modes(serde_json::json!(["hs", "rgbw", "rgbww"]))) == ColorMode::Rgbww);
+        assert!(color_mode(&modes(serde_json::json!(["rgbw", "hs"]))) == ColorMode::Rgbw);
+        assert!(color_mode(&modes(serde_json::json!(["hs", "color_temp"]))) == ColorMode::Hs);
+        assert!(color_mode(&serde_json::json!({})) == ColorMode::Hs);
+    }
+
+    #[test]
+    fn rgb_to_rgbw_moves_shared_white() {
+        assert_eq!(rgb_to_rgbw(255, 255, 255), [0, 0, 0, 255]);
+        assert_eq!(rgb_to_rgbw(255, 200, 180), [75, 20, 0, 180]);
+        assert_eq!(rgb_to_rgbw(255, 0, 0), [255, 0, 0, 0]);
+        assert_eq!(rgb_to_rgbw(0, 128, 255), [0, 128, 255, 0]);
+    }
+
+    #[test]
+    fn rgb_to_rgbww_splits_warm_and_cool_white() {
+        assert_eq!(rgb_to_rgbww(255, 255, 255), [0, 0, 0, 255, 255]);
+        assert_eq!(rgb_to_rgbww(255, 200, 180), [75, 20, 0, 0, 180]);
+        assert_eq!(rgb_to_rgbww(180, 200, 255), [0, 20, 75, 180, 0]);
+        assert_eq!(rgb_to_rgbww(0, 255, 0), [0, 255, 0, 0, 0]);
+    }
+
+    #[test]
+    fn color_data_uses_the_color_mode() {
+        assert_eq!(
+            color_data(ColorMode::Hs, "light.desk", (255, 0, 0)),
+            serde_json::json!({ "entity_id": "light.desk", "hs_color": [0.0, 100.0], "brightness_pct": 100.0 })
+        );
+        assert_eq!(
+            color_data(ColorMode::Rgbw, "light.desk", (255, 255, 255)),
+            serde_json::json!({ "entity_id": "light.desk", "rgbw_color": [0, 0, 0, 255], "brightness_pct": 100.0 })
+        );
+        assert_eq!(
+            color_data(ColorMode::Rgbww, "light.desk", (255, 255, 255)),
+            serde_json::json!({
+                "entity_id": "light.desk",
+                "rgbww_color": [0, 0, 0, 255, 255],
+                "brightness_pct": 100.0
+            })
+        );
+    }
+}
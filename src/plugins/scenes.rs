@@ -0,0 +1,150 @@
+//! Scene list plugin for HomeAssistant.
+//!
+//! This plugin lists every scene (or script) known to HomeAssistant when it
+//! is opened, so new scenes show up without changing the configuration.
+
+use streamdeck_oxide::{
+    generic_array::ArrayLength, md_icons, plugins::{Plugin, PluginContext, PluginNavigation}, view::customizable::{CustomizableView}, View
+};
+
+use crate::{buttons::FeedbackButton, plugins::get_connection};
+
+/// Plugin listing the scenes or scripts of a HomeAssistant instance.
+///
+/// Each entity gets a button activating it with `turn_on`. When there are
+/// more entities than keys, "Previous" and "Next" buttons page through them.
+#[derive(Clone)]
+pub struct SceneListPlugin<W: ArrayLength, H: ArrayLength> {
+    /// Optional navigation to return to when "Back" is pressed
+    pub(crate) back_navigation: Option<PluginNavigation<W, H>>,
+    /// The domain to list, "scene" or "script"
+    pub(crate) domain: String,
+    /// Optional name of the HomeAssistant instance the entities belong to
+    pub(crate) instance: Option<String>,
+    /// Zero-based page shown when there are more entities than keys
+    pub(crate) page: usize,
+}
+
+impl<W: ArrayLength, H: ArrayLength> SceneListPlugin<W, H> {
+    /// Returns a copy of this plugin showing another page.
+    fn with_page(&self, page: usize) -> Self {
+        Self {
+            page,
+            ..self.clone()
+        }
+    }
+}
+
+/// Implementation of the StreamDeck Plugin trait for SceneListPlugin.
+#[async_trait::async_trait]
+impl<W, H> Plugin<W, H> for SceneListPlugin<W, H>
+where
+    W: ArrayLength,
+    H: ArrayLength,
+{
+    fn name(&self) -> &'static str {
+        "SceneListPlugin"
+    }
+
+    async fn get_view(
+        &self,
+        context: PluginContext,
+    ) -> Result<
+        Box<dyn View<W, H, PluginContext, PluginNavigation<W, H>>>,
+        Box<dyn std::error::Error>,
+    > {
+        let mut view = CustomizableView::new();
+
+        let hass = get_connection(&context, self.instance.as_deref()).await?;
+        let prefix = format!("{}.", self.domain);
+        let entities: Vec<(String, Option<String>)> = hass
+            .list_entities()
+            .await
+            .into_iter()
+            .filter(|(entity_id, _)| entity_id.starts_with(&prefix))
+            .collect();
+
+        // Reserve the last slot for the back button, and two more for paging if needed
+        let keys = W::to_usize() * H::to_usize();
+        let available = keys - usize::from(self.back_navigation.is_some());
+        let paginated = entities.len() > available && available > 2;
+        let page_size = if paginated { available - 2 } else { available };
+        let page_count = match page_size {
+            0 => 1,
+            page_size => entities.len().div_ceil(page_size).max(1),
+        };
+        let page = self.page.min(page_count - 1);
+
+        for (index, (entity_id, friendly_name)) in entities
+            .into_iter()
+            .skip(page * page_size)
+            .take(page_size)
+            .enumerate()
+        {
+            let name = friendly_name.unwrap_or_else(|| entity_id.clone());
+            let domain = self.domain.clone();
+            let instance = self.instance.clone();
+            view.set_button(
+                index % W::to_usize(),
+                index / W::to_usize(),
+                FeedbackButton::new(
+                    name,
+                    Some(md_icons::filled::ICON_PALETTE),
+                    move |ctx: PluginContext| {
+                        let entity_id = entity_id.clone();
+                        let domain = domain.clone();
+                        let instance = instance.clone();
+                        async move {
+                            let hass = get_connection(&ctx, instance.as_deref()).await?;
+                            hass.call_service(
+                                &domain,
+                                "turn_on",
+                                Some(serde_json::json!({ "entity_id": entity_id })),
+                            )
+                            .await
+                            .map_err(|e| e.to_string())?;
+                            Ok(())
+                        }
+                    },
+                )
+                .errors_only(),
+            )?;
+        }
+
+        if paginated {
+            let previous_index = available - 2;
+            let next_index = available - 1;
+            if page > 0 {
+                view.set_navigation(
+                    previous_index % W::to_usize(),
+                    previous_index / W::to_usize(),
+                    PluginNavigation::new(self.with_page(page - 1)),
+                    "Previous",
+                    Some(md_icons::filled::ICON_NAVIGATE_BEFORE),
+                )?;
+            }
+            if page + 1 < page_count {
+                view.set_navigation(
+                    next_index % W::to_usize(),
+                    next_index / W::to_usize(),
+                    PluginNavigation::new(self.with_page(page + 1)),
+                    "Next",
+                    Some(md_icons::filled::ICON_NAVIGATE_NEXT),
+                )?;
+            }
+        }
+
+        // Add back button
+        if let Some(back_navigation) = &self.back_navigation {
+            view.set_navigation(
+                W::to_usize() - 1,
+                H::to_usize() - 1,
+                back_navigation.clone(),
+                "Back",
+                Some(md_icons::filled::ICON_ARROW_BACK),
+            )?;
+        }
+
+        Ok(Box::new(view))
+    }
+}
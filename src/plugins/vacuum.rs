@@ -0,0 +1,215 @@
+//! Vacuum controller plugin for HomeAssistant.
+//!
+//! This plugin provides start/pause, stop, return-to-base and locate buttons
+//! for robot vacuums in HomeAssistant.
+
+use streamdeck_oxide::{
+    generic_array::ArrayLength, md_icons, plugins::{Plugin, PluginContext, PluginNavigation}, view::customizable::{CustomizableView}, View
+};
+
+use crate::{
+    buttons::{FeedbackButton, IgnoredPress, LabelButton, LabelToggleButton},
+    hass::EntityState,
+    plugins::{get_connection, get_online_connection},
+};
+
+/// Plugin for controlling robot vacuums in HomeAssistant.
+///
+/// This plugin displays a start/pause toggle reflecting whether the vacuum
+/// is cleaning, buttons for the other vacuum services, and the battery level.
+#[derive(Clone)]
+pub struct VacuumPlugin<W: ArrayLength, H: ArrayLength> {
+    /// Optional navigation to return to when "Back" is pressed
+    pub(crate) back_navigation: Option<PluginNavigation<W, H>>,
+    /// The entity ID of the vacuum to control
+    pub(crate) entity_id: String,
+    /// Optional name of the HomeAssistant instance the entity belongs to
+    pub(crate) instance: Option<String>,
+}
+
+/// Creates a button that calls a vacuum service when clicked.
+///
+/// # Arguments
+///
+/// * `entity_id` - The entity ID of the vacuum
+/// * `instance` - Optional name of the HomeAssistant instance
+/// * `text` - The button label
+/// * `icon` - The button icon
+/// * `service` - The `vacuum` domain service to call
+fn service_button(
+    entity_id: String,
+    instance: Option<String>,
+    text: &str,
+    icon: &'static str,
+    service: &'static str,
+) -> FeedbackButton<PluginContext> {
+    FeedbackButton::new(text, Some(icon), move |ctx: PluginContext| {
+        let entity_id = entity_id.clone();
+        let instance = instance.clone();
+        async move {
+            let hass = get_connection(&ctx, instance.as_deref()).await?;
+            hass.call_service(
+                "vacuum",
+                service,
+                Some(serde_json::json!({ "entity_id": entity_id })),
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+            Ok(())
+        }
+    })
+    .errors_only()
+}
+
+/// Implementation of the StreamDeck Plugin trait for VacuumPlugin.
+#[async_trait::async_trait]
+impl<W, H> Plugin<W, H> for VacuumPlugin<W, H>
+where
+    W: ArrayLength,
+    H: ArrayLength,
+{
+    fn name(&self) -> &'static str {
+        "VacuumPlugin"
+    }
+
+    async fn get_view(
+        &self,
+        _context: PluginContext,
+    ) -> Result<
+        Box<dyn View<W, H, PluginContext, PluginNavigation<W, H>>>,
+        Box<dyn std::error::Error>,
+    > {
+        let mut view = CustomizableView::new();
+        let position = |index: usize| (index % W::to_usize(), index / W::to_usize());
+
+        // Add the start/pause toggle button at the top left
+        let entity_id = self.entity_id.clone();
+        let instance = self.instance.clone();
+        let entity_id_2 = entity_id.clone();
+        let instance_2 = instance.clone();
+        let (x, y) = position(0);
+        view.set_button(
+            x,
+            y,
+            LabelToggleButton::new(
+                "Start",
+                Some(md_icons::filled::ICON_PLAY_ARROW),
+                move |ctx: PluginContext| {
+                    let entity_id = entity_id.clone();
+                    let instance = instance.clone();
+                    async move {
+                        let hass = get_online_connection(&ctx, instance.as_deref()).await?;
+                        let state = hass
+                            .get_state(&entity_id)
+                            .await
+                            .ok_or("Failed to get state")?;
+
+                        let cleaning = state.state == "cleaning";
+                        Ok((cleaning, if cleaning { "Pause" } else { "Start" }.to_string()))
+                    }
+                },
+                move |ctx, value| {
+                    let entity_id = entity_id_2.clone();
+                    let instance = instance_2.clone();
+                    async move {
+                        let hass = get_online_connection(&ctx, instance.as_deref()).await?;
+                        if !hass.accept_press(&entity_id) {
+                            return Err(IgnoredPress.into());
+                        }
+                        hass.call_service(
+                            "vacuum",
+                            if value { "start" } else { "pause" },
+                            Some(serde_json::json!({ "entity_id": entity_id })),
+                        )
+                        .await
+                        .map_err(|e| e.to_string())?;
+                        Ok(())
+                    }
+                },
+            )
+            .when_active(Some(md_icons::filled::ICON_PAUSE))
+            .with_active_text("Pause"),
+        )?;
+
+        let (x, y) = position(1);
+        view.set_button(
+            x,
+            y,
+            service_button(
+                self.entity_id.clone(),
+                self.instance.clone(),
+                "Stop",
+                md_icons::filled::ICON_STOP,
+                "stop",
+            ),
+        )?;
+
+        let (x, y) = position(2);
+        view.set_button(
+            x,
+            y,
+            service_button(
+                self.entity_id.clone(),
+                self.instance.clone(),
+                "Dock",
+                md_icons::filled::ICON_HOME,
+                "return_to_base",
+            ),
+        )?;
+
+        let (x, y) = position(3);
+        view.set_button(
+            x,
+            y,
+            service_button(
+                self.entity_id.clone(),
+                self.instance.clone(),
+                "Locate",
+                md_icons::filled::ICON_MY_LOCATION,
+                "locate",
+            ),
+        )?;
+
+        // Add the battery level display
+        let entity_id = self.entity_id.clone();
+        let instance = self.instance.clone();
+        let (x, y) = position(4);
+        view.set_button(
+            x,
+            y,
+            LabelButton::new(
+                "Battery",
+                Some(md_icons::filled::ICON_BATTERY_STD),
+                move |ctx: PluginContext| {
+                    let entity_id = entity_id.clone();
+                    let instance = instance.clone();
+                    async move {
+                        let hass = get_connection(&ctx, instance.as_deref()).await?;
+                        let state = hass
+                            .get_state(&entity_id)
+                            .await
+                            .ok_or("Failed to get state")?;
+
+                        Ok(match state.attr::<f64>("battery_level") {
+                            Some(level) => format!("{:.0}%", level),
+                            None => "Battery".to_string(),
+                        })
+                    }
+                },
+            ),
+        )?;
+
+        // Add back button
+        if let Some(back_navigation) = &self.back_navigation {
+            view.set_navigation(
+                W::to_usize() - 1,
+                H::to_usize() - 1,
+                back_navigation.clone(),
+                "Back",
+                Some(md_icons::filled::ICON_ARROW_BACK),
+            )?;
+        }
+
+        Ok(Box::new(view))
+    }
+}
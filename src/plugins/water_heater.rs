@@ -0,0 +1,257 @@
+//! Water heater plugin for HomeAssistant.
+//!
+//! This plugin provides target temperature buttons, the current temperature
+//! and the operation mode of water heaters such as boilers in HomeAssistant.
+
+use std::sync::{Arc, Mutex};
+
+use streamdeck_oxide::{
+    generic_array::ArrayLength,
+    md_icons,
+    plugins::{Plugin, PluginContext, PluginNavigation},
+    view::customizable::CustomizableView,
+    View,
+};
+
+use crate::{
+    buttons::LabelButton,
+    hass::EntityState,
+    plugins::{
+        climate::{step_button, Setpoint, SharedTarget, TargetButton, TemperatureUnit},
+        get_connection,
+    },
+};
+
+/// Plugin for controlling water heaters in HomeAssistant.
+///
+/// This plugin displays minus and plus buttons around the target temperature,
+/// like the climate plugin, with the current temperature and operation mode
+/// below.
+#[derive(Clone)]
+pub struct WaterHeaterPlugin<W: ArrayLength, H: ArrayLength> {
+    /// Optional navigation to return to when "Back" is pressed
+    pub(crate) back_navigation: Option<PluginNavigation<W, H>>,
+    /// The entity ID of the water heater to control
+    pub(crate) entity_id: String,
+    /// Optional name of the HomeAssistant instance the entity belongs to
+    pub(crate) instance: Option<String>,
+    /// Degrees to add or subtract per press, in the water heater's unit
+    pub(crate) step: Option<f64>,
+}
+
+/// Returns the bounds used when a water heater doesn't report `min_temp`/`max_temp`.
+///
+/// These are HomeAssistant's defaults of 110-140 °F, with the Celsius bounds
+/// rounded to whole degrees.
+fn default_bounds(unit: TemperatureUnit) -> (f64, f64) {
+    match unit {
+        TemperatureUnit::Celsius => (43.0, 60.0),
+        TemperatureUnit::Fahrenheit => (110.0, 140.0),
+    }
+}
+
+/// Returns the operation mode following the current one, wrapping around.
+///
+/// # Arguments
+///
+/// * `modes` - The water heater's `operation_list`
+/// * `current` - The current operation mode
+///
+/// # Example
+///
+/// ```
+/// # use streamdeck_homeassistant::plugins::water_heater::next_operation_mode;
+/// let modes = vec!["eco".to_string(), "electric".to_string(), "off".to_string()];
+/// assert_eq!(next_operation_mode(&modes, "eco"), Some("electric"));
+/// assert_eq!(next_operation_mode(&modes, "off"), Some("eco"));
+/// assert_eq!(next_operation_mode(&modes, "unknown"), Some("eco"));
+/// assert_eq!(next_operation_mode(&[], "eco"), None);
+/// ```
+pub fn next_operation_mode<'a>(modes: &'a [String], current: &str) -> Option<&'a str> {
+    let next = match modes.iter().position(|mode| mode == current) {
+        Some(index) => (index + 1) % modes.len(),
+        None => 0,
+    };
+    modes.get(next).map(String::as_str)
+}
+
+/// Implementation of the StreamDeck Plugin trait for WaterHeaterPlugin.
+#[async_trait::async_trait]
+impl<W, H> Plugin<W, H> for WaterHeaterPlugin<W, H>
+where
+    W: ArrayLength,
+    H: ArrayLength,
+{
+    fn name(&self) -> &'static str {
+        "WaterHeaterPlugin"
+    }
+
+    async fn get_view(
+        &self,
+        context: PluginContext,
+    ) -> Result<
+        Box<dyn View<W, H, PluginContext, PluginNavigation<W, H>>>,
+        Box<dyn std::error::Error>,
+    > {
+        let mut view = CustomizableView::new();
+
+        let hass = get_connection(&context, self.instance.as_deref()).await?;
+        let state = hass
+            .get_state(&self.entity_id)
+            .await
+            .ok_or("Failed to get state")?;
+
+        // Water heaters rarely report their unit, so fall back to HomeAssistant's
+        let symbol = match state.attr::<String>("temperature_unit") {
+            Some(symbol) => Some(symbol),
+            None => hass.temperature_unit().await,
+        };
+        let unit = symbol
+            .as_deref()
+            .and_then(TemperatureUnit::from_symbol)
+            .ok_or_else(|| format!("Unknown temperature unit for {}", self.entity_id))?;
+        let setpoint =
+            Setpoint::resolve("water_heater", &state, unit, self.step, default_bounds(unit));
+
+        let target: SharedTarget = Arc::new(Mutex::new(None));
+
+        // Minus, target temperature and plus along the top row
+        view.set_button(
+            0,
+            0,
+            step_button(
+                self.entity_id.clone(),
+                self.instance.clone(),
+                target.clone(),
+                setpoint,
+                -1.0,
+                "Down",
+                md_icons::filled::ICON_REMOVE,
+            ),
+        )?;
+        view.set_button(
+            1,
+            0,
+            TargetButton {
+                entity_id: self.entity_id.clone(),
+                instance: self.instance.clone(),
+                target: target.clone(),
+                setpoint,
+            },
+        )?;
+        view.set_button(
+            2,
+            0,
+            step_button(
+                self.entity_id.clone(),
+                self.instance.clone(),
+                target,
+                setpoint,
+                1.0,
+                "Up",
+                md_icons::filled::ICON_ADD,
+            ),
+        )?;
+
+        // Operation mode below, for water heaters that have several
+        if state.attr::<Vec<String>>("operation_list").is_some_and(|modes| modes.len() > 1) {
+            let entity_id = self.entity_id.clone();
+            let instance = self.instance.clone();
+            let entity_id_2 = entity_id.clone();
+            let instance_2 = instance.clone();
+            view.set_button(
+                0,
+                1,
+                LabelButton::new(
+                    "Mode",
+                    Some(md_icons::filled::ICON_LIST),
+                    move |ctx: PluginContext| {
+                        let entity_id = entity_id.clone();
+                        let instance = instance.clone();
+                        async move {
+                            let hass = get_connection(&ctx, instance.as_deref()).await?;
+                            let state = hass
+                                .get_state(&entity_id)
+                                .await
+                                .ok_or("Failed to get state")?;
+                            Ok(state
+                                .attr::<String>("operation_mode")
+                                .unwrap_or(state.state))
+                        }
+                    },
+                )
+                .on_click(move |ctx: PluginContext| {
+                    let entity_id = entity_id_2.clone();
+                    let instance = instance_2.clone();
+                    async move {
+                        let hass = get_connection(&ctx, instance.as_deref()).await?;
+                        let state = hass
+                            .get_state(&entity_id)
+                            .await
+                            .ok_or("Failed to get state")?;
+                        let modes: Vec<String> = state.attr("operation_list").unwrap_or_default();
+                        let current = state
+                            .attr::<String>("operation_mode")
+                            .unwrap_or(state.state);
+                        let mode = next_operation_mode(&modes, &current)
+                            .ok_or("Water heater has no operation modes")?;
+                        hass.call_service(
+                            "water_heater",
+                            "set_operation_mode",
+                            Some(serde_json::json!({
+                                "entity_id": entity_id,
+                                "operation_mode": mode
+                            })),
+                        )
+                        .await
+                        .map_err(|e| e.to_string())?;
+
+                        // Refresh the cache so the label shows the new mode right away
+                        hass.fetch_states().await?;
+                        Ok(())
+                    }
+                }),
+            )?;
+        }
+
+        // Current water temperature next to it
+        let entity_id = self.entity_id.clone();
+        let instance = self.instance.clone();
+        view.set_button(
+            1,
+            1,
+            LabelButton::new(
+                "-",
+                Some(md_icons::filled::ICON_WATER_DROP),
+                move |ctx: PluginContext| {
+                    let entity_id = entity_id.clone();
+                    let instance = instance.clone();
+                    async move {
+                        let hass = get_connection(&ctx, instance.as_deref()).await?;
+                        let state = hass
+                            .get_state(&entity_id)
+                            .await
+                            .ok_or("Failed to get state")?;
+                        Ok(match state.attr::<f64>("current_temperature") {
+                            Some(current) => setpoint.format(current),
+                            None => "-".to_string(),
+                        })
+                    }
+                },
+            ),
+        )?;
+
+        // Add back button
+        if let Some(back_navigation) = &self.back_navigation {
+            view.set_navigation(
+                W::to_usize() - 1,
+                H::to_usize() - 1,
+                back_navigation.clone(),
+                "Back",
+                Some(md_icons::filled::ICON_ARROW_BACK),
+            )?;
+        }
+
+        Ok(Box::new(view))
+    }
+}
@@ -0,0 +1,38 @@
+//! Weather display helpers.
+//!
+//! Maps HomeAssistant weather conditions to icons for the weather button.
+
+use streamdeck_oxide::md_icons;
+
+/// Returns the icon matching a HomeAssistant weather condition.
+///
+/// Unknown conditions get a question mark icon.
+///
+/// # Arguments
+///
+/// * `condition` - The state of a `weather` entity, e.g. "partlycloudy"
+///
+/// # Example
+///
+/// ```
+/// # use streamdeck_homeassistant::weather::condition_icon;
+/// # use streamdeck_oxide::md_icons;
+/// assert_eq!(condition_icon("sunny"), md_icons::filled::ICON_WB_SUNNY);
+/// ```
+pub fn condition_icon(condition: &str) -> &'static str {
+    match condition {
+        "sunny" => md_icons::filled::ICON_WB_SUNNY,
+        "clear-night" => md_icons::filled::ICON_NIGHTS_STAY,
+        "partlycloudy" => md_icons::filled::ICON_WB_CLOUDY,
+        "cloudy" | "fog" => md_icons::filled::ICON_CLOUD,
+        "rainy" => md_icons::filled::ICON_WATER_DROP,
+        "pouring" => md_icons::filled::ICON_UMBRELLA,
+        "lightning" => md_icons::filled::ICON_BOLT,
+        "lightning-rainy" => md_icons::filled::ICON_THUNDERSTORM,
+        "snowy" | "snowy-rainy" => md_icons::filled::ICON_AC_UNIT,
+        "hail" => md_icons::filled::ICON_GRAIN,
+        "windy" | "windy-variant" => md_icons::filled::ICON_AIR,
+        "exceptional" => md_icons::filled::ICON_WARNING,
+        _ => md_icons::filled::ICON_HELP,
+    }
+}